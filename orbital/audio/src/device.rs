@@ -0,0 +1,111 @@
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// where sound comes from and goes to
+/////////////////////////////////////////////////////////////////////////////////////////
+
+//
+// the audio manifest picks them:
+//
+//     input = none             # nothing is captured
+//     input = wav              # a wav file stands in for the microphone, played over and over
+//     input.file = ../public/sounds/hello.wav
+//
+//     output = null            # the mix is thrown away, for running without speakers
+//     output = wav             # the mix is written to a wav file, to listen to or check afterwards
+//     output.file = ../public/recordings/audio.wav
+//
+
+use service::Manifest;
+use service::audio::Samples;
+
+use crate::wav;
+
+pub trait AudioSource: Send {
+    /// How many frames a second it reads
+    fn rate(&self) -> u32;
+    /// The next count frames, or none if there is nothing more
+    fn read(&mut self, count: usize) -> Result<Option<Samples>, String>;
+}
+
+pub trait AudioSink: Send {
+    /// Play some sound
+    fn write(&mut self, samples: &Samples) -> Result<(), String>;
+}
+
+/// Open the input the manifest asks for, if any
+pub fn input(manifest: &Manifest) -> Result<Option<Box<dyn AudioSource>>, String> {
+    match manifest.get("input").unwrap_or("none") {
+        "none" => Ok(None),
+        "wav" => {
+            let file = manifest.get("input.file").ok_or("a wav input needs an input.file")?;
+            Ok(Some(Box::new(WavSource::open(file, manifest.get_or("input.loop", true))?)))
+        },
+        other => Err(format!("unknown audio input {}", other)),
+    }
+}
+
+/// Open the output the manifest asks for, at the rate and channels the mixer uses
+pub fn output(manifest: &Manifest, rate: u32, channels: u16) -> Result<Box<dyn AudioSink>, String> {
+    match manifest.get("output").unwrap_or("null") {
+        "null" => Ok(Box::new(Null)),
+        "wav" => {
+            let file = manifest.get("output.file").ok_or("a wav output needs an output.file")?;
+            Ok(Box::new(wav::Writer::create(file, rate, channels)?))
+        },
+        other => Err(format!("unknown audio output {}", other)),
+    }
+}
+
+/// A wav file read a chunk at a time, in its own rate and channels
+pub struct WavSource {
+    samples: Samples,
+    at: usize,
+    looping: bool,
+}
+
+impl WavSource {
+    pub fn open(file: &str, looping: bool) -> Result<WavSource, String> {
+        let samples = wav::load(file)?;
+        if samples.is_empty() {
+            return Err(format!("{} has no sound in it", file));
+        }
+        Ok(WavSource { samples: samples, at: 0, looping: looping })
+    }
+}
+
+impl AudioSource for WavSource {
+    fn rate(&self) -> u32 { self.samples.rate }
+
+    fn read(&mut self, count: usize) -> Result<Option<Samples>, String> {
+        let channels = self.samples.channels as usize;
+        let mut frames = Vec::with_capacity(count * channels);
+        while frames.len() < count * channels {
+            if self.at >= self.samples.len() {
+                if !self.looping {
+                    break;
+                }
+                self.at = 0;
+            }
+            let take = (count - frames.len() / channels).min(self.samples.len() - self.at);
+            frames.extend_from_slice(&self.samples.frames[self.at * channels..(self.at + take) * channels]);
+            self.at += take;
+        }
+        if frames.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Samples::new(self.samples.rate, self.samples.channels, frames)))
+    }
+}
+
+/// Throws sound away
+pub struct Null;
+
+impl AudioSink for Null {
+    fn write(&mut self, _samples: &Samples) -> Result<(), String> { Ok(()) }
+}
+
+impl AudioSink for wav::Writer {
+    fn write(&mut self, samples: &Samples) -> Result<(), String> {
+        wav::Writer::write(self, samples)
+    }
+}
@@ -0,0 +1,235 @@
+
+//
+// Audio: captures sound onto /audio/in and plays everything sent to /audio/out, mixed together
+//
+// native services send sound as Message::Audio on /audio/out, each on its own stream. scripts and wasm apps, which only
+// send text, ask for sounds on /audio:
+//
+//     play <file>          play a wav file from the sounds folder, ie "play ding.wav"
+//     tone <hertz> <ms>    play a beep
+//     stop                 stop the sounds it asked for
+//     volume <0 to 1>      how loud its sounds are played from now on
+//     status               say what is playing on /audio/status
+//
+// a command may be tagged with who sent it as "play ding.wav @owner", the same as on /display, so that one app stopping
+// its sounds leaves the others playing - untagged commands all count as one app. input and output are set in
+// public/audio.manifest, see device.rs, along with the sounds folder - public/sounds unless it says otherwise. apps can
+// only play what is in there.
+//
+
+use crossbeam::channel::*;
+use service::*;
+use service::audio::*;
+use service::interface::quote;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub mod device;
+pub mod mixer;
+pub mod wav;
+
+use device::{AudioSink, AudioSource};
+use mixer::Mixer;
+
+pub const AUDIO: &str = "/audio";
+pub const STATUS: &str = "/audio/status";
+
+#[derive(Clone)]
+pub struct Audio {
+    manifest: String,
+}
+
+impl Audio {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/audio.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for Audio {
+    fn name(&self) -> &str { "Audio" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("Audio: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let rate = manifest.get_or("rate", 48000u32).max(1);
+            let channels = manifest.get_or("channels", 2u16).max(1);
+            // sound is moved along in chunks this many milliseconds long
+            let chunk = manifest.get_or("chunk", 20u64).max(1);
+            let sounds = PathBuf::from(manifest.get("sounds").unwrap_or("../public/sounds"));
+
+            let input = device::input(&manifest).unwrap_or_else(|err| {
+                log(&send, format!("Audio: cannot open input: {}", err));
+                None
+            });
+            let output = device::output(&manifest, rate, channels).unwrap_or_else(|err| {
+                log(&send, format!("Audio: cannot open output, nothing will be heard: {}", err));
+                Box::new(device::Null)
+            });
+
+            send.send(Message::Subscribe(sid, AUDIO.to_string())).expect("error");
+            send.send(Message::Subscribe(sid, AUDIO_OUT.to_string())).expect("error");
+            send.send(Message::Subscribe(sid, SUBSCRIBERS.to_string())).expect("error");
+
+            let mut session = Session {
+                send: send,
+                mixer: Mixer::new(rate, channels),
+                input: input,
+                output: output,
+                listening: 0,
+                voices: 0,
+                chunk: chunk,
+                sounds: sounds,
+            };
+            let period = Duration::from_millis(chunk);
+            let mut next = Instant::now() + period;
+            loop {
+                match recv.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                    Ok(Message::Audio(topic, stream, samples)) if topic == AUDIO_OUT => {
+                        session.mixer.push(&stream, &samples);
+                    },
+                    Ok(Message::Event(topic, data)) if topic == AUDIO => {
+                        // requests may be tagged with their owner as "play ding.wav @owner"
+                        let (command, owner) = owner(&data);
+                        if let Err(err) = session.command(command, owner.unwrap_or("app")) {
+                            log(&session.send, format!("Audio: {}: {}", command, err));
+                        }
+                    },
+                    Ok(Message::Event(topic, data)) if topic == SUBSCRIBERS => {
+                        // only capture while someone is listening
+                        if let Some((topic, count)) = data.rsplit_once(' ') {
+                            if topic == AUDIO_IN {
+                                session.listening = count.parse().unwrap_or(0);
+                            }
+                        }
+                    },
+                    Ok(_) => { },
+                    Err(RecvTimeoutError::Timeout) => { },
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+
+                // move sound along when it is time whatever came in, so that busy traffic cannot make it stutter
+                if Instant::now() >= next {
+                    next += period;
+                    // after a stall, carry on from now rather than trying to catch up
+                    if next < Instant::now() {
+                        next = Instant::now() + period;
+                    }
+                    session.tick();
+                }
+            }
+        });
+    }
+}
+
+struct Session {
+    send: Sender<Message>,
+    mixer: Mixer,
+    input: Option<Box<dyn AudioSource>>,
+    output: Box<dyn AudioSink>,
+    // how many are subscribed to the input
+    listening: usize,
+    // every sound played by command gets a stream of its own, numbered
+    voices: usize,
+    // milliseconds in a chunk
+    chunk: u64,
+    // where the files apps play are
+    sounds: PathBuf,
+}
+
+impl Session {
+
+    fn command(&mut self, command: &str, owner: &str) -> Result<(), String> {
+        let mut words = command.splitn(2, ' ');
+        let verb = words.next().unwrap_or("");
+        let rest = words.next().unwrap_or("").trim();
+        match verb {
+            "play" => {
+                if rest.is_empty() {
+                    return Err("play what?".to_string());
+                }
+                let samples = wav::load(&sound(&self.sounds, rest)?)?;
+                self.play(owner, &samples);
+            },
+            "tone" => {
+                let mut numbers = rest.split_whitespace().map(|number| number.parse::<f32>());
+                let (hertz, ms) = match (numbers.next(), numbers.next()) {
+                    (Some(Ok(hertz)), Some(Ok(ms))) if hertz > 0.0 && ms > 0.0 && ms <= 60000.0 => (hertz, ms),
+                    _ => return Err("tone is <hertz> <milliseconds>".to_string()),
+                };
+                let samples = mixer::tone(self.mixer.rate, hertz, ms / 1000.0, 0.5);
+                self.play(owner, &samples);
+            },
+            "stop" => self.mixer.stop(owner),
+            "volume" => {
+                let volume = rest.parse::<f32>().map_err(|_| format!("bad volume {}", rest))?;
+                if !(0.0..=1.0).contains(&volume) {
+                    return Err("volume is from 0 to 1".to_string());
+                }
+                self.mixer.volume(owner, volume);
+            },
+            "status" => { },
+            _ => return Err("unknown command".to_string()),
+        }
+        let playing: Vec<String> = self.mixer.playing().iter().map(|stream| quote(stream)).collect();
+        let status = format!("{{\"rate\":{},\"channels\":{},\"input\":{},\"playing\":[{}]}}",
+            self.mixer.rate, self.mixer.channels, self.input.is_some(), playing.join(","));
+        let _ = self.send.send(Message::Event(STATUS.to_string(), status));
+        Ok(())
+    }
+
+    fn play(&mut self, owner: &str, samples: &Samples) {
+        self.voices += 1;
+        self.mixer.push(&format!("{} {}", owner, self.voices), samples);
+    }
+
+    // move one chunk of sound in and one out
+    fn tick(&mut self) {
+        if self.listening > 0 {
+            if let Some(input) = &mut self.input {
+                match input.read(frames(input.rate(), self.chunk)) {
+                    Ok(Some(samples)) => { let _ = self.send.send(samples.message(AUDIO_IN, "input")); },
+                    Ok(None) => self.input = None,
+                    Err(err) => {
+                        log(&self.send, format!("Audio: cannot read input: {}", err));
+                        self.input = None;
+                    },
+                }
+            }
+        }
+        let mix = self.mixer.mix(frames(self.mixer.rate, self.chunk));
+        if let Err(err) = self.output.write(&mix) {
+            log(&self.send, format!("Audio: cannot play, output is off: {}", err));
+            self.output = Box::new(device::Null);
+        }
+    }
+}
+
+// a file to play, which has to be in the sounds folder - an app names one of those, not any file on the disk
+fn sound(folder: &Path, name: &str) -> Result<String, String> {
+    let folder = folder.canonicalize().map_err(|err| format!("no sounds folder {}: {}", folder.display(), err))?;
+    let file = folder.join(name).canonicalize().map_err(|err| format!("{}: {}", name, err))?;
+    if !file.starts_with(&folder) {
+        return Err(format!("{} is not in the sounds folder", name));
+    }
+    Ok(file.to_string_lossy().to_string())
+}
+
+// how many frames at a rate last this many milliseconds
+fn frames(rate: u32, ms: u64) -> usize {
+    (rate as u64 * ms / 1000).max(1) as usize
+}
+
+fn log(send: &Sender<Message>, text: String) {
+    println!("{}", text);
+    let _ = send.send(Message::Event("/log".to_string(), text));
+}
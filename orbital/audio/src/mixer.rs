@@ -0,0 +1,104 @@
+
+// the mixer keeps a queue of sound for every stream and adds them together a chunk at a time. a stream that runs dry
+// just goes quiet until more arrives, and one that stays dry for a while is forgotten.
+
+use std::collections::{HashMap, VecDeque};
+
+use service::audio::Samples;
+
+// chunks a stream can be empty for before it is dropped
+const IDLE: usize = 50;
+
+// the most sound a stream can have waiting, in seconds - what is sent faster than it can be played past this is dropped
+const QUEUE: usize = 60;
+
+struct Stream {
+    queue: VecDeque<f32>,
+    volume: f32,
+    idle: usize,
+}
+
+pub struct Mixer {
+    pub rate: u32,
+    pub channels: u16,
+    streams: HashMap<String, Stream>,
+    // the volume for new streams from each owner
+    volumes: HashMap<String, f32>,
+}
+
+impl Mixer {
+
+    pub fn new(rate: u32, channels: u16) -> Mixer {
+        Mixer { rate: rate, channels: channels.max(1), streams: HashMap::new(), volumes: HashMap::new() }
+    }
+
+    /// Queue sound on the end of a stream, in whatever rate and channels it comes in, as far as there is room for it
+    pub fn push(&mut self, stream: &str, samples: &Samples) {
+        let samples = samples.convert(self.rate, self.channels);
+        let volume = self.volumes.get(owner(stream)).cloned().unwrap_or(1.0);
+        let room = QUEUE * self.rate as usize * self.channels as usize;
+        let stream = self.streams.entry(stream.to_string()).or_insert(Stream { queue: VecDeque::new(), volume: volume, idle: 0 });
+        stream.queue.extend(samples.frames.iter().take(room.saturating_sub(stream.queue.len())));
+        stream.idle = 0;
+    }
+
+    /// Stop a stream, or everything an owner is playing - see owner()
+    pub fn stop(&mut self, name: &str) {
+        self.streams.retain(|stream, _| stream != name && owner(stream) != name);
+    }
+
+    /// Set the volume of a stream, or of everything an owner is playing now and from now on
+    pub fn volume(&mut self, name: &str, volume: f32) {
+        let volume = volume.max(0.0);
+        for (stream, playing) in self.streams.iter_mut() {
+            if stream == name || owner(stream) == name {
+                playing.volume = volume;
+            }
+        }
+        self.volumes.insert(name.to_string(), volume);
+    }
+
+    /// Which streams have something left to play
+    pub fn playing(&self) -> Vec<String> {
+        let mut playing: Vec<String> = self.streams.iter().filter(|(_, stream)| !stream.queue.is_empty()).map(|(name, _)| name.clone()).collect();
+        playing.sort();
+        playing
+    }
+
+    /// Take the next count frames of every stream added together
+    pub fn mix(&mut self, count: usize) -> Samples {
+        let mut frames = vec![0.0f32; count * self.channels as usize];
+        for stream in self.streams.values_mut() {
+            let take = frames.len().min(stream.queue.len());
+            for (frame, sample) in frames.iter_mut().zip(stream.queue.drain(..take)) {
+                *frame += sample * stream.volume;
+            }
+            if take == 0 {
+                stream.idle += 1;
+            }
+        }
+        self.streams.retain(|_, stream| stream.idle < IDLE);
+
+        // loud sounds on top of each other are clipped rather than wrapping around
+        for frame in frames.iter_mut() {
+            *frame = frame.max(-1.0).min(1.0);
+        }
+        Samples::new(self.rate, self.channels, frames)
+    }
+}
+
+/// Who a stream belongs to - a stream named "<owner> <anything>" is one of several playing for that owner
+pub fn owner(stream: &str) -> &str {
+    stream.split(' ').next().unwrap_or(stream)
+}
+
+/// A sine wave, faded in and out so it does not click
+pub fn tone(rate: u32, hertz: f32, seconds: f32, volume: f32) -> Samples {
+    let count = (rate as f32 * seconds.max(0.0)) as usize;
+    let fade = (rate as usize / 100).min(count / 2).max(1);
+    let frames = (0..count).map(|index| {
+        let envelope = (index.min(count - 1 - index) as f32 / fade as f32).min(1.0);
+        (index as f32 * hertz * std::f32::consts::PI * 2.0 / rate as f32).sin() * volume * envelope
+    }).collect();
+    Samples::new(rate, 1, frames)
+}
@@ -0,0 +1,120 @@
+
+// wav files - a riff file with a fmt chunk saying how the sound is stored and a data chunk holding it. 8, 16, 24 and 32
+// bit integer samples and 32 bit float samples are read; files are written as 16 bit integers, which everything plays.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+use service::audio::Samples;
+
+/// Read a wav file
+pub fn load(file: &str) -> Result<Samples, String> {
+    let bytes = std::fs::read(file).map_err(|err| format!("cannot read {}: {}", file, err))?;
+    decode(&bytes).map_err(|err| format!("cannot read {}: {}", file, err))
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Samples, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a wav file".to_string());
+    }
+
+    let mut format = None;
+    let mut at = 12;
+    while at + 8 <= bytes.len() {
+        let kind = &bytes[at..at + 4];
+        let size = u32::from_le_bytes([bytes[at + 4], bytes[at + 5], bytes[at + 6], bytes[at + 7]]) as usize;
+        // a file cut off part way through still has what is there
+        let body = &bytes[at + 8..(at + 8 + size).min(bytes.len())];
+        match kind {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err("fmt chunk is too short".to_string());
+                }
+                let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                // extensible files say what they really are further in
+                if tag == 0xfffe && body.len() >= 26 {
+                    tag = u16::from_le_bytes([body[24], body[25]]);
+                }
+                if channels == 0 || rate == 0 {
+                    return Err("fmt chunk has no channels or rate".to_string());
+                }
+                format = Some((tag, channels, rate, bits));
+            },
+            b"data" => {
+                let (tag, channels, rate, bits) = format.ok_or("data comes before fmt")?;
+                let frames = match (tag, bits) {
+                    (1, 8) => body.iter().map(|byte| (*byte as f32 - 128.0) / 128.0).collect(),
+                    (1, 16) => body.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0).collect(),
+                    (1, 24) => body.chunks_exact(3).map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0).collect(),
+                    (1, 32) => body.chunks_exact(4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0).collect(),
+                    (3, 32) => body.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect(),
+                    _ => return Err(format!("samples of format {} with {} bits are not supported", tag, bits)),
+                };
+                let mut samples = Samples::new(rate, channels, frames);
+                samples.frames.truncate(samples.len() * channels as usize);
+                return Ok(samples);
+            },
+            _ => { },
+        }
+        // chunks start on even bytes
+        at += 8 + size + size % 2;
+    }
+    Err("no data chunk".to_string())
+}
+
+/// Writes 16 bit samples to a wav file. the sizes in the header are kept up to date as it goes, so the file can be
+/// played even if nobody gets to finish it.
+pub struct Writer {
+    file: String,
+    writer: BufWriter<File>,
+    rate: u32,
+    channels: u16,
+    bytes: u32,
+}
+
+impl Writer {
+
+    pub fn create(file: &str, rate: u32, channels: u16) -> Result<Writer, String> {
+        let channels = channels.max(1);
+        let failed = |err: std::io::Error| format!("cannot write {}: {}", file, err);
+        let mut writer = BufWriter::new(File::create(file).map_err(failed)?);
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&36u32.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&rate.to_le_bytes());
+        header.extend_from_slice(&(rate * channels as u32 * 2).to_le_bytes());
+        header.extend_from_slice(&(channels * 2).to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_all(&header).map_err(failed)?;
+        Ok(Writer { file: file.to_string(), writer: writer, rate: rate, channels: channels, bytes: 0 })
+    }
+
+    pub fn write(&mut self, samples: &Samples) -> Result<(), String> {
+        let samples = samples.convert(self.rate, self.channels);
+        let mut bytes = Vec::with_capacity(samples.frames.len() * 2);
+        for sample in &samples.frames {
+            bytes.extend_from_slice(&((sample.max(-1.0).min(1.0) * 32767.0).round() as i16).to_le_bytes());
+        }
+        let file = &self.file;
+        let failed = |err: std::io::Error| format!("cannot write {}: {}", file, err);
+        self.writer.write_all(&bytes).map_err(failed)?;
+        self.bytes += bytes.len() as u32;
+
+        // put the sizes in and go back to the end
+        self.writer.seek(SeekFrom::Start(4)).map_err(failed)?;
+        self.writer.write_all(&(36 + self.bytes).to_le_bytes()).map_err(failed)?;
+        self.writer.seek(SeekFrom::Start(40)).map_err(failed)?;
+        self.writer.write_all(&self.bytes.to_le_bytes()).map_err(failed)?;
+        self.writer.seek(SeekFrom::End(0)).map_err(failed)?;
+        self.writer.flush().map_err(failed)
+    }
+}
@@ -0,0 +1,180 @@
+
+// sound in and out without any speakers or microphone - wav files stand in for both
+
+use crossbeam::channel::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use audio::*;
+use broker::*;
+use service::{Message, SID};
+use service::audio::*;
+
+const PROBE: SID = 1;
+const AUDIO_SID: SID = 2;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orbital-audio-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn loudest(samples: &Samples) -> f32 {
+    samples.frames.iter().fold(0.0, |loudest, sample| sample.abs().max(loudest))
+}
+
+#[test]
+fn streams_are_converted_and_mixed() {
+    // a stereo buffer at 48k going to mono at 24k averages the sides and keeps every other frame
+    let stereo = Samples::new(48000, 2, (0..8).map(|index| index as f32 / 10.0).collect());
+    let mono = stereo.convert(24000, 1);
+    assert_eq!((mono.rate, mono.channels, mono.len()), (24000, 1, 2));
+    assert!((mono.frames[0] - 0.05).abs() < 1e-6 && (mono.frames[1] - 0.45).abs() < 1e-6, "{:?}", mono.frames);
+
+    // two apps at once are added together, and clipped when that is too loud
+    let mut mixer = mixer::Mixer::new(8000, 1);
+    mixer.push("one 1", &Samples::new(8000, 1, vec![0.25; 4]));
+    mixer.push("two 2", &Samples::new(8000, 1, vec![0.5, 0.5, 0.9, 0.9]));
+    assert_eq!(mixer.mix(4).frames, vec![0.75, 0.75, 1.0, 1.0]);
+    assert_eq!(mixer.mix(2).frames, vec![0.0, 0.0]);
+
+    // stopping an owner stops only its sounds
+    mixer.push("one 3", &Samples::new(8000, 1, vec![0.25; 4]));
+    mixer.push("two 4", &Samples::new(8000, 1, vec![0.5; 4]));
+    mixer.stop("two");
+    assert_eq!(mixer.playing(), vec!["one 3".to_string()]);
+    mixer.volume("one", 0.5);
+    assert_eq!(mixer.mix(1).frames, vec![0.125]);
+
+    // a stream sent faster than it plays keeps only the first minute
+    let mut mixer = mixer::Mixer::new(100, 1);
+    for _ in 0..3 {
+        mixer.push("fast 1", &Samples::new(100, 1, vec![0.5; 100 * 50]));
+    }
+    assert!(mixer.mix(100 * 60).frames.iter().all(|frame| *frame == 0.5));
+    assert_eq!(mixer.mix(1).frames, vec![0.0]);
+}
+
+#[test]
+fn wav_files_keep_their_sound() {
+    let dir = scratch("wav");
+    let file = dir.join("tone.wav");
+    let tone = mixer::tone(16000, 440.0, 0.25, 0.5);
+    let mut writer = wav::Writer::create(file.to_str().unwrap(), 16000, 1).unwrap();
+    writer.write(&tone).unwrap();
+
+    // nobody finished it, and it still reads back
+    let read = wav::load(file.to_str().unwrap()).unwrap();
+    assert_eq!((read.rate, read.channels, read.len()), (16000, 1, tone.len()));
+    for (a, b) in read.frames.iter().zip(tone.frames.iter()) {
+        assert!((a - b).abs() < 1.0 / 16000.0, "{} {}", a, b);
+    }
+
+    assert!(wav::decode(b"RIFF\0\0\0\0WAVE").is_err());
+    assert!(wav::load(dir.join("missing.wav").to_str().unwrap()).is_err());
+}
+
+#[test]
+fn the_service_captures_and_plays_through_wav_files() {
+    let dir = scratch("service");
+    let input = dir.join("input.wav");
+    let mut writer = wav::Writer::create(input.to_str().unwrap(), 8000, 1).unwrap();
+    writer.write(&mixer::tone(8000, 200.0, 0.1, 0.5)).unwrap();
+    let output = dir.join("output.wav");
+    let manifest = dir.join("audio.manifest");
+    std::fs::write(&manifest, format!("rate = 16000\nchannels = 1\ninput = wav\ninput.file = {}\noutput = wav\noutput.file = {}\n",
+        input.display(), output.display())).unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,AUDIO_IN.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,STATUS.to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Audio::with_manifest(manifest.to_str().unwrap());
+    brokersend.send(Message::Channel(AUDIO_SID,"audio".to_string(),localsend)).unwrap();
+    instance.start("audio".to_string(),AUDIO_SID,brokersend.clone(),localrecv);
+
+    // the input wav comes through as it is, a chunk at a time
+    let captured = loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("nothing captured") {
+            Message::Audio(topic,stream,samples) if topic == AUDIO_IN => break (stream,samples),
+            _ => { },
+        }
+    };
+    assert_eq!(captured.0, "input");
+    assert_eq!((captured.1.rate, captured.1.channels, captured.1.len()), (8000, 1, 160));
+
+    // a script beeps and a native service plays a buffer of its own
+    brokersend.send(Message::Event(AUDIO.to_string(),"tone 440 200 @script.js".to_string())).unwrap();
+    brokersend.send(Samples::new(8000, 2, vec![0.25; 1600]).message(AUDIO_OUT, "native")).unwrap();
+    let status = loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no status") {
+            Message::Event(topic,data) if topic == STATUS => break data,
+            _ => { },
+        }
+    };
+    assert!(status.contains("\"script.js 1\""), "{}", status);
+    std::thread::sleep(Duration::from_millis(500));
+
+    let played = wav::load(output.to_str().unwrap()).unwrap();
+    assert_eq!((played.rate, played.channels), (16000, 1));
+    assert!(loudest(&played) > 0.5, "the mix is only {} loud", loudest(&played));
+}
+
+#[test]
+fn apps_only_play_what_is_in_the_sounds_folder() {
+    let dir = scratch("sounds");
+    std::fs::create_dir_all(dir.join("sounds")).unwrap();
+    for file in [dir.join("sounds").join("ding.wav"), dir.join("secret.wav")] {
+        let mut writer = wav::Writer::create(file.to_str().unwrap(), 8000, 1).unwrap();
+        writer.write(&mixer::tone(8000, 440.0, 0.1, 0.5)).unwrap();
+    }
+    let manifest = dir.join("audio.manifest");
+    std::fs::write(&manifest, format!("sounds = {}\n", dir.join("sounds").display())).unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,STATUS.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Audio::with_manifest(manifest.to_str().unwrap());
+    brokersend.send(Message::Channel(AUDIO_SID,"audio".to_string(),localsend)).unwrap();
+    instance.start("audio".to_string(),AUDIO_SID,brokersend.clone(),localrecv);
+
+    // wait until the service is listening before asking it for anything
+    brokersend.send(Message::Subscribe(PROBE,service::SUBSCRIBERS.to_string())).unwrap();
+    loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no audio service") {
+            Message::Event(_,data) if data == format!("{} 1",AUDIO) => break,
+            _ => { },
+        }
+    }
+    let next = || loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("nothing from audio") {
+            Message::Event(topic,data) if topic != service::SUBSCRIBERS => break (topic,data),
+            _ => { },
+        }
+    };
+
+    // a file outside, however it is named, is refused
+    let outside = dir.join("secret.wav");
+    for file in ["../secret.wav", outside.to_str().unwrap()] {
+        brokersend.send(Message::Event(AUDIO.to_string(),format!("play {} @a.js",file))).unwrap();
+        let (topic,data) = next();
+        assert_eq!(topic, "/log");
+        assert!(data.contains("is not in the sounds folder"), "{}", data);
+    }
+
+    // and one in the folder plays
+    brokersend.send(Message::Event(AUDIO.to_string(),"play ding.wav @a.js".to_string())).unwrap();
+    let (topic,data) = next();
+    assert_eq!(topic, STATUS);
+    assert!(data.contains("\"a.js 1\""), "{}", data);
+}
@@ -6,6 +6,12 @@ use service::*;
 use broker::*;
 use camera::*;
 use tensor::*;
+use tracker::*;
+use scanner::*;
+use recorder::*;
+use ::audio::*;
+use scene::*;
+use viewsoft::*;
 use viewmakepad::*;
 
 
@@ -52,6 +58,72 @@ fn main() {
 	    instance.start(name,sid,brokersend.clone(),localrecv);
 	}
 
+    // tracker - follows what tensor finds from frame to frame
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = Tracker::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
+    // scanner - reads qr codes and barcodes held up to the camera
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = Scanner::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
+    // recorder - writes frames to disk when asked to on /recorder
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = Recorder::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
+    // audio - sound in from /audio/in and out through a mix of everything on /audio/out
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = Audio::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
+    // scene - the one scene graph apps draw into on /scene, mirrored by renderers from /scene/changes
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = Scene::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
+    // viewsoft - draws the scene on the cpu for snapshots on /viewsoft, or as frames when there is no window to draw in
+
+    {
+	    let sid: SID = rand::random::<SID>();
+	    let (localsend,localrecv) = unbounded::<Message>();
+	    let instance = ViewSoft::new();
+	    let name = instance.name().to_string();
+	    let _ = brokersend.send(Message::Channel(sid,name.clone(),localsend));
+	    instance.start(name,sid,brokersend.clone(),localrecv);
+	}
+
 	// app
 	// here i load up a script that describes an app (as a demo)
 	// it looks like just another unit of computation
@@ -77,7 +77,7 @@ impl Serviceable for Broker {
 
                     Message::Subscribe(sid,topic) => {
                         if !registry.contains_key(&sid) {
-                            println!("Broker: forcing entry for non-existent app {} ('{}') to topic '{}'",sid,registry[&sid].name,topic);
+                            println!("Broker: forcing entry for non-existent app {} to topic '{}'",sid,topic);
                             let (_trashsend,_trashreceive) = unbounded::<Message>();
                             let wrapper = ServiceWrapper {
                                 sid: sid,
@@ -88,25 +88,36 @@ impl Serviceable for Broker {
                             registry.insert(sid,wrapper);
                         }
                         println!("Broker: subscribing app {} ('{}') to topic '{}'",sid,registry[&sid].name,topic);
-                        registry[&sid].subscriptions.borrow_mut().insert(topic);
+                        let added = registry[&sid].subscriptions.borrow_mut().insert(topic.clone());
+
+                        // someone new to watching subscriptions needs to know how things stand
+                        if topic == SUBSCRIBERS {
+                            let mut topics: Vec<String> = registry.values().flat_map(|target| target.subscriptions.borrow().iter().cloned().collect::<Vec<String>>()).collect();
+                            topics.sort();
+                            topics.dedup();
+                            for topic in topics {
+                                let _res = registry[&sid].send.send(Message::Event(SUBSCRIBERS.to_string(),format!("{} {}",topic,subscribers(&registry,&topic))));
+                            }
+                        }
+                        if added {
+                            announce(&registry,&topic);
+                        }
                     },
 
                     Message::Unsubscribe(sid,topic) => {
-                        println!("Broker: unsubscribing app {} ('{}') from topic '{}'",sid,registry[&sid].name,topic);
-                        registry[&sid].subscriptions.borrow_mut().remove(&topic);
+                        if let Some(target) = registry.get(&sid) {
+                            println!("Broker: unsubscribing app {} ('{}') from topic '{}'",sid,target.name,topic);
+                            if target.subscriptions.borrow_mut().remove(&topic) {
+                                announce(&registry,&topic);
+                            }
+                        }
                     },
 
-                    // hack, forward share objects...
-                    Message::Share(sharedmemory) => {
-                        // repost event objects 
+                    // frames are shared rather than copied - everyone subscribed to the topic gets a handle to the same one
+                    Message::Share(topic,sharedmemory) => {
                         for target in &registry {
-                            if target.1.subscriptions.borrow_mut().contains(&"/view".to_string()) {
-                                //let mut ptr = sharedmemory.lock().unwrap();
-                                //let mut sharedmemory = Arc::new(Mutex::new(Box::new(ptr)));
-                                let _res = target.1.send.send(Message::Share(sharedmemory));
-                                // TODO i can't really send it to multiple listeners without some kind of shared handle...
-                                // so i have to break here... else if there are two listeners it cannot work...
-                                break;
+                            if target.1.subscriptions.borrow().contains(&topic) {
+                                let _res = target.1.send.send(Message::Share(topic.clone(),sharedmemory.clone()));
                             }
                         }
                     },
@@ -120,6 +131,15 @@ impl Serviceable for Broker {
                         }
                     },
 
+                    // sound is shared like frames, rather than copied for every listener
+                    Message::Audio(topic,stream,samples) => {
+                        for target in &registry {
+                            if target.1.subscriptions.borrow().contains(&topic) {
+                                let _res = target.1.send.send(Message::Audio(topic.clone(),stream.clone(),samples.clone()));
+                            }
+                        }
+                    },
+
 /*
                     // this is disabled because of a design quirk in the way that winit demands the main thread
                     // so we have to start all services on the main thread sadly
@@ -156,8 +176,8 @@ impl Serviceable for Broker {
                             registry.insert(sid,wrapper);
                         } else {
                             println!("Broker: revising existing channel for {} {}",sid,name);
-                            registry.remove(&sid);
-                            let subscriptions = registry[&sid].subscriptions.clone();
+                            // keep whatever it subscribed to before its channel arrived
+                            let subscriptions = registry.remove(&sid).unwrap().subscriptions;
                             let wrapper = ServiceWrapper {
                                 sid: sid,
                                 name: name.clone(),
@@ -217,7 +237,19 @@ impl Serviceable for Broker {
 }
 
 
+fn subscribers(registry: &std::collections::HashMap<SID,ServiceWrapper>, topic: &str) -> usize {
+    registry.values().filter(|target| target.subscriptions.borrow().contains(topic)).count()
+}
 
+// tell anyone watching subscriptions how many subscribers a topic has now
+fn announce(registry: &std::collections::HashMap<SID,ServiceWrapper>, topic: &str) {
+    let count = subscribers(registry,topic);
+    for target in registry.values() {
+        if target.subscriptions.borrow().contains(SUBSCRIBERS) {
+            let _res = target.send.send(Message::Event(SUBSCRIBERS.to_string(),format!("{} {}",topic,count)));
+        }
+    }
+}
 
 ///
 /// A helpful bootstrapper that kicks off a few services and wires them up to the broker
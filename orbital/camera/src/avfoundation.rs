@@ -0,0 +1,504 @@
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// the mac webcam as a video source - only built on macos
+/////////////////////////////////////////////////////////////////////////////////////////
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::source::*;
+
+pub struct AVFoundation {
+    // the capture session, kept as a number so that the source can be sent to the camera thread
+    session: usize,
+    resolution: (usize, usize),
+}
+
+// the sizes a capture session can be asked for
+const PRESETS: [(usize, usize, &str); 6] = [
+    (352, 288, "AVCaptureSessionPreset352x288"),
+    (640, 480, "AVCaptureSessionPreset640x480"),
+    (960, 540, "AVCaptureSessionPresetiFrame960x540"),
+    (1280, 720, "AVCaptureSessionPreset1280x720"),
+    (1920, 1080, "AVCaptureSessionPreset1920x1080"),
+    (3840, 2160, "AVCaptureSessionPreset3840x2160"),
+];
+
+impl AVFoundation {
+    /// Start the webcam with this name, or the default one
+    pub fn open(name: Option<&str>) -> Result<AVFoundation, String> {
+        // START VIDEO RECEIVER
+        let session = appleWebCamCaptureStart(name)?;
+        Ok(AVFoundation { session: session as usize, resolution: (FRAME_WIDTH, FRAME_HEIGHT) })
+    }
+}
+
+impl VideoSource for AVFoundation {
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String> {
+        // the capture callback keeps the latest frame in the singleton, since it cannot be handed anything else
+        let sharedmemory = singleton().sharedmemory;
+        let ptr = sharedmemory.lock().unwrap();
+        frame.copy_from_slice(&ptr[..]);
+        Ok(true)
+    }
+
+    fn resolution(&self) -> Option<(usize, usize)> { Some(self.resolution) }
+
+    fn set_resolution(&mut self, width: usize, height: usize) -> Result<(), String> {
+        let preset = match PRESETS.iter().find(|(w,h,_)| *w == width && *h == height) {
+            Some((_,_,preset)) => preset,
+            None => return Err(format!("the webcam can capture at {}", PRESETS.iter().map(|(w,h,_)| format!("{}x{}",w,h)).collect::<Vec<String>>().join(", "))),
+        };
+        unsafe {
+            let session = self.session as *mut Object;
+            let preset = NSString::alloc(nil).init_str(preset);
+            let supported: bool = msg_send![session, canSetSessionPreset:preset];
+            if !supported {
+                return Err(format!("this webcam cannot capture at {}x{}", width, height));
+            }
+            let _: () = msg_send![session, beginConfiguration];
+            let _: () = msg_send![session, setSessionPreset:preset];
+            let _: () = msg_send![session, commitConfiguration];
+        }
+        self.resolution = (width, height);
+        Ok(())
+    }
+
+    fn pause(&mut self) {
+        unsafe { let _: () = msg_send![self.session as *mut Object, stopRunning]; }
+    }
+
+    fn resume(&mut self) {
+        unsafe { let _: () = msg_send![self.session as *mut Object, startRunning]; }
+    }
+}
+
+impl Drop for AVFoundation {
+    fn drop(&mut self) {
+        self.pause();
+    }
+}
+
+/// The names of every webcam
+pub fn devices() -> Vec<String> {
+    let mut names = Vec::new();
+    unsafe {
+        let AVMediaTypeVideo = NSString::alloc(nil).init_str(&"vide".to_string());
+        let devices: *mut Object = msg_send![class!(AVCaptureDevice), devicesWithMediaType:AVMediaTypeVideo ];
+        let count: u64 = msg_send![devices, count];
+        for i in 0..count {
+            let device: *mut Object = msg_send![devices, objectAtIndex:i];
+            let name: *mut Object = msg_send![device, localizedName];
+            let text: *const std::os::raw::c_char = msg_send![name, UTF8String];
+            names.push(std::ffi::CStr::from_ptr(text).to_string_lossy().into_owned());
+        }
+    }
+    names
+}
+
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// a singleton due to the appleWebCamCaptureOutput not being able to be a closure
+/////////////////////////////////////////////////////////////////////////////////////////
+
+use std::sync::{Once};
+//use std::time::Duration;
+use std::{mem};
+
+#[derive(Clone)]
+struct SingletonReader {
+    // Since we will be used in many threads, we need to protect
+    // concurrent access
+    inner: Arc<Mutex<u8>>,
+    //raw: [u32;921600],
+    //memory: Box<[u32;921600]>,
+    sharedmemory: Arc<Mutex<Box<[u32;921600]>>>,
+}
+
+fn singleton() -> SingletonReader {
+
+    static mut SINGLETON: *const SingletonReader = 0 as *const SingletonReader;
+    static ONCE: Once = Once::new();
+
+    unsafe {
+        ONCE.call_once(|| {
+
+            const SIZE: usize = 1280*720;
+            let raw = [0;SIZE];
+            let memory = Box::new(raw);
+            let sharedmemory = Arc::new(Mutex::new(memory));
+
+            let singleton = SingletonReader {
+                inner: Arc::new(Mutex::new(0)),
+                //raw: raw,
+                //memory: memory,
+                sharedmemory: sharedmemory,
+            };
+
+            SINGLETON = mem::transmute(Box::new(singleton));
+        });
+
+        (*SINGLETON).clone()
+    }
+}
+
+
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// get at apple avfoundation webcam
+/////////////////////////////////////////////////////////////////////////////////////////
+
+
+//
+// Rust WebCam access using AVFoundation - see these useful and fun links:
+//
+// https://gist.github.com/bellbind/6954679
+// https://github.com/SSheldon/rust-objc/blob/master/examples/example.rs
+// https://kyle.space/posts/cocoa-apps-in-rust-eventually/
+// https://github.com/pcwalton/rust-media/blob/master/platform/macos/coremedia.rs
+//
+
+
+
+// ----------------------------------------------------------------------------------------------------
+// bind to an objective c native layer to perform some avfoundation operations - no longer used
+
+//#![allow(non_upper_case_globals)]
+//#![allow(non_camel_case_types)]
+//#![allow(non_snake_case)]
+//include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+// #[link(name = "avtest")]
+
+// ----------------------------------------------------------------------------------------------------
+// pull in all of avfoundation headers as built by bindgen - no longer used
+// this is not used because products are huge and buggy - it's just too overwhelming to deal with
+//include!("../avtest/avtestbind.in");
+
+// ----------------------------------------------------------------------------------------------------
+// super weird bug work around - no longer used
+// if I include a source file with the below then there are compile time errors - avtest not foudn
+// if I cut and paste that here - then those errors go away... 
+// perhaps i don't understand how include works
+//
+
+// avfoundation bindgen version with full type declarations
+//extern "C" {
+//    pub fn avtest(
+//        device: AVCaptureDevice,
+//        input: AVCaptureDeviceInput,
+//        output: AVCaptureVideoDataOutput,
+//    );
+//}
+
+// void* version which is easier to build and does not rely on buggy bindgen attempt at avfoundation
+//extern "C" {
+//    pub fn avtest(
+//        device: *mut Object,
+//        input: *mut Object,
+//        output: *mut Object,
+//    );
+//}
+
+// ----------------------------------------------------------------------------------------------------
+// Objective C helper - does most of our bridging - does provide its own selector and sel! macros
+use objc::runtime::{Class, Object, Sel, Protocol};
+use objc::declare::ClassDecl;
+
+// Macros annoyingly have to be specified in main.rs ... bad rust parser design that pollute scopes...
+//#[macro_use] extern crate objc;
+
+// ----------------------------------------------------------------------------------------------------
+//  get services from core foundation
+//use core_foundation::base::{CFTypeID};
+
+// ----------------------------------------------------------------------------------------------------
+// build.rs can specify these also... notably the app will link but will fail to run without these
+#[link(name = "AVFoundation", kind = "framework")]
+#[link(name = "CoreMedia", kind = "framework")]
+#[link(name = "CoreImage", kind = "framework")]
+#[link(name = "CoreFoundation", kind = "framework")]
+#[link(name = "Foundation", kind = "framework")]
+extern { pub fn NSLog(fmt: *mut Object, ...); }
+
+// ----------------------------------------------------------------------------------------------------
+// NSString
+
+use cocoa::foundation::NSString;
+//use cocoa::appkit::NSColor;
+
+// various ways I can get at strings and manipulate them 
+// use std::ffi::CString;
+// CString::new("vide").unwrap();
+// msg_send![class!(NSString), stringWithUTF8String:AVMediaTypeVideo];
+// NSString::alloc(nil).init_str(&"something".to_string()).autorelease();
+// let NSString = Class::get("NSString").unwrap();
+// Seems like I can get away with not releasing strings?
+// use cocoa::foundation::NSAutoreleasePool;
+
+// ----------------------------------------------------------------------------------------------------
+// cocoa::base - provides a selector builder also
+
+//#[allow(non_upper_case_globals)]
+//type id = *mut Object;
+//const nil: id = 0 as Id;
+use cocoa::base::{nil, id};
+
+// ----------------------------------------------------------------------------------------------------
+// trying to get at some of these methods; seems easiest to just use id
+
+/*
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct __CVBuffer {
+    _unused: [u8; 0],
+}
+pub type CVBufferRef = *mut __CVBuffer;
+pub type CVImageBufferRef = CVBufferRef;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct opaqueCMSampleBuffer {
+    _unused: [u8; 0],
+}
+pub type CMSampleBufferRef = *mut opaqueCMSampleBuffer;
+*/
+
+extern "C" {
+    pub fn CMSampleBufferGetImageBuffer(buffer: id) -> id;
+    //pub fn CVPixelBufferGetBaseAddress(buffer:id) -> id;
+    //pub fn CVPixelBufferLockBaseAddress(buffer:id,flags:u64);
+    //pub fn CVPixelBufferUnlockBaseAddress(buffer:id,flags:u64);
+    //pub fn CVPixelBufferGetWidth(buffer:id) -> u64;
+    //pub fn CVPixelBufferGetHeight(buffer:id) -> u64;
+    //pub fn CVPixelBufferGetBaseAddressOfPlane(buffer:id,flags:u64) -> id;
+    // pub fn CMSampleBufferGetOutputPresentationTimeStamp(buffer:id) -> id;
+    // pub fn CMTimeGetSeconds(time:id) -> f64;
+}
+
+// generate_counter!(Counter, usize);
+
+
+extern fn appleWebCamCaptureOutput(_this: &Object, _cmd: Sel, _id1: id, sbuf: id, _id3: id) {
+
+    // hmmm - TODO should build a system where this doesn't get overloaded
+    // thread::spawn(move || {
+
+    unsafe {
+
+        // get timestamp - this crashes...
+        //let time = CMSampleBufferGetOutputPresentationTimeStamp(buffer);
+        //let time = CMTimeGetSeconds(time);
+        //println!("Time is {}",time);
+
+        // given a CMSampleBuffer convert to a CVImageBuffer and also is a CVPixelBuffer
+        let ibuf = CMSampleBufferGetImageBuffer(sbuf);
+
+        // given a CVImageBuffer return a CIImage (this may have to be done before any more operations on ibuf)
+        let image: *mut Object = msg_send![class!(CIImage), imageWithCVImageBuffer: ibuf];
+
+        /* this prints this:
+         <CVPixelBuffer 0x10fc80050 width=1280 height=720 bytesPerRow=2560 pixelFormat=2vuy iosurface=0x11f078270 attributes={
+            Height = 720;
+            IOSurfaceProperties =     {
+                IOSurfacePurgeWhenNotInUse = 1;
+            };
+            PixelFormatType = 846624121;
+            Width = 1280;
+        } propagatedAttachments={
+            CVImageBufferColorPrimaries = "ITU_R_709_2";
+            CVImageBufferTransferFunction = "ITU_R_709_2";
+            CVImageBufferYCbCrMatrix = "ITU_R_601_4";
+        } nonPropagatedAttachments={
+        }>
+        */
+        //NSLog(NSString::alloc(nil).init_str("ImageBuf is %@"),ibuf);
+
+        // this prints this: <CIImage: 0x11df090f0 extent [0 0 1280 720]>
+        //NSLog(NSString::alloc(nil).init_str("CIImage is %@"),image);
+
+        // GET AT PIXELS ATTEMPT #1: TRY LOCK ADDRESS AND PEEK
+        //
+        // this crashes or returns null if I do not lock it
+        //CVPixelBufferLockBaseAddress(ibuf, 0);
+        //let baseAddress: id = CVPixelBufferGetBaseAddress(ibuf);
+        //NSLog(NSString::alloc(nil).init_str("DATA is %@"),baseAddress);
+        //CVPixelBufferUnlockBaseAddress(ibuf,0);
+
+        // also crashes
+        //let lumaBaseAddress = CVPixelBufferGetBaseAddressOfPlane(ibuf, 0);
+        //NSLog(NSString::alloc(nil).init_str("DATA is %@"),lumaBaseAddress);
+
+        // if i could get at a raw buffer then I could browse it...
+        //    let ptr = baseAddress as *mut u32;
+        //    let val = *(ptr.add(1));
+        //    println!("peering at raw buffer {}",val);
+
+        // some queries work...
+        //let width = CVPixelBufferGetWidth(ibuf);
+        //let height = CVPixelBufferGetHeight(ibuf);
+
+        // given a CIImage return an NSBitmapImageRep and populate it
+        let bitmap: *mut Object = msg_send![class!(NSBitmapImageRep), alloc];
+        let _: () = msg_send![bitmap,initWithCIImage: image];
+        //NSLog(NSString::alloc(nil).init_str("DATA is %@"),bitmap);
+
+        //this works
+        let w: u64 = msg_send![bitmap,pixelsWide];
+        let h: u64 = msg_send![bitmap,pixelsHigh];
+        let m: u64 = msg_send![bitmap,bytesPerRow];
+        let w = w as usize;
+        let h = h as usize;
+        let m = m as usize;
+        let rawsrc: *mut u32 = msg_send![bitmap,bitmapData];
+
+        // how long is this taking?
+        //use std::time::Instant;
+        //let now = Instant::now();
+
+        // write to the raw pixels
+        let sharedmemory = singleton().sharedmemory;
+        let ptr = sharedmemory.lock().unwrap();
+
+        //let rawdest = singleton().raw;
+        //let rawdest: *const u32 = &(rawdest[0]);
+        //let rawdest: *mut u32 = rawdest as *mut u32;
+
+        // have to copy and get out fast due to next frame coming along
+        let rawdest: *mut u32 = ptr.as_ptr() as *mut u32;
+        if w == FRAME_WIDTH && h == FRAME_HEIGHT && m == FRAME_WIDTH*4 {
+            std::ptr::copy_nonoverlapping(rawsrc,rawdest,720*1280);
+        } else if w > 0 && h > 0 {
+            // some other resolution was asked for - pick the nearest pixel to fill the frame
+            for y in 0..FRAME_HEIGHT {
+                let row = rawsrc.add((y*h/FRAME_HEIGHT)*(m/4));
+                for x in 0..FRAME_WIDTH {
+                    *rawdest.add(y*FRAME_WIDTH+x) = *row.add(x*w/FRAME_WIDTH);
+                }
+            }
+        }
+
+        /*
+        for y in 0..512{
+            for x in 0..512{
+
+                // GET AT PIXELS ATTEMPT #2: GET A POINTER
+
+                let index = (y*w)+x;
+                let pixel = *(rawsrc.add(index));
+                // go from ARGB to BGRA to ABGR
+                let pixel = pixel.swap_bytes().rotate_right(8);  // target format is ARGB ignoring A, and src format is probaby RGBA
+                ptr[index]=pixel;
+
+                /*
+                // GET AT PIXELS ATTEMPT #3: CONVERT EACH ONE TO NSColor tediously -> this works but it is so slow it silently fails because it runs out of time
+
+                // get one pixel as an NSColor -> this works and returns a NSDeviceRGBColorSpace triplet
+                let cspace: *mut Object = msg_send![bitmap, colorAtX:x y:y];
+                //NSLog(NSString::alloc(nil).init_str("COLOR is %@"),cspace);
+
+                // ?can i cast this to become rust visible NSColor? no - because NSColor is a trait and Rust is unable to cast a reference to a trait absurdly
+                // https://stackoverflow.com/questions/34419561/can-i-cast-between-two-traits
+                // http://idubrov.name/rust/2018/06/16/dynamic-casting-traits.html
+                //unsafe {
+                //let testColor = cspace as *NSColor;
+                //let testcolor = NSColor::colorWithRed_green_blue_alpha_(nil, 0.5, 0.3, 0.9, 1.0);
+                //println!("Test color is {}",testcolor.blueComponent());
+                //}
+
+                // try get one color from this in turn - this fails to extract the color - it just returns the whole blob again
+                let r: f64 = msg_send![cspace, redComponent];
+                let g: f64 = msg_send![cspace, greenComponent];
+                let b: f64 = msg_send![cspace, blueComponent];
+
+                let r = (r*255.0) as u32;
+                let g = (g*255.0) as u32;
+                let b = (b*255.0) as u32;
+
+                let c = r*65536 + g*256 + b;
+
+                ptr[y*1280+x]=c;
+                */
+            }
+        }
+        */
+
+        // build a png
+        //if false {
+        //    let filename = format!("result{}.png",Counter::next() );
+        //    let filename = NSString::alloc(nil).init_str(filename.as_str());
+        //    let data: *mut Object = msg_send![bitmap, representationUsingType:4 properties: nil];
+        //    let _: () = msg_send![data, writeToFile: filename atomically: YES];
+        //}
+
+        //let elapsed = now.elapsed();
+        //println!("The Camera paint routine took {:.2?}",elapsed);
+    }
+
+   // });
+
+}
+
+
+
+fn appleWebCamCaptureStart(name: Option<&str>) -> Result<id, String> {
+    unsafe {
+
+        // MAKE A DEVICE - the one asked for by name, or the default one
+        let AVMediaTypeVideo = NSString::alloc(nil).init_str(&"vide".to_string());
+        let device: *mut Object = match name {
+            Some(name) => {
+                let index = match devices().iter().position(|device| device == name) {
+                    Some(index) => index as u64,
+                    None => return Err(format!("there is no webcam called {}", name)),
+                };
+                let devices: *mut Object = msg_send![class!(AVCaptureDevice), devicesWithMediaType:AVMediaTypeVideo ];
+                msg_send![devices, objectAtIndex:index]
+            },
+            None => msg_send![class!(AVCaptureDevice), defaultDeviceWithMediaType:AVMediaTypeVideo ],
+        };
+        if device == nil {
+            return Err("there is no webcam".to_string());
+        }
+        NSLog(NSString::alloc(nil).init_str("Device is %@"),device);
+
+        // MAKE AN INPUT
+        let input: *mut Object = msg_send![class!(AVCaptureDeviceInput), deviceInputWithDevice:device error:0 ]; 
+        NSLog(NSString::alloc(nil).init_str("Input is %@"),input);
+
+        // MAKE AN OUTPUT
+        let output: *mut Object = msg_send![class!(AVCaptureVideoDataOutput),alloc];
+        let output: *mut Object = msg_send![output,init];
+        //let _: () = msg_send![output,alwaysDiscardsLateVideoFrames:YES];
+        //let _: () = msg_send![output,setEnabled:YES]; [[output connectionWithMediaType:AVMediaTypeVideo] setEnabled:YES];
+
+        // MAKE A DISPATCHER
+        let queue = dispatch::ffi::dispatch_get_main_queue();
+        NSLog(NSString::alloc(nil).init_str("queue is %@"),queue);
+
+        // MAKE A CAPTURE HANDLER - the class is only declared the first time round, a device can be opened again
+        if Class::get("MyCapture").is_none() {
+            let mut Capture = ClassDecl::new("MyCapture", class!(NSObject)).unwrap();
+            let protocol = &Protocol::get("AVCaptureVideoDataOutputSampleBufferDelegate").unwrap();
+            Capture.add_protocol(protocol);
+            let magic = sel!(captureOutput: didOutputSampleBuffer: fromConnection:);
+            Capture.add_method(magic, appleWebCamCaptureOutput as extern fn(&Object,Sel, id, id, id));
+            Capture.register();
+        }
+        let Capture = Class::get("MyCapture").unwrap(); // why can't I somehow dereference the one I built above?
+        let capture: *mut Object = msg_send![Capture,alloc];
+        let capture: *mut Object = msg_send![capture,init];
+        NSLog(NSString::alloc(nil).init_str("Capture is %@"),capture);
+        let _: () = msg_send![output, setSampleBufferDelegate:capture queue:queue];
+
+        // MAKE SESSION
+        let session: *mut Object = msg_send![class!(AVCaptureSession),alloc];
+        let session: *mut Object = msg_send![session,init];
+        let _: () = msg_send![session,addInput:input];
+        let _: () = msg_send![session,addOutput:output];
+        let _: () = msg_send![session,startRunning];
+        NSLog(NSString::alloc(nil).init_str("Session is %@"),session);
+        Ok(session)
+   }
+}
+
+
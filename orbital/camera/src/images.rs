@@ -0,0 +1,105 @@
+
+// still pictures as a video source - one png or jpeg shown forever, or a folder of them played in name order
+
+use std::path::{Path, PathBuf};
+
+use crate::source::*;
+
+pub struct Images {
+    files: Vec<PathBuf>,
+    next: usize,
+    // a single picture is only decoded once
+    still: Option<Image>,
+}
+
+impl Images {
+
+    pub fn open(file: &str) -> Result<Images, String> {
+        let path = Path::new(file);
+        if !path.is_dir() {
+            return Ok(Images { files: Vec::new(), next: 0, still: Some(decode_file(path)?) });
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|err| format!("cannot read {}: {}", file, err))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| kind(path).is_some())
+            .collect();
+        if files.is_empty() {
+            return Err(format!("{} has no png or jpeg files", file));
+        }
+        files.sort();
+        Ok(Images { files: files, next: 0, still: None })
+    }
+}
+
+impl VideoSource for Images {
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String> {
+        if let Some(still) = &self.still {
+            still.fit(frame);
+            return Ok(true);
+        }
+        let path = match self.files.get(self.next) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        decode_file(path)?.fit(frame);
+        self.next += 1;
+        Ok(true)
+    }
+}
+
+fn kind(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref() {
+        Some("png") => Some("png"),
+        Some("jpg") | Some("jpeg") => Some("jpeg"),
+        _ => None,
+    }
+}
+
+fn decode_file(path: &Path) -> Result<Image, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let image = match kind(path) {
+        Some("png") => decode_png(&bytes),
+        _ => decode_jpeg(&bytes),
+    };
+    image.map_err(|err| format!("cannot decode {}: {}", path.display(), err))
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|err| err.to_string())?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("palette was not expanded".to_string()),
+    };
+    let pixels = buffer[..width * height * channels].chunks(channels).map(|pixel| match channels {
+        1 | 2 => pack(pixel[0], pixel[0], pixel[0]),
+        _ => pack(pixel[0], pixel[1], pixel[2]),
+    }).collect();
+    Ok(Image { width: width, height: height, pixels: pixels })
+}
+
+pub fn decode_jpeg(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let buffer = decoder.decode().map_err(|err| err.to_string())?;
+    let info = decoder.info().ok_or("no image in jpeg")?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => buffer.iter().map(|l| pack(*l, *l, *l)).collect(),
+        jpeg_decoder::PixelFormat::L16 => buffer.chunks(2).map(|l| pack(l[0], l[0], l[0])).collect(),
+        jpeg_decoder::PixelFormat::RGB24 => buffer.chunks(3).map(|p| pack(p[0], p[1], p[2])).collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => buffer.chunks(4).map(|p| {
+            let k = p[3] as u32;
+            let ink = |c: u8| (c as u32 * k / 255) as u8;
+            pack(ink(p[0]), ink(p[1]), ink(p[2]))
+        }).collect(),
+    };
+    Ok(Image { width: width, height: height, pixels: pixels })
+}
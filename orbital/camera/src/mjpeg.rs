@@ -0,0 +1,110 @@
+
+// motion jpeg - jpegs one after the other. the same reading works for an avi of mjpeg, since each frame sits whole in
+// its chunk: anything between one jpeg's end and the next one's start is skipped.
+
+use std::fs::File;
+use std::io::{BufReader, Bytes, Read};
+
+use crate::images::decode_jpeg;
+use crate::source::*;
+
+pub struct Mjpeg {
+    bytes: Bytes<BufReader<File>>,
+    jpeg: Vec<u8>,
+}
+
+impl Mjpeg {
+    pub fn open(file: &str) -> Result<Mjpeg, String> {
+        let file = File::open(file).map_err(|err| format!("cannot open {}: {}", file, err))?;
+        Ok(Mjpeg { bytes: BufReader::new(file).bytes(), jpeg: Vec::new() })
+    }
+
+    fn byte(&mut self) -> Result<Option<u8>, String> {
+        self.bytes.next().transpose().map_err(|err| err.to_string())
+    }
+
+    // the next whole jpeg in the file, from its start of image marker to its end of image marker - stepping over each
+    // segment by its length, since exif thumbnails are whole jpegs of their own
+    fn next_jpeg(&mut self) -> Result<bool, String> {
+        self.jpeg.clear();
+        let mut last = 0u8;
+        loop {
+            match self.byte()? {
+                Some(0xd8) if last == 0xff => break,
+                Some(byte) => last = byte,
+                None => return Ok(false),
+            }
+        }
+        self.jpeg.extend_from_slice(&[0xff, 0xd8]);
+
+        let mut marker = self.marker()?;
+        loop {
+            match marker {
+                None => return Ok(false),
+                Some(0xd9) => {
+                    self.jpeg.extend_from_slice(&[0xff, 0xd9]);
+                    return Ok(true);
+                },
+                Some(kind) => {
+                    self.jpeg.extend_from_slice(&[0xff, kind]);
+                    let high = self.byte()?.ok_or("jpeg cut short")?;
+                    let low = self.byte()?.ok_or("jpeg cut short")?;
+                    self.jpeg.extend_from_slice(&[high, low]);
+                    let length = ((high as usize) << 8 | low as usize).saturating_sub(2);
+                    for _ in 0..length {
+                        let byte = self.byte()?.ok_or("jpeg cut short")?;
+                        self.jpeg.push(byte);
+                    }
+                    marker = if kind == 0xda { self.scan()? } else { self.marker()? };
+                },
+            }
+        }
+    }
+
+    // the next marker, skipping any padding of 0xff
+    fn marker(&mut self) -> Result<Option<u8>, String> {
+        match self.byte()? {
+            Some(0xff) => { },
+            Some(byte) => return Err(format!("expected a jpeg marker but found {:02x}", byte)),
+            None => return Ok(None),
+        }
+        loop {
+            match self.byte()? {
+                Some(0xff) => { },
+                other => return Ok(other),
+            }
+        }
+    }
+
+    // copy compressed data up to the marker after it - in there a 0xff is followed by 0x00, or is a restart marker
+    fn scan(&mut self) -> Result<Option<u8>, String> {
+        loop {
+            let byte = match self.byte()? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+            if byte != 0xff {
+                self.jpeg.push(byte);
+                continue;
+            }
+            let mut next = self.byte()?;
+            while next == Some(0xff) {
+                next = self.byte()?;
+            }
+            match next {
+                Some(code) if code == 0x00 || (0xd0..=0xd7).contains(&code) => self.jpeg.extend_from_slice(&[0xff, code]),
+                other => return Ok(other),
+            }
+        }
+    }
+}
+
+impl VideoSource for Mjpeg {
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String> {
+        if !self.next_jpeg()? {
+            return Ok(false);
+        }
+        decode_jpeg(&self.jpeg)?.fit(frame);
+        Ok(true)
+    }
+}
@@ -0,0 +1,68 @@
+
+// a made up video source for when there is no camera - eight color bars with a white box sweeping across them, so
+// anything watching can tell frames apart. the same frame number always gives the same picture.
+
+use crate::source::*;
+
+const BARS: [(u8, u8, u8); 8] = [
+    (255, 255, 255), (255, 255, 0), (0, 255, 255), (0, 255, 0),
+    (255, 0, 255), (255, 0, 0), (0, 0, 255), (0, 0, 0),
+];
+
+const BOX: usize = 160;
+const STEP: usize = 16;
+
+pub struct Pattern {
+    count: usize,
+    // drawn at this size and then scaled, to stand in for a camera that was asked for another resolution
+    image: Image,
+}
+
+impl Pattern {
+    pub fn new() -> Pattern {
+        Pattern { count: 0, image: Image { width: FRAME_WIDTH, height: FRAME_HEIGHT, pixels: vec![0; FRAME_WIDTH * FRAME_HEIGHT] } }
+    }
+}
+
+impl VideoSource for Pattern {
+
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String> {
+        draw(self.count, &mut self.image);
+        self.image.fit(frame);
+        self.count += 1;
+        Ok(true)
+    }
+
+    fn fps(&self) -> Option<f64> { Some(10.0) }
+
+    fn resolution(&self) -> Option<(usize, usize)> { Some((self.image.width, self.image.height)) }
+
+    fn set_resolution(&mut self, width: usize, height: usize) -> Result<(), String> {
+        if width < BARS.len() || height < 1 || width * height > 4096 * 4096 {
+            return Err(format!("the pattern cannot be drawn at {}x{}", width, height));
+        }
+        self.image = Image { width: width, height: height, pixels: vec![0; width * height] };
+        Ok(())
+    }
+}
+
+/// Draw frame number count of the pattern, with the box scaled to the image
+pub fn draw(count: usize, image: &mut Image) {
+    let (width, height) = (image.width, image.height);
+    let bar = width / BARS.len();
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = BARS[(x / bar).min(BARS.len() - 1)];
+            image.pixels[y * width + x] = pack(r, g, b);
+        }
+    }
+    let size = (BOX * height / FRAME_HEIGHT).max(1).min(width);
+    let step = (STEP * width / FRAME_WIDTH).max(1);
+    let left = if width > size { (count * step) % (width - size) } else { 0 };
+    let top = (height - size.min(height)) / 2;
+    for y in top..(top + size).min(height) {
+        for x in left..left + size {
+            image.pixels[y * width + x] = pack(255, 255, 255);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// video sources - anything that can fill a frame for the camera to share
+/////////////////////////////////////////////////////////////////////////////////////////
+
+//
+// frames are always 1280x720 u32 pixels with red in the low byte, then green, then blue, then alpha - which is what
+// the webcam delivers and what everyone listening to /frames expects. sources of any other size are scaled to fit.
+//
+// the camera manifest picks the source:
+//
+//     source = avfoundation    # the mac webcam
+//     source = pattern         # color bars with a box moving across them, made up on the spot
+//     source = y4m             # a yuv4mpeg2 file, as ffmpeg writes with -f yuv4mpegpipe
+//     source = mjpeg           # a stream of jpegs one after another, or an avi of them
+//     source = images          # a png or jpeg, or a folder of them played in name order
+//     file = ../public/video/walk.y4m
+//
+// for the webcam file can name which one to use, otherwise it is the default one.
+//
+
+use service::Manifest;
+
+pub const FRAME_WIDTH: usize = 1280;
+pub const FRAME_HEIGHT: usize = 720;
+
+pub trait VideoSource: Send {
+    /// Fill a FRAME_WIDTH by FRAME_HEIGHT frame with the next picture, or say there are no more
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String>;
+    /// How many frames a second the source is meant to be played at, if it knows
+    fn fps(&self) -> Option<f64> { None }
+    /// How big the pictures are before they are scaled into a frame, if it knows
+    fn resolution(&self) -> Option<(usize, usize)> { None }
+    /// Capture pictures of a different size - they are still scaled to fill a frame
+    fn set_resolution(&mut self, _width: usize, _height: usize) -> Result<(), String> {
+        Err("this source cannot change its resolution".to_string())
+    }
+    /// Nobody wants frames for now - a source that is doing work on its own can stop
+    fn pause(&mut self) {}
+    fn resume(&mut self) {}
+}
+
+/// Open the source the manifest asks for
+pub fn open_manifest(manifest: &Manifest) -> Result<Box<dyn VideoSource>, String> {
+    let kind = manifest.get("source").unwrap_or(if cfg!(target_os = "macos") { "avfoundation" } else { "pattern" });
+    open(kind, manifest.get("file"))
+}
+
+/// Open a source by kind - file is what a file source plays, or which webcam to use by name
+pub fn open(kind: &str, file: Option<&str>) -> Result<Box<dyn VideoSource>, String> {
+    let file = || file.ok_or(format!("a {} source needs a file", kind));
+    match kind {
+        #[cfg(target_os = "macos")]
+        "avfoundation" => Ok(Box::new(crate::avfoundation::AVFoundation::open(file().ok())?)),
+        #[cfg(not(target_os = "macos"))]
+        "avfoundation" => Err("avfoundation is only on macos".to_string()),
+        "pattern" => Ok(Box::new(crate::pattern::Pattern::new())),
+        "y4m" => Ok(Box::new(crate::y4m::Y4m::open(file()?)?)),
+        "mjpeg" => Ok(Box::new(crate::mjpeg::Mjpeg::open(file()?)?)),
+        "images" => Ok(Box::new(crate::images::Images::open(file()?)?)),
+        _ => Err(format!("unknown video source {}", kind)),
+    }
+}
+
+/// Every device that can be opened without a file, as "<kind>" or "<kind> <name>"
+pub fn devices() -> Vec<String> {
+    let mut devices = Vec::new();
+    #[cfg(target_os = "macos")]
+    {
+        for name in crate::avfoundation::devices() {
+            devices.push(format!("avfoundation {}", name));
+        }
+    }
+    devices.push("pattern".to_string());
+    devices
+}
+
+pub fn pack(r: u8, g: u8, b: u8) -> u32 {
+    r as u32 | (g as u32) << 8 | (b as u32) << 16 | 0xff00_0000
+}
+
+/// A picture of any size, before it is scaled into a frame
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+
+    /// Scale to fill the frame, picking the nearest pixel
+    pub fn fit(&self, frame: &mut [u32]) {
+        if self.width == FRAME_WIDTH && self.height == FRAME_HEIGHT {
+            frame.copy_from_slice(&self.pixels);
+            return;
+        }
+        for y in 0..FRAME_HEIGHT {
+            let sy = y * self.height / FRAME_HEIGHT;
+            for x in 0..FRAME_WIDTH {
+                let sx = x * self.width / FRAME_WIDTH;
+                frame[y * FRAME_WIDTH + x] = self.pixels[sy * self.width + sx];
+            }
+        }
+    }
+}
+
+/// Turn a y, u and v sample into a pixel, as studio range bt.601 - which is what y4m files almost always are
+pub fn yuv(y: u8, u: u8, v: u8) -> u32 {
+    let c = (y as i32 - 16) * 298;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).max(0).min(255) as u8;
+    pack(clamp(c + 409 * e), clamp(c - 100 * d - 208 * e), clamp(c + 516 * d))
+}
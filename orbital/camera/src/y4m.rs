@@ -0,0 +1,123 @@
+
+// yuv4mpeg2 files - a text header, then each frame is a FRAME line followed by the y plane and any u and v planes
+//
+//     YUV4MPEG2 W640 H480 F30:1 Ip A1:1 C420jpeg
+//     FRAME
+//     <640*480 bytes of y><320*240 bytes of u><320*240 bytes of v>
+//
+// ffmpeg -i clip.mp4 -pix_fmt yuv420p clip.y4m makes one.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::source::*;
+
+pub struct Y4m {
+    reader: BufReader<File>,
+    width: usize,
+    height: usize,
+    // how many luma samples across and down each chroma sample covers - none for mono
+    chroma: Option<(usize, usize)>,
+    fps: Option<f64>,
+    image: Image,
+    planes: Vec<u8>,
+}
+
+impl Y4m {
+
+    pub fn open(file: &str) -> Result<Y4m, String> {
+        let mut reader = BufReader::new(File::open(file).map_err(|err| format!("cannot open {}: {}", file, err))?);
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|err| format!("cannot read {}: {}", file, err))?;
+        let mut words = header.trim_end().split(' ');
+        if words.next() != Some("YUV4MPEG2") {
+            return Err(format!("{} is not a y4m file", file));
+        }
+
+        let (mut width, mut height, mut chroma, mut fps) = (0, 0, Some((2, 2)), None);
+        for word in words {
+            let (tag, value) = word.split_at(1.min(word.len()));
+            match tag {
+                "W" => width = value.parse().map_err(|_| format!("bad width {}", value))?,
+                "H" => height = value.parse().map_err(|_| format!("bad height {}", value))?,
+                "F" => {
+                    let mut parts = value.split(':').map(|part| part.parse::<f64>());
+                    if let (Some(Ok(n)), Some(Ok(d))) = (parts.next(), parts.next()) {
+                        if n > 0.0 && d > 0.0 {
+                            fps = Some(n / d);
+                        }
+                    }
+                },
+                "C" => chroma = match value {
+                    "420" | "420jpeg" | "420paldv" | "420mpeg2" => Some((2, 2)),
+                    "422" => Some((2, 1)),
+                    "444" => Some((1, 1)),
+                    "mono" => None,
+                    _ => return Err(format!("{} uses colorspace {} which is not supported", file, value)),
+                },
+                _ => { },
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err(format!("{} does not say how big it is", file));
+        }
+
+        Ok(Y4m {
+            reader: reader,
+            width: width,
+            height: height,
+            chroma: chroma,
+            fps: fps,
+            image: Image { width: width, height: height, pixels: vec![0; width * height] },
+            planes: Vec::new(),
+        })
+    }
+}
+
+impl VideoSource for Y4m {
+
+    fn read(&mut self, frame: &mut [u32]) -> Result<bool, String> {
+        let mut line = String::new();
+        let read = self.reader.read_line(&mut line).map_err(|err| err.to_string())?;
+        if read == 0 {
+            return Ok(false);
+        }
+        if !line.starts_with("FRAME") {
+            return Err(format!("expected a FRAME but found {}", line.trim_end()));
+        }
+
+        let (width, height) = (self.width, self.height);
+        let (cw, ch) = match self.chroma {
+            Some((sx, sy)) => ((width + sx - 1) / sx, (height + sy - 1) / sy),
+            None => (0, 0),
+        };
+        self.planes.resize(width * height + 2 * cw * ch, 0);
+        match self.reader.read_exact(&mut self.planes) {
+            Ok(()) => { },
+            // a file cut off part way through a frame just ends there
+            Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err.to_string()),
+        }
+
+        let (luma, rest) = self.planes.split_at(width * height);
+        let (u, v) = rest.split_at(cw * ch);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = match self.chroma {
+                    Some((sx, sy)) => {
+                        let c = (y / sy) * cw + x / sx;
+                        yuv(luma[y * width + x], u[c], v[c])
+                    },
+                    None => yuv(luma[y * width + x], 128, 128),
+                };
+                self.image.pixels[y * width + x] = pixel;
+            }
+        }
+        self.image.fit(frame);
+        Ok(true)
+    }
+
+    fn fps(&self) -> Option<f64> { self.fps }
+
+    fn resolution(&self) -> Option<(usize, usize)> { Some((self.width, self.height)) }
+}
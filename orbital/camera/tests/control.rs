@@ -0,0 +1,128 @@
+
+// driving the camera with commands on /camera, against the test pattern
+
+use crossbeam::channel::*;
+use std::time::{Duration, Instant};
+
+use broker::*;
+use camera::*;
+use service::*;
+
+const PROBE: SID = 1;
+const CAMERA_SID: SID = 2;
+
+fn start() -> (Sender<Message>, Receiver<Message>) {
+    let dir = std::env::temp_dir().join(format!("orbital-camera-control-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("media")).unwrap();
+    let manifest = dir.join("camera.manifest");
+    std::fs::write(&manifest, format!("source = pattern\nfps = 50\nmedia = {}\n", dir.join("media").display())).unwrap();
+
+    // one frame of 2x2 video to play
+    let mut still = b"YUV4MPEG2 W2 H2 F25:1 Ip A1:1 C444\nFRAME\n".to_vec();
+    still.extend_from_slice(&[128; 12]);
+    std::fs::write(dir.join("media").join("still.y4m"), still).unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,STATUS.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,DEVICES.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Camera::with_manifest(manifest.to_str().unwrap());
+    brokersend.send(Message::Channel(CAMERA_SID,"camera".to_string(),localsend)).unwrap();
+    instance.start("camera".to_string(),CAMERA_SID,brokersend.clone(),localrecv);
+
+    // the camera subscribes from its own thread, so wait until it is listening before telling it anything
+    brokersend.send(Message::Subscribe(PROBE,SUBSCRIBERS.to_string())).unwrap();
+    loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("the camera never subscribed") {
+            Message::Event(topic,data) if topic == SUBSCRIBERS && data == format!("{} 1",CAMERA) => break,
+            _ => { },
+        }
+    }
+    (brokersend, proberecv)
+}
+
+fn command(send: &Sender<Message>, recv: &Receiver<Message>, command: &str) -> (String,String) {
+    send.send(Message::Event(CAMERA.to_string(),command.to_string())).unwrap();
+    loop {
+        match recv.recv_timeout(Duration::from_secs(10)).expect("no answer from the camera") {
+            Message::Event(topic,_) if topic == SUBSCRIBERS => { },
+            Message::Event(topic,data) => return (topic,data),
+            _ => { },
+        }
+    }
+}
+
+// how many frames arrive over a while
+fn frames(recv: &Receiver<Message>, wait: Duration) -> usize {
+    let until = Instant::now() + wait;
+    let mut count = 0;
+    while let Ok(message) = recv.recv_deadline(until) {
+        if let Message::Share(_,_) = message {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[test]
+fn frames_only_flow_while_wanted_and_not_paused() {
+    let (send, recv) = start();
+
+    // nobody is subscribed to frames yet
+    let (_, status) = command(&send, &recv, "status");
+    assert!(status.contains("\"capturing\":false"), "{}", status);
+    assert_eq!(frames(&recv, Duration::from_millis(200)), 0);
+
+    send.send(Message::Subscribe(PROBE,"/frames".to_string())).unwrap();
+    assert!(frames(&recv, Duration::from_millis(200)) > 0);
+
+    // paused, only a still comes through
+    let (_, status) = command(&send, &recv, "pause");
+    assert!(status.contains("\"paused\":true") && status.contains("\"capturing\":false"), "{}", status);
+    frames(&recv, Duration::from_millis(100));
+    send.send(Message::Event(CAMERA.to_string(),"still".to_string())).unwrap();
+    assert_eq!(frames(&recv, Duration::from_millis(300)), 1);
+
+    let (_, status) = command(&send, &recv, "resume");
+    assert!(status.contains("\"capturing\":true"), "{}", status);
+
+    // and it stops again once nobody wants frames
+    send.send(Message::Unsubscribe(PROBE,"/frames".to_string())).unwrap();
+    let (_, status) = command(&send, &recv, "status");
+    assert!(status.contains("\"capturing\":false"), "{}", status);
+}
+
+#[test]
+fn settings_can_be_changed_while_running() {
+    let (send, recv) = start();
+
+    let (topic, devices) = command(&send, &recv, "devices");
+    assert_eq!(topic, DEVICES);
+    assert!(devices.contains("\"pattern\""), "{}", devices);
+
+    let (_, status) = command(&send, &recv, "fps 25");
+    assert!(status.contains("\"fps\":25"), "{}", status);
+
+    let (_, status) = command(&send, &recv, "resolution 640x360");
+    assert!(status.contains("\"width\":640,\"height\":360"), "{}", status);
+
+    // a bad command is logged and changes nothing
+    let (topic, _) = command(&send, &recv, "resolution big");
+    assert_eq!(topic, "/log");
+    let (topic, _) = command(&send, &recv, "device y4m /no/such/file.y4m");
+    assert_eq!(topic, "/log");
+
+    // files are played from the media folder and nowhere else
+    let (topic, log) = command(&send, &recv, "device y4m ../camera.manifest");
+    assert!(topic == "/log" && log.contains("is not in the media folder"), "{}", log);
+    let (_, status) = command(&send, &recv, "device y4m still.y4m");
+    assert!(status.contains("\"device\":\"y4m\"") && status.contains("still.y4m"), "{}", status);
+    let (_, status) = command(&send, &recv, "device pattern");
+    assert!(status.contains("\"device\":\"pattern\"") && status.contains("\"width\":1280"), "{}", status);
+}
@@ -0,0 +1,103 @@
+
+// the video sources that need no camera, so the vision pipeline can be run on any box
+
+use crossbeam::channel::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use broker::*;
+use camera::*;
+use service::*;
+
+const PROBE: SID = 1;
+const CAMERA: SID = 2;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orbital-camera-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read(source: &mut dyn VideoSource) -> Vec<u32> {
+    let mut frame = vec![0u32; FRAME_WIDTH * FRAME_HEIGHT];
+    assert_eq!(source.read(&mut frame), Ok(true));
+    frame
+}
+
+#[test]
+fn pattern_frames_are_shared_on_frames() {
+    let dir = scratch("pattern");
+    let manifest = dir.join("camera.manifest");
+    std::fs::write(&manifest, "source = pattern\nfps = 50\n").unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/frames".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Camera::with_manifest(manifest.to_str().unwrap());
+    brokersend.send(Message::Channel(CAMERA,"camera".to_string(),localsend)).unwrap();
+    instance.start("camera".to_string(),CAMERA,brokersend.clone(),localrecv);
+
+    let mut frames = Vec::new();
+    while frames.len() < 2 {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no frame from the camera") {
+            Message::Share(_,sharedmemory) => frames.push(sharedmemory.lock().unwrap().to_vec()),
+            _ => { },
+        }
+    }
+
+    // the first bar is white and the last is black, and the box has moved on between frames
+    assert_eq!(frames[0][0], pack(255,255,255));
+    assert_eq!(frames[0][FRAME_WIDTH-1], pack(0,0,0));
+    assert_ne!(frames[0], frames[1]);
+}
+
+#[test]
+fn y4m_frames_are_converted_and_scaled() {
+    let dir = scratch("y4m");
+    let file = dir.join("clip.y4m");
+
+    // two 2x2 frames, one color each: gray then pure-ish red, as 444 so every pixel has its own chroma
+    let mut bytes = b"YUV4MPEG2 W2 H2 F25:1 Ip A1:1 C444\n".to_vec();
+    bytes.extend_from_slice(b"FRAME\n");
+    bytes.extend_from_slice(&[126; 4]);
+    bytes.extend_from_slice(&[128; 4]);
+    bytes.extend_from_slice(&[128; 4]);
+    bytes.extend_from_slice(b"FRAME\n");
+    bytes.extend_from_slice(&[81; 4]);
+    bytes.extend_from_slice(&[90; 4]);
+    bytes.extend_from_slice(&[240; 4]);
+    std::fs::write(&file, bytes).unwrap();
+
+    let mut source = y4m::Y4m::open(file.to_str().unwrap()).unwrap();
+    assert_eq!(source.fps(), Some(25.0));
+    let gray = read(&mut source);
+    assert!(gray.iter().all(|pixel| *pixel == pack(128,128,128)));
+    let red = read(&mut source);
+    let (r, g, b) = (red[0] & 0xff, (red[0] >> 8) & 0xff, (red[0] >> 16) & 0xff);
+    assert!(r > 240 && g < 10 && b < 10, "expected red but got {} {} {}", r, g, b);
+    assert_eq!(source.read(&mut vec![0u32; FRAME_WIDTH * FRAME_HEIGHT]), Ok(false));
+}
+
+#[test]
+fn folders_of_pngs_play_in_name_order() {
+    let dir = scratch("images");
+    for (name, rgb) in &[("b.png", [0u8, 0, 255]), ("a.png", [255u8, 0, 0])] {
+        let file = std::fs::File::create(dir.join(name)).unwrap();
+        let mut encoder = png::Encoder::new(file, 4, 4);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&rgb.repeat(16)).unwrap();
+    }
+
+    let mut source = images::Images::open(dir.to_str().unwrap()).unwrap();
+    assert_eq!(read(&mut source)[0], pack(255,0,0));
+    assert_eq!(read(&mut source)[FRAME_WIDTH*FRAME_HEIGHT-1], pack(0,0,255));
+    assert_eq!(source.read(&mut vec![0u32; FRAME_WIDTH * FRAME_HEIGHT]), Ok(false));
+}
@@ -22,6 +22,9 @@ struct MyProperties {
 	y: f32,
 }
 
+// which app asked for an entity - so that everything an app made can be cleared away when it reloads or quits
+struct Owner(String);
+
 //////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Clone)]
@@ -88,18 +91,32 @@ fn listen_to_messages(
 	mut assets: Res<AssetServer>,
 	mut meshes: ResMut<Assets<Mesh>>,
 	mut materials: ResMut<Assets<StandardMaterial>>,
-	mut mystate: ResMut<AWayToHaveGlobalState>
+	mut mystate: ResMut<AWayToHaveGlobalState>,
+	owned: Query<(Entity, &Owner)>
 ) {
     while let Ok(message) = mystate.receiver.try_recv() {
         match message {
             Message::Event(topic,data) => {
                 println!("Graphics: Received: {} {}",topic, data);
-                match data.as_str() {
+
+                // requests may be tagged with their owner as "cube @owner"
+                let (data, owner) = service::owner(&data);
+                let owner = owner.unwrap_or("").to_string();
+
+                match data {
+                    "clear" => {
+                        for (entity, other) in owned.iter() {
+                            if other.0 == owner {
+                                commands.entity(entity).despawn_recursive();
+                            }
+                        }
+                    },
                     "camera" => {
 						commands.spawn_bundle(PerspectiveCameraBundle {
 							transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
 							..Default::default()
 						})
+						.insert(Owner(owner))
 					    .insert(OrbitCamera::default())
 						.insert_bundle(PickingCameraBundle::default());
                     },
@@ -107,14 +124,16 @@ fn listen_to_messages(
 						commands.spawn_bundle(PointLightBundle {
 							transform: Transform::from_xyz(4.0, 8.0, 4.0),
 							..Default::default()
-						});
+						})
+						.insert(Owner(owner));
                     },
                     "plane" => {
 						commands.spawn_bundle(PbrBundle {
 							mesh: meshes.add(Mesh::from(shape::Plane { size: 5.0 })),
 							material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
 							..Default::default()
-						});
+						})
+						.insert(Owner(owner));
                     },
                 	"load" => {
                     },
@@ -127,6 +146,7 @@ fn listen_to_messages(
 						})
 						
 						.insert(MyProperties{ x:3.0, y:3.0 })
+						.insert(Owner(owner))
 						.insert_bundle(PickableBundle::default());
                     },
                     "move" => {
@@ -135,10 +155,13 @@ fn listen_to_messages(
                     	// note meshes need vertex tangents (just use blender) -> https://github.com/bevyengine/bevy/issues/121
                     	println!("loading from disk");
 
-						let path = "../../../public/".to_string() + data.as_str() + "#Scene0";
+						let path = "../../../public/".to_string() + data + "#Scene0";
 
+					    // the scene hangs off an owned parent so that it can be cleared along with everything else
 					    let stuff: Handle<Scene> = assets.load(path.as_str());
-					    commands.spawn_scene(stuff);
+					    commands.spawn_bundle((Transform::default(), GlobalTransform::default()))
+					    	.insert(Owner(owner))
+					    	.with_children(|parent| { parent.spawn_scene(stuff); });
 /*
 
 						let mesh: Handle<Mesh> = assets.load(path.as_str());
@@ -0,0 +1,26 @@
+
+// a small orbital app: puts a cube in the scene and answers anything said to it on /hello
+//
+// cargo build --example hello --target wasm32-unknown-unknown --release
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use guest::*;
+
+// state lives as long as the app does
+static GREETED: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+pub extern "C" fn run() {
+    subscribe("/hello");
+    scene::camera();
+    scene::light();
+    scene::cube();
+}
+
+fn handle(_topic: &str, payload: &str) {
+    let count = GREETED.fetch_add(1, Ordering::SeqCst) + 1;
+    publish("/hello/reply", &format!("hello {} ({})", payload, count));
+}
+
+on_message!(handle);
@@ -0,0 +1,25 @@
+// helpers for making sounds - each is one request on /audio, see orbital/audio
+
+use crate::publish;
+
+const AUDIO: &str = "/audio";
+
+/// Play a wav file from public/sounds, ie play("ding.wav")
+pub fn play(file: &str) {
+    publish(AUDIO, &format!("play {}", file));
+}
+
+/// Play a beep of some pitch for some milliseconds
+pub fn tone(hertz: f32, ms: u32) {
+    publish(AUDIO, &format!("tone {} {}", hertz, ms));
+}
+
+/// Stop every sound this app has asked for
+pub fn stop() {
+    publish(AUDIO, "stop");
+}
+
+/// How loud sounds are played from now on, from 0 to 1
+pub fn volume(volume: f32) {
+    publish(AUDIO, &format!("volume {}", volume));
+}
@@ -0,0 +1,110 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// The guest side of the orbital wasm host abi - see orbital/wasm/src/host.rs
+//
+// an app built against this needs no unsafe code of its own:
+//
+//     use guest::*;
+//
+//     #[no_mangle]
+//     pub extern "C" fn run() {
+//         subscribe("/cubes");
+//         scene::cube();
+//     }
+//
+//     fn handle(topic: &str, payload: &str) {
+//         publish("/display", payload);
+//     }
+//
+//     on_message!(handle);
+//
+// build it with cargo build --target wasm32-unknown-unknown --release and hand the .wasm to the Wasm service.
+//
+//////////////////////////////////////////////////////////////////////////
+
+pub mod audio;
+pub mod pixels;
+pub mod scene;
+
+#[doc(hidden)]
+pub mod abi {
+    #[link(wasm_import_module = "orbital")]
+    extern "C" {
+        pub fn publish(topic_ptr: *const u8, topic_len: usize, payload_ptr: *const u8, payload_len: usize);
+        pub fn subscribe(topic_ptr: *const u8, topic_len: usize);
+        pub fn call(target_ptr: *const u8, target_len: usize, args_ptr: *const u8, args_len: usize) -> i32;
+        pub fn result(ptr: *const u8, len: usize);
+    }
+}
+
+/// Send a message to anyone subscribed to topic
+pub fn publish(topic: &str, payload: &str) {
+    unsafe { abi::publish(topic.as_ptr(), topic.len(), payload.as_ptr(), payload.len()) }
+}
+
+/// Ask for traffic on topic to be handed to the on_message handler
+pub fn subscribe(topic: &str) {
+    unsafe { abi::subscribe(topic.as_ptr(), topic.len()) }
+}
+
+/// Call a function another app offers, named "<app>.<function>" and declared in this apps manifest; args are laid out as
+/// orbital/wasm/src/interface.rs describes. the answer comes back through an on_reply export with the returned id
+pub fn call(target: &str, args: &[u8]) -> i32 {
+    unsafe { abi::call(target.as_ptr(), target.len(), args.as_ptr(), args.len()) }
+}
+
+/// Hand back the result of a function this app offers, before returning from it
+pub fn result(bytes: &[u8]) {
+    unsafe { abi::result(bytes.as_ptr(), bytes.len()) }
+}
+
+// the host asks for space to write each inbound message into and hands it back afterwards
+
+#[doc(hidden)]
+pub fn alloc(len: usize) -> *mut u8 {
+    // nothing to write needs no space, and the allocator may not be asked for none
+    if len == 0 {
+        return std::ptr::NonNull::dangling().as_ptr();
+    }
+    let layout = std::alloc::Layout::array::<u8>(len).unwrap();
+    let ptr = unsafe { std::alloc::alloc(layout) };
+    if ptr.is_null() {
+        std::alloc::handle_alloc_error(layout);
+    }
+    ptr
+}
+
+#[doc(hidden)]
+pub unsafe fn dealloc(ptr: *mut u8, len: usize) {
+    if len != 0 {
+        std::alloc::dealloc(ptr, std::alloc::Layout::array::<u8>(len).unwrap());
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn text<'a>(ptr: *const u8, len: usize) -> &'a str {
+    // the host only ever writes utf8 but a guest should not fall over if that ever changes
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap_or("")
+}
+
+/// Export everything the host needs to hand an app its messages, passing each to handler(topic, payload)
+#[macro_export]
+macro_rules! on_message {
+    ($handler:path) => {
+        #[no_mangle]
+        pub extern "C" fn alloc(len: usize) -> *mut u8 {
+            $crate::alloc(len)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn dealloc(ptr: *mut u8, len: usize) {
+            $crate::dealloc(ptr, len)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn on_message(topic_ptr: *const u8, topic_len: usize, payload_ptr: *const u8, payload_len: usize) {
+            $handler($crate::text(topic_ptr, topic_len), $crate::text(payload_ptr, payload_len));
+        }
+    };
+}
@@ -0,0 +1,35 @@
+
+// helpers for drawing in 2d - each is one request on /pixels, see orbital/viewpixels. shapes have ids so that sending
+// one again with the same id moves or changes it
+
+use crate::publish;
+
+const PIXELS: &str = "/pixels";
+
+/// Draw a shape, given as json fields, ie draw("rect", "panel", r#""x":10,"y":10,"w":100,"h":60,"fill":"#203040""#)
+pub fn draw(op: &str, id: &str, fields: &str) {
+    let id = id.replace('\\', "\\\\").replace('"', "\\\"");
+    let comma = if fields.is_empty() { "" } else { "," };
+    publish(PIXELS, &format!("{{\"op\":\"{}\",\"id\":\"{}\"{}{}}}", op, id, comma, fields));
+}
+
+/// Write some words with their top left corner at x, y
+pub fn text(id: &str, x: f32, y: f32, text: &str, size: f32) {
+    let text = text.replace('\\', "\\\\").replace('"', "\\\"");
+    draw("text", id, &format!("\"x\":{},\"y\":{},\"text\":\"{}\",\"size\":{}", x, y, text, size));
+}
+
+/// Show the camera, stretched into a rectangle
+pub fn image(id: &str, x: i32, y: i32, w: i32, h: i32) {
+    draw("image", id, &format!("\"x\":{},\"y\":{},\"w\":{},\"h\":{}", x, y, w, h));
+}
+
+/// Take away a shape
+pub fn delete(id: &str) {
+    draw("delete", id, "");
+}
+
+/// Take away everything this app has drawn
+pub fn clear() {
+    publish(PIXELS, "{\"op\":\"clear\"}");
+}
@@ -0,0 +1,68 @@
+
+// helpers for putting things in the 3d scene - each is one request on /display
+
+use crate::publish;
+
+const DISPLAY: &str = "/display";
+
+pub fn camera() {
+    publish(DISPLAY, "camera");
+}
+
+pub fn light() {
+    publish(DISPLAY, "light");
+}
+
+pub fn plane() {
+    publish(DISPLAY, "plane");
+}
+
+pub fn cube() {
+    publish(DISPLAY, "cube");
+}
+
+/// Load a gltf scene that sits in public/, ie load("anselm2.glb")
+pub fn load(file: &str) {
+    publish(DISPLAY, file);
+}
+
+// the scene graph proper - nodes with ids that can be changed and taken away again, see orbital/scene. an app's nodes
+// are placed from the middle of the region of the scene it is given, and have to stay inside it
+
+const SCENE: &str = "/scene";
+
+/// Add a node, given as json fields, ie create("earth", r#""parent":"sun","mesh":"sphere","position":[2,0,0]"#)
+pub fn create(id: &str, fields: &str) {
+    publish(SCENE, &request("create", id, fields));
+}
+
+/// Change some fields of a node, ie update("earth", r#""position":[0,0,2]"#)
+pub fn update(id: &str, fields: &str) {
+    publish(SCENE, &request("update", id, fields));
+}
+
+/// Take away a node and everything under it
+pub fn delete(id: &str) {
+    publish(SCENE, &request("delete", id, ""));
+}
+
+/// Take away everything this app has put in the scene
+pub fn clear() {
+    publish(SCENE, "{\"op\":\"clear\"}");
+}
+
+/// Ask something about the scene, ie query(1, "/myapp/answers", r#""query":"find","tag":"planet""#) - the answer
+/// comes back on the reply topic as {"id":1,"ok":..}, so subscribe to it first
+pub fn query(id: u64, reply: &str, fields: &str) {
+    let reply = reply.replace('\\', "\\\\").replace('"', "\\\"");
+    publish("/scene/query", &format!("{{\"id\":{},\"reply\":\"{}\",{}}}", id, reply, fields));
+}
+
+fn request(op: &str, id: &str, fields: &str) -> String {
+    let id = id.replace('\\', "\\\\").replace('"', "\\\"");
+    if fields.trim().is_empty() {
+        format!("{{\"op\":\"{}\",\"id\":\"{}\"}}", op, id)
+    } else {
+        format!("{{\"op\":\"{}\",\"id\":\"{}\",{}}}", op, id, fields)
+    }
+}
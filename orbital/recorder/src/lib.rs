@@ -0,0 +1,281 @@
+
+//
+// Recorder: writes the frames going by on /frames to disk, so a session can be captured once and played back later
+//
+// it takes commands as text on /recorder:
+//
+//     start [format] [name]   start recording - format is png, y4m or mjpeg, and name is what the files are called
+//     stop                    finish the recording
+//     segment                 finish the file being written and carry on in a new one
+//     status                  say how things stand
+//
+// and says how things stand on /recorder/status after each one, as json. it only subscribes to frames while it is
+// recording, so the camera can rest otherwise. settings are in public/recorder.manifest.
+//
+// every file gets a <name>-<segment>.timestamps file next to it with the frame number and the time it was recorded,
+// in milliseconds like the timestamps on /faces. a recording plays back through the camera's file sources, frame for
+// frame, so whatever watches /frames sees the same pictures in the same order every time.
+//
+
+use crossbeam::channel::*;
+use service::*;
+use service::interface::quote;
+use service::vision::timestamp;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub mod writer;
+
+use writer::Writer;
+
+pub const FRAME_WIDTH: usize = 1280;
+pub const FRAME_HEIGHT: usize = 720;
+
+pub const RECORDER: &str = "/recorder";
+pub const STATUS: &str = "/recorder/status";
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    // where recordings go
+    pub folder: String,
+    pub format: String,
+    // which frames to record - the camera shares on /frames, and anything else that makes frames on a topic of its own
+    pub topic: String,
+    // frames a second written into the file headers - should match the camera
+    pub fps: f64,
+    // jpeg quality for mjpeg, 1 to 100
+    pub quality: u8,
+    // start a new file every this many seconds, or never if 0
+    pub segment: u64,
+}
+
+impl Settings {
+    pub fn from_manifest(manifest: &Manifest) -> Settings {
+        Settings {
+            folder: manifest.get("folder").unwrap_or("../public/recordings").to_string(),
+            format: manifest.get("format").unwrap_or("y4m").to_string(),
+            topic: manifest.get("topic").unwrap_or("/frames").to_string(),
+            fps: manifest.get_or("fps", 10.0_f64).max(0.1),
+            quality: manifest.get_or("quality", 85),
+            segment: manifest.get_or("segment", 0),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Recorder {
+    manifest: String,
+}
+
+impl Recorder {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/recorder.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for Recorder {
+    fn name(&self) -> &str { "Recorder" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("Recorder: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let settings = Settings::from_manifest(&manifest);
+
+            send.send(Message::Subscribe(sid, RECORDER.to_string())).expect("error");
+
+            let mut session = Session {
+                send: send,
+                sid: sid,
+                format: settings.format.clone(),
+                settings: settings,
+                name: String::new(),
+                segment: 0,
+                take: None,
+                file: None,
+                frames: 0,
+                buffer: vec![0; FRAME_WIDTH * FRAME_HEIGHT],
+            };
+            while let Ok(message) = recv.recv() {
+                match message {
+                    Message::Share(topic, sharedmemory) => {
+                        if session.take.is_none() || topic != session.settings.topic {
+                            continue;
+                        }
+                        let timestamp = timestamp();
+
+                        // take a copy and let go of the frame before writing it out
+                        let ptr = sharedmemory.lock().unwrap();
+                        session.buffer.copy_from_slice(&ptr[..]);
+                        drop(ptr);
+
+                        if let Err(err) = session.record(timestamp) {
+                            session.log(&format!("Recorder: {}", err));
+                            let _ = session.stop();
+                            session.publish(STATUS, session.status());
+                        }
+                    },
+                    Message::Event(topic, data) if topic == RECORDER => {
+                        match session.command(data.trim()) {
+                            Ok(()) => session.publish(STATUS, session.status()),
+                            Err(err) => session.log(&format!("Recorder: {}: {}", data.trim(), err)),
+                        }
+                    },
+                    _ => { },
+                }
+            }
+        });
+    }
+}
+
+// one file being written
+struct Take {
+    writer: Box<dyn Writer>,
+    timestamps: BufWriter<File>,
+    started: Instant,
+}
+
+struct Session {
+    send: Sender<Message>,
+    sid: SID,
+    settings: Settings,
+    format: String,
+    name: String,
+    segment: usize,
+    take: Option<Take>,
+    // the file being written, or the last one
+    file: Option<PathBuf>,
+    frames: usize,
+    buffer: Vec<u32>,
+}
+
+impl Session {
+
+    fn command(&mut self, command: &str) -> Result<(), String> {
+        let mut words = command.split_whitespace();
+        match words.next().unwrap_or("") {
+            "start" => {
+                if self.take.is_some() {
+                    return Err("already recording".to_string());
+                }
+                let format = words.next().unwrap_or(&self.settings.format).to_string();
+                writer::extension(&format)?;
+                let name = match words.next() {
+                    Some(name) => name.to_string(),
+                    None => format!("recording-{}", timestamp()),
+                };
+                if name.contains('/') || name.contains('\\') || name.starts_with('.') {
+                    return Err(format!("{} cannot be used as a name", name));
+                }
+                self.format = format;
+                self.name = name;
+                self.segment = 0;
+                self.open()?;
+                let _ = self.send.send(Message::Subscribe(self.sid, self.settings.topic.clone()));
+            },
+            "stop" => {
+                if self.take.is_none() {
+                    return Err("not recording".to_string());
+                }
+                self.stop()?;
+            },
+            "segment" => {
+                if self.take.is_none() {
+                    return Err("not recording".to_string());
+                }
+                if let Err(err) = self.next() {
+                    let _ = self.stop();
+                    return Err(err);
+                }
+            },
+            "status" => { },
+            _ => return Err("unknown command".to_string()),
+        }
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        let file = match &self.file {
+            Some(file) => quote(&file.display().to_string()),
+            None => "null".to_string(),
+        };
+        format!("{{\"recording\":{},\"format\":{},\"file\":{},\"segment\":{},\"frames\":{}}}",
+            self.take.is_some(), quote(&self.format), file, self.segment, self.frames)
+    }
+
+    // start the file for the segment
+    fn open(&mut self) -> Result<(), String> {
+        let folder = PathBuf::from(&self.settings.folder);
+        std::fs::create_dir_all(&folder).map_err(|err| format!("cannot make {}: {}", folder.display(), err))?;
+        let base = format!("{}-{:03}", self.name, self.segment);
+        let file = folder.join(format!("{}{}", base, writer::extension(&self.format)?));
+        let stamps = folder.join(format!("{}.timestamps", base));
+
+        let writer = writer::create(&self.format, &file, self.settings.fps, self.settings.quality)?;
+        let mut timestamps = BufWriter::new(File::create(&stamps).map_err(|err| format!("cannot write {}: {}", stamps.display(), err))?);
+        let _ = writeln!(timestamps, "# frame and when it was recorded, in milliseconds since 1970");
+        println!("Recorder: recording to {}", file.display());
+        self.take = Some(Take { writer: writer, timestamps: timestamps, started: Instant::now() });
+        self.file = Some(file);
+        self.frames = 0;
+        Ok(())
+    }
+
+    // finish the file being written
+    fn close(&mut self) -> Result<(), String> {
+        if let Some(mut take) = self.take.take() {
+            take.writer.finish()?;
+            take.timestamps.flush().map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    // finish this segment and start the next
+    fn next(&mut self) -> Result<(), String> {
+        self.close()?;
+        self.segment += 1;
+        self.open()
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        let _ = self.send.send(Message::Unsubscribe(self.sid, self.settings.topic.clone()));
+        self.close()
+    }
+
+    fn record(&mut self, timestamp: u64) -> Result<(), String> {
+        let full = match &self.take {
+            Some(take) => self.settings.segment > 0 && take.started.elapsed() >= Duration::from_secs(self.settings.segment),
+            None => return Ok(()),
+        };
+        if full {
+            self.next()?;
+            self.publish(STATUS, self.status());
+        }
+        if let Some(take) = &mut self.take {
+            take.writer.write(&self.buffer)?;
+            writeln!(take.timestamps, "{} {}", self.frames, timestamp).map_err(|err| err.to_string())?;
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    fn publish(&self, topic: &str, data: String) {
+        let _ = self.send.send(Message::Event(topic.to_string(), data));
+    }
+
+    fn log(&self, text: &str) {
+        println!("{}", text);
+        self.publish("/log", text.to_string());
+    }
+}
@@ -0,0 +1,289 @@
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// writers - somewhere for recorded frames to go
+/////////////////////////////////////////////////////////////////////////////////////////
+
+//
+// each format writes files the camera can play back again:
+//
+//     png      a folder of frame-000000.png, frame-000001.png ...    source = images
+//     y4m      a yuv4mpeg2 stream, 4:4:4 so no color is thrown away     source = y4m
+//     mjpeg    an avi of jpegs, which most players open too            source = mjpeg
+//
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{FRAME_WIDTH, FRAME_HEIGHT};
+
+pub trait Writer: Send {
+    /// Add a FRAME_WIDTH by FRAME_HEIGHT frame
+    fn write(&mut self, frame: &[u32]) -> Result<(), String>;
+    /// Tidy up once the last frame is in - a file that is not finished may not play
+    fn finish(&mut self) -> Result<(), String>;
+}
+
+/// What goes on the end of a recording's name in this format - a png recording is a folder
+pub fn extension(format: &str) -> Result<&'static str, String> {
+    match format {
+        "png" => Ok(""),
+        "y4m" => Ok(".y4m"),
+        "mjpeg" => Ok(".avi"),
+        _ => Err(format!("unknown format {} - it can be png, y4m or mjpeg", format)),
+    }
+}
+
+/// Start writing a recording in a format
+pub fn create(format: &str, path: &Path, fps: f64, quality: u8) -> Result<Box<dyn Writer>, String> {
+    match format {
+        "png" => Ok(Box::new(Pngs::create(path)?)),
+        "y4m" => Ok(Box::new(Y4m::create(path, fps)?)),
+        "mjpeg" => Ok(Box::new(Avi::create(path, fps, quality)?)),
+        _ => Err(format!("unknown format {} - it can be png, y4m or mjpeg", format)),
+    }
+}
+
+fn rgb(frame: &[u32], bytes: &mut Vec<u8>) {
+    bytes.clear();
+    for pixel in frame {
+        bytes.extend_from_slice(&[*pixel as u8, (*pixel >> 8) as u8, (*pixel >> 16) as u8]);
+    }
+}
+
+fn failed(path: &Path) -> impl Fn(std::io::Error) -> String + '_ {
+    move |err| format!("cannot write {}: {}", path.display(), err)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// png
+
+pub struct Pngs {
+    folder: PathBuf,
+    count: usize,
+    bytes: Vec<u8>,
+}
+
+impl Pngs {
+    pub fn create(folder: &Path) -> Result<Pngs, String> {
+        std::fs::create_dir_all(folder).map_err(failed(folder))?;
+        Ok(Pngs { folder: folder.to_path_buf(), count: 0, bytes: Vec::new() })
+    }
+}
+
+impl Writer for Pngs {
+    fn write(&mut self, frame: &[u32]) -> Result<(), String> {
+        let path = self.folder.join(format!("frame-{:06}.png", self.count));
+        let file = File::create(&path).map_err(failed(&path))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), FRAME_WIDTH as u32, FRAME_HEIGHT as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        // fast rather than small, so it keeps up with the camera
+        encoder.set_compression(png::Compression::Fast);
+        rgb(frame, &mut self.bytes);
+        let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+        writer.write_image_data(&self.bytes).map_err(|err| err.to_string())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> { Ok(()) }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// y4m
+
+pub struct Y4m {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    planes: Vec<u8>,
+}
+
+impl Y4m {
+    pub fn create(path: &Path, fps: f64) -> Result<Y4m, String> {
+        let mut writer = BufWriter::new(File::create(path).map_err(failed(path))?);
+        // the frame rate is a fraction, so a rate like 29.97 goes in as thousandths
+        let header = format!("YUV4MPEG2 W{} H{} F{}:1000 Ip A1:1 C444\n", FRAME_WIDTH, FRAME_HEIGHT, (fps * 1000.0).round() as u64);
+        writer.write_all(header.as_bytes()).map_err(failed(path))?;
+        Ok(Y4m { path: path.to_path_buf(), writer: writer, planes: vec![0; FRAME_WIDTH * FRAME_HEIGHT * 3] })
+    }
+}
+
+impl Writer for Y4m {
+    fn write(&mut self, frame: &[u32]) -> Result<(), String> {
+        let size = FRAME_WIDTH * FRAME_HEIGHT;
+        for (index, pixel) in frame.iter().enumerate() {
+            let (y, u, v) = yuv(*pixel);
+            self.planes[index] = y;
+            self.planes[size + index] = u;
+            self.planes[2 * size + index] = v;
+        }
+        self.writer.write_all(b"FRAME\n").map_err(failed(&self.path))?;
+        self.writer.write_all(&self.planes).map_err(failed(&self.path))
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(failed(&self.path))
+    }
+}
+
+/// A pixel as studio range bt.601 y, u and v - the other way around from the camera's yuv()
+pub fn yuv(pixel: u32) -> (u8, u8, u8) {
+    let (r, g, b) = ((pixel & 0xff) as i32, ((pixel >> 8) & 0xff) as i32, ((pixel >> 16) & 0xff) as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////
+// mjpeg in an avi
+//
+// an avi is a riff file: a header list saying what the stream is, a movi list with a 00dc chunk per jpeg, and an idx1
+// index of where each chunk is. the sizes and frame counts are only known at the end, so they are filled in then.
+//
+
+pub struct Avi {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    quality: u8,
+    // where each chunk starts counting from the movi list, and how big it is
+    index: Vec<(u32, u32)>,
+    // how big the movi list is so far
+    movi: u32,
+    // where the frame counts and the movi list size are in the file
+    frames_at: [u64; 2],
+    movi_at: u64,
+    bytes: Vec<u8>,
+    jpeg: Vec<u8>,
+}
+
+impl Avi {
+    pub fn create(path: &Path, fps: f64, quality: u8) -> Result<Avi, String> {
+        let (width, height) = (FRAME_WIDTH as u32, FRAME_HEIGHT as u32);
+        let rate = (fps * 1000.0).round().max(1.0) as u32;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF\0\0\0\0AVI ");
+        header.extend_from_slice(b"LIST");
+        put(&mut header, 4 + 8 + 56 + 8 + 4 + 8 + 56 + 8 + 40);
+        header.extend_from_slice(b"hdrl");
+
+        header.extend_from_slice(b"avih");
+        put(&mut header, 56);
+        put(&mut header, (1_000_000_000 / rate as u64) as u32);
+        put(&mut header, 0);
+        put(&mut header, 0);
+        // has an index
+        put(&mut header, 0x10);
+        let total = header.len() as u64;
+        put(&mut header, 0);
+        put(&mut header, 0);
+        put(&mut header, 1);
+        put(&mut header, width * height * 3);
+        put(&mut header, width);
+        put(&mut header, height);
+        header.extend_from_slice(&[0; 16]);
+
+        header.extend_from_slice(b"LIST");
+        put(&mut header, 4 + 8 + 56 + 8 + 40);
+        header.extend_from_slice(b"strl");
+        header.extend_from_slice(b"strh");
+        put(&mut header, 56);
+        header.extend_from_slice(b"vidsMJPG");
+        put(&mut header, 0);
+        put(&mut header, 0);
+        put(&mut header, 0);
+        put(&mut header, 1000);
+        put(&mut header, rate);
+        put(&mut header, 0);
+        let length = header.len() as u64;
+        put(&mut header, 0);
+        put(&mut header, width * height * 3);
+        put(&mut header, u32::MAX);
+        put(&mut header, 0);
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        header.extend_from_slice(&(width as u16).to_le_bytes());
+        header.extend_from_slice(&(height as u16).to_le_bytes());
+
+        header.extend_from_slice(b"strf");
+        put(&mut header, 40);
+        put(&mut header, 40);
+        put(&mut header, width);
+        put(&mut header, height);
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        header.extend_from_slice(b"MJPG");
+        put(&mut header, width * height * 3);
+        header.extend_from_slice(&[0; 16]);
+
+        let movi = header.len() as u64;
+        header.extend_from_slice(b"LIST\0\0\0\0movi");
+
+        let mut writer = BufWriter::new(File::create(path).map_err(failed(path))?);
+        writer.write_all(&header).map_err(failed(path))?;
+        Ok(Avi {
+            path: path.to_path_buf(),
+            writer: writer,
+            quality: quality.max(1).min(100),
+            index: Vec::new(),
+            movi: 4,
+            frames_at: [total, length],
+            movi_at: movi,
+            bytes: Vec::new(),
+            jpeg: Vec::new(),
+        })
+    }
+}
+
+impl Writer for Avi {
+    fn write(&mut self, frame: &[u32]) -> Result<(), String> {
+        rgb(frame, &mut self.bytes);
+        self.jpeg.clear();
+        jpeg_encoder::Encoder::new(&mut self.jpeg, self.quality)
+            .encode(&self.bytes, FRAME_WIDTH as u16, FRAME_HEIGHT as u16, jpeg_encoder::ColorType::Rgb)
+            .map_err(|err| err.to_string())?;
+
+        let size = self.jpeg.len() as u32;
+        self.index.push((self.movi, size));
+        // chunks start on even bytes
+        self.movi += 8 + (size + 1) / 2 * 2;
+        let path = &self.path;
+        self.writer.write_all(b"00dc").map_err(failed(path))?;
+        self.writer.write_all(&size.to_le_bytes()).map_err(failed(path))?;
+        self.writer.write_all(&self.jpeg).map_err(failed(path))?;
+        if size % 2 == 1 {
+            self.writer.write_all(&[0]).map_err(failed(path))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let mut index = Vec::new();
+        index.extend_from_slice(b"idx1");
+        put(&mut index, self.index.len() as u32 * 16);
+        for (offset, size) in &self.index {
+            index.extend_from_slice(b"00dc");
+            // every jpeg is a keyframe
+            put(&mut index, 0x10);
+            put(&mut index, *offset);
+            put(&mut index, *size);
+        }
+        let path = &self.path;
+        let frames = self.index.len() as u32;
+        self.writer.write_all(&index).map_err(failed(path))?;
+
+        // now the sizes and counts are known
+        let end = self.writer.seek(SeekFrom::End(0)).map_err(failed(path))?;
+        let patches = [(4, end as u32 - 8), (self.frames_at[0], frames), (self.frames_at[1], frames), (self.movi_at + 4, self.movi)];
+        for (at, value) in patches.iter() {
+            self.writer.seek(SeekFrom::Start(*at)).map_err(failed(path))?;
+            self.writer.write_all(&value.to_le_bytes()).map_err(failed(path))?;
+        }
+        self.writer.flush().map_err(failed(path))
+    }
+}
+
+fn put(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
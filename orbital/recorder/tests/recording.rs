@@ -0,0 +1,186 @@
+
+// recordings are only any good if they play back, so these write frames and read them again with the camera's sources
+
+use crossbeam::channel::*;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use broker::*;
+use camera::source::VideoSource;
+use recorder::*;
+use service::*;
+
+const PROBE: SID = 1;
+const RECORDER_SID: SID = 2;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orbital-recorder-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// a few frames of the camera's test pattern, so they are all different
+fn pattern(count: usize) -> Vec<Vec<u32>> {
+    let mut source = camera::pattern::Pattern::new();
+    (0..count).map(|_| {
+        let mut frame = vec![0u32; FRAME_WIDTH * FRAME_HEIGHT];
+        source.read(&mut frame).unwrap();
+        frame
+    }).collect()
+}
+
+fn playback(mut source: Box<dyn VideoSource>) -> Vec<Vec<u32>> {
+    let mut frames = Vec::new();
+    loop {
+        let mut frame = vec![0u32; FRAME_WIDTH * FRAME_HEIGHT];
+        if !source.read(&mut frame).unwrap() {
+            return frames;
+        }
+        frames.push(frame);
+    }
+}
+
+// how far apart two frames are on average, per color
+fn difference(a: &[u32], b: &[u32]) -> f64 {
+    let mut total = 0u64;
+    for (a, b) in a.iter().zip(b.iter()) {
+        for shift in &[0, 8, 16] {
+            total += (((a >> shift) & 0xff) as i64 - ((b >> shift) & 0xff) as i64).unsigned_abs();
+        }
+    }
+    total as f64 / (a.len() * 3) as f64
+}
+
+fn record(format: &str, path: &Path, frames: &[Vec<u32>]) {
+    let mut writer = writer::create(format, path, 25.0, 90).unwrap();
+    for frame in frames {
+        writer.write(frame).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn every_format_plays_back_through_the_camera() {
+    let dir = scratch("formats");
+    let frames = pattern(3);
+
+    let pngs = dir.join("pngs");
+    record("png", &pngs, &frames);
+    assert_eq!(playback(camera::source::open("images", pngs.to_str()).unwrap()), frames);
+
+    let y4m = dir.join("clip.y4m");
+    record("y4m", &y4m, &frames);
+    let source = camera::y4m::Y4m::open(y4m.to_str().unwrap()).unwrap();
+    assert_eq!(source.fps(), Some(25.0));
+    let played = playback(Box::new(source));
+    assert_eq!(played.len(), 3);
+    for (played, frame) in played.iter().zip(frames.iter()) {
+        assert!(difference(played, frame) < 1.0, "y4m is {} off", difference(played, frame));
+    }
+
+    let avi = dir.join("clip.avi");
+    record("mjpeg", &avi, &frames);
+    let played = playback(camera::source::open("mjpeg", avi.to_str()).unwrap());
+    assert_eq!(played.len(), 3);
+    for (played, frame) in played.iter().zip(frames.iter()) {
+        assert!(difference(played, frame) < 3.0, "mjpeg is {} off", difference(played, frame));
+    }
+
+    // the avi says how many frames it has and where they are
+    let bytes = std::fs::read(&avi).unwrap();
+    let word = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize;
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(word(4), bytes.len() - 8);
+    assert_eq!(word(48), 3);
+    let index = bytes.len() - 3 * 16 - 8;
+    assert_eq!(&bytes[index..index + 4], b"idx1");
+}
+
+fn start(folder: &Path) -> (Sender<Message>, Receiver<Message>) {
+    let manifest = folder.join("recorder.manifest");
+    std::fs::write(&manifest, format!("folder = {}\nformat = y4m\nfps = 25\n", folder.display())).unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,STATUS.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Recorder::with_manifest(manifest.to_str().unwrap());
+    brokersend.send(Message::Channel(RECORDER_SID,"recorder".to_string(),localsend)).unwrap();
+    instance.start("recorder".to_string(),RECORDER_SID,brokersend.clone(),localrecv);
+
+    // wait until the recorder is listening before telling it anything
+    brokersend.send(Message::Subscribe(PROBE,SUBSCRIBERS.to_string())).unwrap();
+    loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("the recorder never subscribed") {
+            Message::Event(topic,data) if topic == SUBSCRIBERS && data == format!("{} 1",RECORDER) => break,
+            _ => { },
+        }
+    }
+    (brokersend, proberecv)
+}
+
+fn command(send: &Sender<Message>, recv: &Receiver<Message>, command: &str) -> (String,String) {
+    send.send(Message::Event(RECORDER.to_string(),command.to_string())).unwrap();
+    loop {
+        match recv.recv_timeout(Duration::from_secs(10)).expect("no answer from the recorder") {
+            Message::Event(topic,_) if topic == SUBSCRIBERS => { },
+            Message::Event(topic,data) => return (topic,data),
+            _ => { },
+        }
+    }
+}
+
+fn share(send: &Sender<Message>, frame: &[u32]) {
+    let frame: Box<[u32;921600]> = frame.to_vec().into_boxed_slice().try_into().unwrap();
+    send.send(Message::Share("/frames".to_string(),std::sync::Arc::new(std::sync::Mutex::new(frame)))).unwrap();
+}
+
+#[test]
+fn recordings_are_split_into_segments_with_timestamps() {
+    let dir = scratch("segments");
+    let (send, recv) = start(&dir);
+    let frames = pattern(5);
+
+    // frames that go by before recording starts are not kept
+    share(&send, &frames[0]);
+    let (_, status) = command(&send, &recv, "start y4m session");
+    assert!(status.contains("\"recording\":true") && status.contains("session-000.y4m"), "{}", status);
+    for frame in &frames[..3] {
+        share(&send, frame);
+    }
+    // frames shared on other topics are not the ones being recorded
+    let other: Box<[u32;921600]> = frames[0].to_vec().into_boxed_slice().try_into().unwrap();
+    send.send(Message::Share("/viewsoft/frames".to_string(),std::sync::Arc::new(std::sync::Mutex::new(other)))).unwrap();
+    let (_, status) = command(&send, &recv, "segment");
+    assert!(status.contains("session-001.y4m") && status.contains("\"segment\":1"), "{}", status);
+    for frame in &frames[3..] {
+        share(&send, frame);
+    }
+    let (_, status) = command(&send, &recv, "stop");
+    assert!(status.contains("\"recording\":false") && status.contains("\"frames\":2"), "{}", status);
+
+    // asking twice is an error, and so is a format nobody has heard of
+    let (topic, _) = command(&send, &recv, "stop");
+    assert_eq!(topic, "/log");
+    let (topic, _) = command(&send, &recv, "start gif");
+    assert_eq!(topic, "/log");
+
+    for (segment, count) in &[(0, 3), (1, 2)] {
+        let y4m = dir.join(format!("session-{:03}.y4m", segment));
+        let played = playback(camera::source::open("y4m", y4m.to_str()).unwrap());
+        assert_eq!(played.len(), *count);
+
+        let timestamps = std::fs::read_to_string(dir.join(format!("session-{:03}.timestamps", segment))).unwrap();
+        let lines: Vec<&str> = timestamps.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(lines.len(), *count);
+        assert!(lines[0].starts_with("0 "), "{}", timestamps);
+    }
+    assert!(difference(&playback(camera::source::open("y4m", dir.join("session-001.y4m").to_str()).unwrap())[1], &frames[4]) < 1.0);
+}
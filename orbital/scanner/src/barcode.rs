@@ -0,0 +1,192 @@
+
+///
+/// Reading ean13, upca and ean8 barcodes off rows of gray pixels
+///
+/// each row is cut into runs of dark and light. a barcode is a guard of bar-space-bar, then digits of four runs each
+/// (seven modules wide), a center guard of five runs, more digits and another guard - so any stretch of runs that
+/// has the right count and guards in the right places is tried as a barcode. each digit is whichever pattern its runs
+/// are closest to, and the check digit has to work out.
+///
+/// the barcode can be upside down, so each row is also tried backwards.
+///
+
+// widths of the four runs of each digit in the left half of an ean, starting with a space
+const L: [[u32; 4]; 10] = [
+    [3, 2, 1, 1], [2, 2, 2, 1], [2, 1, 2, 2], [1, 4, 1, 1], [1, 1, 3, 2],
+    [1, 2, 3, 1], [1, 1, 1, 4], [1, 3, 1, 2], [1, 2, 1, 3], [3, 1, 1, 2],
+];
+
+// which of the six left digits of an ean13 are mirrored, by its first digit, which is not printed as bars
+const PARITY: [[bool; 6]; 10] = [
+    [false, false, false, false, false, false],
+    [false, false, true, false, true, true],
+    [false, false, true, true, false, true],
+    [false, false, true, true, true, false],
+    [false, true, false, false, true, true],
+    [false, true, true, false, false, true],
+    [false, true, true, true, false, false],
+    [false, true, false, true, false, true],
+    [false, true, false, true, true, false],
+    [false, true, true, false, true, false],
+];
+
+// how far a digit's runs can be from a pattern, in modules summed over its runs
+const SLOP: f32 = 1.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Barcode {
+    pub kind: &'static str,
+    pub payload: String,
+    // where along the row the barcode starts and ends, in pixels, left and right as it was printed
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find any barcodes along one row
+pub fn scan(row: &[u8]) -> Vec<Barcode> {
+    let runs = runs(row);
+    let mut found = Vec::new();
+    find(&runs, &mut found);
+    // and upside down, with positions turned back around
+    let mut reversed: Vec<Run> = runs.iter().rev().cloned().collect();
+    for run in reversed.iter_mut() {
+        let start = row.len() - (run.start + run.width);
+        run.start = start;
+    }
+    let mut backwards = Vec::new();
+    find(&reversed, &mut backwards);
+    for barcode in backwards {
+        found.push(Barcode { start: row.len() - barcode.start, end: row.len() - barcode.end, ..barcode });
+    }
+    found
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    dark: bool,
+    start: usize,
+    width: usize,
+}
+
+// cut the row where it crosses halfway between its darkest and lightest
+fn runs(row: &[u8]) -> Vec<Run> {
+    let mut runs = Vec::new();
+    if row.is_empty() {
+        return runs;
+    }
+    let darkest = *row.iter().min().unwrap();
+    let lightest = *row.iter().max().unwrap();
+    if lightest - darkest < 32 {
+        return runs;
+    }
+    let threshold = (darkest as u32 + lightest as u32) / 2;
+    let mut current = Run { dark: (row[0] as u32) < threshold, start: 0, width: 0 };
+    for (x, pixel) in row.iter().enumerate() {
+        let dark = (*pixel as u32) < threshold;
+        if dark != current.dark {
+            runs.push(current);
+            current = Run { dark: dark, start: x, width: 0 };
+        }
+        current.width += 1;
+    }
+    runs.push(current);
+    runs
+}
+
+fn find(runs: &[Run], found: &mut Vec<Barcode>) {
+    let mut i = 0;
+    while i < runs.len() {
+        let decoded = if runs[i].dark { ean(&runs[i..], 6).or_else(|| ean(&runs[i..], 4)) } else { None };
+        match decoded {
+            Some((barcode, used)) => {
+                found.push(barcode);
+                i += used;
+            },
+            None => i += 1,
+        }
+    }
+}
+
+// try the runs as an ean with this many digits each side - 6 for ean13, 4 for ean8
+fn ean(runs: &[Run], half: usize) -> Option<(Barcode, usize)> {
+    let count = 3 + half * 4 + 5 + half * 4 + 3;
+    if runs.len() < count {
+        return None;
+    }
+    let runs = &runs[..count];
+    let modules = (3 + half * 7 + 5 + half * 7 + 3) as f32;
+    let width: usize = runs.iter().map(|run| run.width).sum();
+    let module = width as f32 / modules;
+
+    // the guards are all one module wide
+    let guards = [0, 1, 2, 3 + half * 4, 4 + half * 4, 5 + half * 4, 6 + half * 4, 7 + half * 4, count - 3, count - 2, count - 1];
+    for g in guards.iter() {
+        let w = runs[*g].width as f32 / module;
+        if !(0.5..=1.6).contains(&w) {
+            return None;
+        }
+    }
+
+    let mut digits = Vec::new();
+    let mut mirrored = Vec::new();
+    for d in 0..half {
+        let (digit, flipped) = digit(&runs[3 + d * 4..7 + d * 4], true)?;
+        digits.push(digit);
+        mirrored.push(flipped);
+    }
+    for d in 0..half {
+        let at = 8 + half * 4 + d * 4;
+        let (digit, _) = digit(&runs[at..at + 4], false)?;
+        digits.push(digit);
+    }
+
+    let (kind, digits) = if half == 6 {
+        // the first digit is told by which of the left digits are mirrored
+        let first = PARITY.iter().position(|parity| parity[..] == mirrored[..])? as u32;
+        let mut all = vec![first];
+        all.extend(digits);
+        (if first == 0 { "upca" } else { "ean13" }, all)
+    } else {
+        if mirrored.iter().any(|flipped| *flipped) {
+            return None;
+        }
+        ("ean8", digits)
+    };
+
+    // weights alternate 3 and 1 going left from the check digit
+    let sum: u32 = digits[..digits.len() - 1].iter().rev().enumerate().map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d }).sum();
+    if (10 - sum % 10) % 10 != digits[digits.len() - 1] {
+        return None;
+    }
+
+    let mut payload: String = digits.iter().map(|d| std::char::from_digit(*d, 10).unwrap()).collect();
+    if kind == "upca" {
+        payload.remove(0);
+    }
+    let last = runs[count - 1];
+    Some((Barcode { kind: kind, payload: payload, start: runs[0].start, end: last.start + last.width }, count))
+}
+
+// the digit four runs are closest to, and whether it was mirrored - only the left half can be
+fn digit(runs: &[Run], left: bool) -> Option<(u32, bool)> {
+    let width: usize = runs.iter().map(|run| run.width).sum();
+    let module = width as f32 / 7.0;
+    let mut best = None;
+    let mut best_error = SLOP;
+    for (value, pattern) in L.iter().enumerate() {
+        let mut forms = vec![(*pattern, false)];
+        if left {
+            let mut flipped = *pattern;
+            flipped.reverse();
+            forms.push((flipped, true));
+        }
+        for (form, flipped) in forms {
+            let error: f32 = runs.iter().zip(form.iter()).map(|(run, w)| (run.width as f32 / module - *w as f32).abs()).sum();
+            if error < best_error {
+                best_error = error;
+                best = Some((value as u32, flipped));
+            }
+        }
+    }
+    best
+}
@@ -0,0 +1,162 @@
+
+///
+/// Scanner: looks for qr codes and barcodes in camera frames and publishes what they say on /codes
+///
+/// like the tensor service it listens to every frame on /frames and only looks at one every so often. qr codes are
+/// found with rqrr; ean13, upca and ean8 barcodes are read along rows of the frame, and have to be read the same on
+/// a few rows before they count. settings are in public/scanner.manifest.
+///
+
+use crossbeam::channel::*;
+use service::*;
+use service::vision::*;
+
+pub mod barcode;
+
+const FRAME_WIDTH: usize = 1280;
+const FRAME_HEIGHT: usize = 720;
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    // look at one frame in this many
+    pub every: i32,
+    pub qr: bool,
+    pub barcodes: bool,
+    // read a row for barcodes every this many pixels down the frame
+    pub rows: usize,
+    // and count a barcode once it has been read on this many rows
+    pub lines: usize,
+}
+
+impl Settings {
+    pub fn from_manifest(manifest: &Manifest) -> Settings {
+        Settings {
+            every: manifest.get_or("every", 10),
+            qr: manifest.get_or("qr", true),
+            barcodes: manifest.get_or("barcodes", true),
+            rows: manifest.get_or("rows", 8).max(1),
+            lines: manifest.get_or("lines", 2),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Scanner {
+    manifest: String,
+}
+
+impl Scanner {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/scanner.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for Scanner {
+    fn name(&self) -> &str { "Scanner" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("Scanner: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let settings = Settings::from_manifest(&manifest);
+
+            send.send(Message::Subscribe(sid, "/frames".to_string())).expect("error");
+
+            let mut gray = vec![0u8; FRAME_WIDTH * FRAME_HEIGHT];
+            let mut count = 0;
+            while let Ok(message) = recv.recv() {
+                match message {
+                    Message::Share(_, sharedmemory) => {
+                        count += 1;
+                        if count < settings.every {
+                            continue;
+                        }
+                        count = 0;
+                        let timestamp = timestamp();
+
+                        // take a gray copy and let go of the frame before the slow part
+                        let ptr = sharedmemory.lock().unwrap();
+                        for (value, pixel) in gray.iter_mut().zip(ptr.iter()) {
+                            let (r, g, b) = (*pixel & 0xff, (*pixel >> 8) & 0xff, (*pixel >> 16) & 0xff);
+                            *value = ((r * 299 + g * 587 + b * 114) / 1000) as u8;
+                        }
+                        drop(ptr);
+
+                        let codes = scan(&gray, FRAME_WIDTH, FRAME_HEIGHT, &settings);
+                        let codes = Codes { timestamp: timestamp, width: FRAME_WIDTH as u32, height: FRAME_HEIGHT as u32, codes: codes };
+                        send.send(Message::Event(CODES.to_string(), codes.to_json())).expect("error");
+                    },
+                    _ => { },
+                }
+            }
+        });
+    }
+}
+
+/// Every code found in a gray frame
+pub fn scan(gray: &[u8], width: usize, height: usize, settings: &Settings) -> Vec<Code> {
+    let mut codes = Vec::new();
+    if settings.qr {
+        codes.extend(qr(gray, width, height));
+    }
+    if settings.barcodes {
+        codes.extend(barcodes(gray, width, height, settings));
+    }
+    codes
+}
+
+fn qr(gray: &[u8], width: usize, height: usize) -> Vec<Code> {
+    let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| gray[y * width + x]);
+    let mut codes = Vec::new();
+    for grid in image.detect_grids() {
+        // a grid that will not decode is usually something that only looks like a finder pattern
+        if let Ok((_, payload)) = grid.decode() {
+            codes.push(Code {
+                kind: "qr".to_string(),
+                payload: payload,
+                corners: grid.bounds.iter().map(|point| (point.x, point.y)).collect(),
+            });
+        }
+    }
+    codes
+}
+
+fn barcodes(gray: &[u8], width: usize, height: usize, settings: &Settings) -> Vec<Code> {
+
+    // every row a barcode was read on, by what it said
+    let mut seen: Vec<(barcode::Barcode, Vec<(usize, barcode::Barcode)>)> = Vec::new();
+    for y in (0..height).step_by(settings.rows) {
+        for found in barcode::scan(&gray[y * width..(y + 1) * width]) {
+            match seen.iter_mut().find(|(first, _)| first.kind == found.kind && first.payload == found.payload) {
+                Some((_, rows)) => rows.push((y, found)),
+                None => seen.push((found.clone(), vec![(y, found)])),
+            }
+        }
+    }
+
+    let mut codes = Vec::new();
+    for (first, rows) in seen {
+        if rows.len() < settings.lines {
+            continue;
+        }
+        let (top, upper) = &rows[0];
+        let (bottom, lower) = &rows[rows.len() - 1];
+        let (top, bottom) = (*top as i32, *bottom as i32);
+        // upside down the printed top left is at the bottom right of the frame
+        let corners = if upper.start <= upper.end {
+            vec![(upper.start as i32, top), (upper.end as i32, top), (lower.end as i32, bottom), (lower.start as i32, bottom)]
+        } else {
+            vec![(lower.start as i32, bottom), (lower.end as i32, bottom), (upper.end as i32, top), (upper.start as i32, top)]
+        };
+        codes.push(Code { kind: first.kind.to_string(), payload: first.payload, corners: corners });
+    }
+    codes
+}
@@ -0,0 +1,99 @@
+use scanner::barcode::*;
+use scanner::Settings;
+
+// how many pixels wide one module is, and the quiet zone either side in modules
+const MODULE: usize = 3;
+const QUIET: usize = 10;
+
+// widths of the four runs of each digit, starting with a space on the left and a bar on the right
+const DIGITS: [[usize; 4]; 10] = [
+    [3, 2, 1, 1], [2, 2, 2, 1], [2, 1, 2, 2], [1, 4, 1, 1], [1, 1, 3, 2],
+    [1, 2, 3, 1], [1, 1, 1, 4], [1, 3, 1, 2], [1, 2, 1, 3], [3, 1, 1, 2],
+];
+
+// which left digits are mirrored, by the first digit of an ean13
+const PARITY: [&str; 10] = ["LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL", "LGGLGL"];
+
+// print an ean13 as a row of gray pixels
+fn ean13(code: &str) -> Vec<u8> {
+    let digits: Vec<usize> = code.chars().map(|c| c.to_digit(10).unwrap() as usize).collect();
+    assert_eq!(digits.len(), 13);
+    let parity = PARITY[digits[0]].as_bytes();
+    // widths in modules, alternating bar and space starting with a bar
+    let mut widths = vec![1, 1, 1];
+    for (i, digit) in digits[1..7].iter().enumerate() {
+        let mut runs = DIGITS[*digit];
+        if parity[i] == b'G' {
+            runs.reverse();
+        }
+        widths.extend(runs.iter());
+    }
+    widths.extend([1, 1, 1, 1, 1].iter());
+    for digit in digits[7..].iter() {
+        widths.extend(DIGITS[*digit].iter());
+    }
+    widths.extend([1, 1, 1].iter());
+
+    let mut row = vec![230; QUIET * MODULE];
+    for (i, width) in widths.iter().enumerate() {
+        let shade = if i % 2 == 0 { 20 } else { 230 };
+        row.resize(row.len() + width * MODULE, shade);
+    }
+    row.resize(row.len() + QUIET * MODULE, 230);
+    row
+}
+
+const WIDTH: usize = (QUIET + 95 + QUIET) * MODULE;
+
+#[test]
+fn reads_an_ean13() {
+    let row = ean13("4006381333931");
+    assert_eq!(row.len(), WIDTH);
+    assert_eq!(scan(&row), vec![Barcode { kind: "ean13", payload: "4006381333931".to_string(), start: QUIET * MODULE, end: (QUIET + 95) * MODULE }]);
+}
+
+#[test]
+fn reads_a_upca() {
+    // a upc-a is an ean13 starting with 0, which is left off
+    let found = scan(&ean13("0036000291452"));
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].kind, found[0].payload.as_str()), ("upca", "036000291452"));
+}
+
+#[test]
+fn reads_one_upside_down() {
+    let mut row = ean13("5901234123457");
+    row.reverse();
+    let found = scan(&row);
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].kind, found[0].payload.as_str()), ("ean13", "5901234123457"));
+    // where it was printed to start is now on the right
+    assert_eq!((found[0].start, found[0].end), ((QUIET + 95) * MODULE, QUIET * MODULE));
+}
+
+#[test]
+fn a_wrong_check_digit_reads_as_nothing() {
+    assert!(scan(&ean13("4006381333932")).is_empty());
+    assert!(scan(&ean13("0036000291450")).is_empty());
+    // nor does a blank row
+    assert!(scan(&[128; WIDTH]).is_empty());
+}
+
+#[test]
+fn a_frame_needs_the_barcode_on_a_few_rows() {
+    let settings = Settings { every: 1, qr: false, barcodes: true, rows: 4, lines: 2 };
+    let row = ean13("4006381333931");
+    let mut gray = vec![230; WIDTH * 16];
+    // printed on rows 4 to 11, so it is read on rows 4 and 8
+    for y in 4..12 {
+        gray[y * WIDTH..(y + 1) * WIDTH].copy_from_slice(&row);
+    }
+    let codes = scanner::scan(&gray, WIDTH, 16, &settings);
+    assert_eq!(codes.len(), 1);
+    assert_eq!((codes[0].kind.as_str(), codes[0].payload.as_str()), ("ean13", "4006381333931"));
+    let (left, right) = ((QUIET * MODULE) as i32, ((QUIET + 95) * MODULE) as i32);
+    assert_eq!(codes[0].corners, vec![(left, 4), (right, 4), (right, 8), (left, 8)]);
+
+    let settings = Settings { lines: 3, ..settings };
+    assert!(scanner::scan(&gray, WIDTH, 16, &settings).is_empty());
+}
@@ -0,0 +1,248 @@
+
+// things a node does by itself, so that an app says once how something moves rather than sending where it is every
+// frame. they are given to a node as a list, and the scene service steps them along every frame:
+//
+//     "behaviors":[{"kind":"rotate","axis":[0,1,0],"speed":1}]                 turn this many radians a second
+//     "behaviors":[{"kind":"tween","to":[0,2,0],"seconds":1.5,"ease":"smooth"}] move there over a time, then stop
+//     "behaviors":[{"kind":"path","points":[[0,0,0],[1,0,0]],"speed":0.5,"loop":true}] go from point to point
+//     "behaviors":[{"kind":"oscillate","offset":[0,0.5,0],"period":2}]          swing to and fro from where it is
+//     "behaviors":[{"kind":"collide"}]                                          say when it runs into others that collide
+//
+// kinds may also be written the way asteroid.js does, as "3d/behavior/rotate". a tween and a path that does not loop
+// are taken off the node when they are done. what happens is told to each app that listens for it (see lib.rs), and only
+// when it may see every node the event is about:
+//
+//     {"event":"collide","nodes":["rock","ship"]}      two colliding nodes have begun to touch
+//     {"event":"separate","nodes":["rock","ship"]}     and have come apart again
+//     {"event":"arrived","node":"ship"}                a tween or path has finished
+//     {"event":"blocked","node":"ship"}                a node's moves would take it out of its app's region, so stop
+//
+// collisions are between the boxes nodes take up in the scene, see query::bounds.
+
+use std::collections::HashSet;
+
+use service::interface::{quote, Json};
+
+use crate::graph::{Change, Graph};
+use crate::math::*;
+use crate::node::*;
+use crate::owners::Owners;
+use crate::query;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ease {
+    Linear,
+    // slow to start and to stop
+    Smooth,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Behavior {
+    Rotate { axis: Vec3, speed: f32 },
+    // where it started from is taken the first time it steps
+    Tween { to: Vec3, seconds: f32, ease: Ease, from: Option<Vec3>, elapsed: f32 },
+    Path { points: Vec<Vec3>, speed: f32, looping: bool, travelled: f32 },
+    Oscillate { offset: Vec3, period: f32, elapsed: f32 },
+    Collide,
+}
+
+impl Behavior {
+
+    pub fn parse(json: &Json) -> Result<Behavior, String> {
+        let kind = string(json, "kind")?;
+        match kind.strip_prefix("3d/behavior/").unwrap_or(&kind) {
+            "rotate" => Ok(Behavior::Rotate {
+                axis: optional(json, "axis", vector)?.unwrap_or([0.0, 1.0, 0.0]),
+                speed: optional(json, "speed", number)?.unwrap_or(1.0),
+            }),
+            "tween" => Ok(Behavior::Tween {
+                to: vector(json.get("to").ok_or("a tween needs somewhere to go to")?, "to")?,
+                seconds: optional(json, "seconds", number)?.unwrap_or(1.0).max(0.0),
+                ease: match optional(json, "ease", string_of)?.as_deref() {
+                    None | Some("linear") => Ease::Linear,
+                    Some("smooth") => Ease::Smooth,
+                    Some(ease) => return Err(format!("unknown ease {}", ease)),
+                },
+                from: None,
+                elapsed: 0.0,
+            }),
+            "path" => {
+                let points = match json.get("points") {
+                    Some(Json::Array(points)) if !points.is_empty() => points.iter().map(|point| vector(point, "points")).collect::<Result<Vec<Vec3>, String>>()?,
+                    _ => return Err("a path needs some points".to_string()),
+                };
+                let looping = match json.get("loop") {
+                    None => false,
+                    Some(Json::Bool(looping)) => *looping,
+                    Some(_) => return Err("loop is true or false".to_string()),
+                };
+                Ok(Behavior::Path { points: points, speed: optional(json, "speed", number)?.unwrap_or(1.0), looping: looping, travelled: 0.0 })
+            },
+            "oscillate" => {
+                let period = optional(json, "period", number)?.unwrap_or(1.0);
+                if period <= 0.0 {
+                    return Err("an oscillation needs a period".to_string());
+                }
+                Ok(Behavior::Oscillate { offset: optional(json, "offset", vector)?.unwrap_or([0.0, 1.0, 0.0]), period: period, elapsed: 0.0 })
+            },
+            "collide" => Ok(Behavior::Collide),
+            _ => Err(format!("unknown behavior {}", kind)),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            Behavior::Rotate { axis, speed } => format!("{{\"kind\":\"rotate\",\"axis\":{},\"speed\":{}}}", list(axis), speed),
+            Behavior::Tween { to, seconds, ease, .. } => format!("{{\"kind\":\"tween\",\"to\":{},\"seconds\":{},\"ease\":\"{}\"}}",
+                list(to), seconds, if *ease == Ease::Smooth { "smooth" } else { "linear" }),
+            Behavior::Path { points, speed, looping, .. } => format!("{{\"kind\":\"path\",\"points\":[{}],\"speed\":{},\"loop\":{}}}",
+                points.iter().map(|point| list(point)).collect::<Vec<String>>().join(","), speed, looping),
+            Behavior::Oscillate { offset, period, .. } => format!("{{\"kind\":\"oscillate\",\"offset\":{},\"period\":{}}}", list(offset), period),
+            Behavior::Collide => "{\"kind\":\"collide\"}".to_string(),
+        }
+    }
+
+    // move a node along by some seconds, saying whether this is done with
+    fn step(&mut self, node: &mut Node, dt: f32) -> bool {
+        match self {
+            Behavior::Rotate { axis, speed } => {
+                node.rotation = quat_normalize(quat_mul(axis_angle(*axis, *speed * dt), node.rotation));
+                false
+            },
+            Behavior::Tween { to, seconds, ease, from, elapsed } => {
+                let from = *from.get_or_insert(node.position);
+                *elapsed += dt;
+                let t = if *seconds > 0.0 { (*elapsed / *seconds).min(1.0) } else { 1.0 };
+                let t = if *ease == Ease::Smooth { t * t * (3.0 - 2.0 * t) } else { t };
+                node.position = lerp(from, *to, t);
+                t >= 1.0
+            },
+            Behavior::Path { points, speed, looping, travelled } => {
+                *travelled += *speed * dt;
+                let (at, done) = along(points, *looping, *travelled);
+                node.position = at;
+                done
+            },
+            Behavior::Oscillate { offset, period, elapsed } => {
+                let swing = |t: f32| (t / *period * std::f32::consts::TAU).sin();
+                let moved = swing(*elapsed + dt) - swing(*elapsed);
+                *elapsed = (*elapsed + dt) % *period;
+                node.position = add(node.position, scale(*offset, moved));
+                false
+            },
+            Behavior::Collide => false,
+        }
+    }
+}
+
+// where something is after going some way along a path, and whether it has come to the end - a path that loops goes
+// back to its first point and round again
+fn along(points: &[Vec3], looping: bool, travelled: f32) -> (Vec3, bool) {
+    let mut legs: Vec<(Vec3, Vec3)> = points.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    if looping && points.len() > 1 {
+        legs.push((points[points.len() - 1], points[0]));
+    }
+    let total: f32 = legs.iter().map(|(a, b)| length(sub(*b, *a))).sum();
+    if total <= 0.0 {
+        return (points[0], !looping);
+    }
+    let mut left = if looping { travelled.rem_euclid(total) } else { travelled.min(total) };
+    for (a, b) in &legs {
+        let leg = length(sub(*b, *a));
+        if left <= leg && leg > 0.0 {
+            return (lerp(*a, *b, left / leg), !looping && travelled >= total);
+        }
+        left -= leg;
+    }
+    (points[points.len() - 1], !looping)
+}
+
+/// Something that happened to one or two nodes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub event: &'static str,
+    pub nodes: Vec<String>,
+}
+
+impl Event {
+
+    fn new(event: &'static str, nodes: &[&str]) -> Event {
+        Event { event: event, nodes: nodes.iter().map(|node| node.to_string()).collect() }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self.nodes.as_slice() {
+            [node] => format!("{{\"event\":\"{}\",\"node\":{}}}", self.event, quote(node)),
+            nodes => format!("{{\"event\":\"{}\",\"nodes\":[{}]}}", self.event, nodes.iter().map(|node| quote(node)).collect::<Vec<String>>().join(",")),
+        }
+    }
+}
+
+/// Steps every node's behaviors along, remembering what was touching what
+#[derive(Clone, Debug, Default)]
+pub struct Motion {
+    touching: HashSet<(String, String)>,
+}
+
+impl Motion {
+
+    pub fn new() -> Motion {
+        Motion::default()
+    }
+
+    /// Move everything on by some seconds, giving back the nodes that changed and the events to tell
+    pub fn step(&mut self, graph: &mut Graph, owners: &Owners, dt: f32) -> (Vec<Change>, Vec<Event>) {
+        let mut changes = Vec::new();
+        let mut events = Vec::new();
+        let moving: Vec<String> = graph.walk().iter()
+            .filter(|node| node.behaviors.iter().any(|behavior| *behavior != Behavior::Collide))
+            .map(|node| node.id.clone())
+            .collect();
+        for id in moving {
+            let old = match graph.get(&id) { Some(node) => node.clone(), None => continue };
+            let mut node = old.clone();
+            let mut behaviors = std::mem::take(&mut node.behaviors);
+            let before = behaviors.len();
+            behaviors.retain_mut(|behavior| !behavior.step(&mut node, dt));
+            if behaviors.len() < before {
+                events.push(Event::new("arrived", &[id.as_str()]));
+            }
+            node.behaviors = behaviors;
+            graph.set(node);
+            // an app's nodes stay in its region however they are moved, so whatever would take them out stops
+            let owner = old.owner.clone().unwrap_or_default();
+            if !owner.is_empty() && owners.fits(graph, &owner, &id).is_err() {
+                let mut node = old;
+                node.behaviors.retain(|behavior| *behavior == Behavior::Collide);
+                graph.set(node);
+                events.push(Event::new("blocked", &[id.as_str()]));
+            }
+            changes.push(Change::Set(Box::new(graph.get(&id).cloned().expect("just set"))));
+        }
+
+        // what collides and is being drawn, and where it is
+        let colliders: Vec<(String, (Vec3, Vec3))> = graph.walk().iter()
+            .filter(|node| node.behaviors.contains(&Behavior::Collide) && graph.shown(&node.id))
+            .filter_map(|node| query::bounds(graph, &node.id).map(|bounds| (node.id.clone(), bounds)))
+            .collect();
+        let mut touching = HashSet::new();
+        for (index, (a, (amin, amax))) in colliders.iter().enumerate() {
+            for (b, (bmin, bmax)) in &colliders[index + 1..] {
+                if (0..3).all(|axis| amin[axis] <= bmax[axis] && amax[axis] >= bmin[axis]) {
+                    touching.insert(if a < b { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) });
+                }
+            }
+        }
+        let mut begun: Vec<&(String, String)> = touching.difference(&self.touching).collect();
+        let mut ended: Vec<&(String, String)> = self.touching.difference(&touching).collect();
+        begun.sort();
+        ended.sort();
+        for (event, pairs) in [("collide", begun), ("separate", ended)] {
+            for (a, b) in pairs {
+                events.push(Event::new(event, &[a.as_str(), b.as_str()]));
+            }
+        }
+        self.touching = touching;
+        (changes, events)
+    }
+}
@@ -0,0 +1,227 @@
+
+// the nodes and how they hang together. the scene service keeps one and every renderer keeps a copy, brought up to date
+// by the changes the service sends out:
+//
+//     {"op":"set","node":{..}}       the node as it now is, whether it is new or changed
+//     {"op":"delete","id":".."}      the node and everything under it are gone
+//     {"op":"reset"}                 start again from nothing
+//
+// changes always travel as a json array, in order, and a parent is always set before its children.
+
+use std::collections::HashMap;
+
+use service::interface::Json;
+
+use crate::math::*;
+use crate::node::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Set(Box<Node>),
+    Delete(String),
+    Reset,
+}
+
+impl Change {
+    pub fn to_json(&self) -> String {
+        match self {
+            Change::Set(node) => format!("{{\"op\":\"set\",\"node\":{}}}", node.to_json()),
+            Change::Delete(id) => format!("{{\"op\":\"delete\",\"id\":{}}}", service::interface::quote(id)),
+            Change::Reset => "{\"op\":\"reset\"}".to_string(),
+        }
+    }
+}
+
+/// Some changes as the array they travel in
+pub fn changes(changes: &[Change]) -> String {
+    format!("[{}]", changes.iter().map(|change| change.to_json()).collect::<Vec<String>>().join(","))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    nodes: HashMap<String, Node>,
+    // the nodes with no parent, in the order they were added
+    roots: Vec<String>,
+}
+
+impl Graph {
+
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    /// Every node, each parent before its children
+    pub fn walk(&self) -> Vec<&Node> {
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for root in &self.roots {
+            self.walk_from(root, &mut nodes);
+        }
+        nodes
+    }
+
+    fn walk_from<'a>(&'a self, id: &str, nodes: &mut Vec<&'a Node>) {
+        if let Some(node) = self.nodes.get(id) {
+            nodes.push(node);
+            for child in &node.children {
+                self.walk_from(child, nodes);
+            }
+        }
+    }
+
+    /// The node and everything under it
+    pub fn subtree(&self, id: &str) -> Vec<String> {
+        let mut nodes = Vec::new();
+        self.walk_from(id, &mut nodes);
+        nodes.iter().map(|node| node.id.clone()).collect()
+    }
+
+    /// Where the node is in the scene, with all its parents taken into account
+    pub fn world(&self, id: &str) -> Option<Mat4> {
+        let mut node = self.nodes.get(id)?;
+        let mut matrix = node.matrix();
+        while let Some(parent) = node.parent.as_ref().and_then(|parent| self.nodes.get(parent)) {
+            matrix = mul(&parent.matrix(), &matrix);
+            node = parent;
+        }
+        Some(matrix)
+    }
+
+    /// Seen from the world, whether the node would be drawn - it and all its parents are visible
+    pub fn shown(&self, id: &str) -> bool {
+        let mut at = self.nodes.get(id);
+        while let Some(node) = at {
+            if !node.visible {
+                return false;
+            }
+            at = node.parent.as_ref().and_then(|parent| self.nodes.get(parent));
+        }
+        true
+    }
+
+    /// Add a node that is not there yet, under a parent that is
+    pub fn create(&mut self, node: Node) -> Result<Change, String> {
+        if node.id.is_empty() {
+            return Err("a node needs an id".to_string());
+        }
+        if self.nodes.contains_key(&node.id) {
+            return Err(format!("{} already exists", node.id));
+        }
+        if let Some(parent) = &node.parent {
+            if !self.nodes.contains_key(parent) {
+                return Err(format!("no parent {} for {}", parent, node.id));
+            }
+        }
+        self.set(Node { children: Vec::new(), ..node.clone() });
+        Ok(Change::Set(Box::new(self.nodes[&node.id].clone())))
+    }
+
+    /// Change some fields of a node, maybe moving it under another parent or out to the top with "parent":null
+    pub fn update(&mut self, id: &str, json: &Json) -> Result<Change, String> {
+        let mut node = self.nodes.get(id).ok_or(format!("no node {}", id))?.clone();
+        match json.get("parent") {
+            None => { },
+            Some(Json::Null) => node.parent = None,
+            Some(Json::String(parent)) => {
+                if !self.nodes.contains_key(parent) {
+                    return Err(format!("no parent {} for {}", parent, id));
+                }
+                if self.subtree(id).contains(parent) {
+                    return Err(format!("{} cannot go under itself", id));
+                }
+                node.parent = Some(parent.clone());
+            },
+            Some(_) => return Err("parent is an id or null".to_string()),
+        }
+        node.merge(json).map_err(|err| format!("{}: {}", id, err))?;
+        self.set(node);
+        Ok(Change::Set(Box::new(self.nodes[id].clone())))
+    }
+
+    /// Take away a node and everything under it
+    pub fn delete(&mut self, id: &str) -> Result<Change, String> {
+        let node = self.nodes.get(id).ok_or(format!("no node {}", id))?;
+        let parent = node.parent.clone();
+        self.unlink(id, parent.as_deref());
+        for id in self.subtree(id) {
+            self.nodes.remove(&id);
+        }
+        Ok(Change::Delete(id.to_string()))
+    }
+
+    /// Put a node in as it is, replacing any with the same id but keeping its children - the parent has to be there
+    /// already, which the changes a graph sends out make sure of
+    pub fn set(&mut self, mut node: Node) {
+        let old = self.nodes.remove(&node.id);
+        node.children = Vec::new();
+        if let Some(old) = old {
+            node.children = old.children;
+            if old.parent == node.parent {
+                self.nodes.insert(node.id.clone(), node);
+                return;
+            }
+            self.unlink(&node.id, old.parent.as_deref());
+        }
+        match node.parent.as_ref().and_then(|parent| self.nodes.get_mut(parent)) {
+            Some(parent) => parent.children.push(node.id.clone()),
+            None => {
+                node.parent = None;
+                self.roots.push(node.id.clone());
+            },
+        }
+        self.nodes.insert(node.id.clone(), node);
+    }
+
+    fn unlink(&mut self, id: &str, parent: Option<&str>) {
+        let siblings = match parent.and_then(|parent| self.nodes.get_mut(parent)) {
+            Some(parent) => &mut parent.children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| sibling != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.roots.clear();
+    }
+
+    /// The changes that bring an empty graph - or one that has gone astray - to the same as this one
+    pub fn snapshot(&self) -> Vec<Change> {
+        let mut changes = vec![Change::Reset];
+        changes.extend(self.walk().into_iter().map(|node| Change::Set(Box::new(node.clone()))));
+        changes
+    }
+
+    /// Bring a copy up to date with changes as they were sent
+    pub fn apply(&mut self, text: &str) -> Result<(), String> {
+        let json = Json::parse(text)?;
+        let items = match &json {
+            Json::Array(items) => items.as_slice(),
+            _ => std::slice::from_ref(&json),
+        };
+        for item in items {
+            match string(item, "op")?.as_str() {
+                "set" => self.set(Node::parse(item.get("node").ok_or("set needs a node")?)?),
+                "delete" => { self.delete(&string(item, "id")?)?; },
+                "reset" => self.clear(),
+                op => return Err(format!("unknown change {}", op)),
+            }
+        }
+        Ok(())
+    }
+}
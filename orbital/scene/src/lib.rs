@@ -0,0 +1,207 @@
+
+//
+// Scene: one retained scene graph that every app draws into and every renderer mirrors
+//
+// apps send json on /scene, one request or an array of them that are handled in order:
+//
+//     {"op":"create","id":"sun","mesh":"sphere","material":{"color":"#ffcc00"}}
+//     {"op":"create","id":"earth","parent":"sun","position":[2,0,0],"scale":0.3,"mesh":"sphere"}
+//     {"op":"update","id":"earth","position":[0,0,2]}
+//     {"op":"delete","id":"sun"}
+//     {"op":"listen","reply":"/mine/events"}     send the scene's events to this topic, see below
+//     {"op":"clear"}                             take away everything the app made, and stop its events
+//
+// see node.rs for what a node may have. a request is tagged with who sent it as "{..} @owner", the same as on
+// /display, and untagged requests all count as one app. each app may only touch its own nodes and has to keep them
+// inside a region of the scene it is given - see owners.rs, and public/scene.manifest for how regions are laid out.
+// what the requests did goes out on /scene/changes for renderers to mirror with Graph::apply (see graph.rs), and
+// whenever a renderer subscribes the whole scene is sent again from a reset, so that a late one catches up. bad or
+// refused requests are reported on /log and stop the rest of their array.
+//
+// apps can also ask what is in the scene on /scene/query - raycasts, finding nodes by tag or kind or place, and where
+// a node is in the world, answered only with what the app may see. see query.rs.
+//
+// nodes can be given behaviors - turning, moving somewhere, following a path, swinging to and fro, colliding - which
+// the scene steps along every frame so that apps do not have to, telling what happens to each app that listens - and,
+// like queries, only what happens to nodes the app may see. see behavior.rs.
+//
+
+use crossbeam::channel::*;
+use service::*;
+use service::interface::{quote, Json};
+
+use std::time::{Duration, Instant};
+
+pub mod behavior;
+pub mod graph;
+pub mod math;
+pub mod node;
+pub mod owners;
+pub mod query;
+
+use behavior::{Event, Motion};
+use graph::{Change, Graph};
+use owners::{Owners, Rules};
+
+pub const SCENE: &str = "/scene";
+pub const CHANGES: &str = "/scene/changes";
+pub const QUERY: &str = "/scene/query";
+
+#[derive(Clone)]
+pub struct Scene {
+    manifest: String,
+}
+
+impl Scene {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/scene.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for Scene {
+    fn name(&self) -> &str { "Scene" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("Scene: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let rules = Rules::from_manifest(&manifest).unwrap_or_else(|err| {
+                log(&send, format!("Scene: bad manifest, using defaults: {}", err));
+                Rules::default()
+            });
+            // behaviors are stepped this often
+            let period = Duration::from_secs_f32(1.0 / manifest.get_or("fps", 30.0f32).max(1.0));
+
+            send.send(Message::Subscribe(sid, SCENE.to_string())).expect("error");
+            send.send(Message::Subscribe(sid, QUERY.to_string())).expect("error");
+            send.send(Message::Subscribe(sid, SUBSCRIBERS.to_string())).expect("error");
+
+            let mut graph = Graph::new();
+            let mut owners = Owners::new(rules);
+            let mut motion = Motion::new();
+            let mut mirrors = 0;
+            let mut last = Instant::now();
+            let mut next = last + period;
+            loop {
+                match recv.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                    Ok(Message::Event(topic, data)) if topic == SCENE => {
+                        let (request, owner) = owner(&data);
+                        let (changes, result) = handle(&mut graph, &mut owners, owner.unwrap_or("app"), request);
+                        if let Err(err) = result {
+                            log(&send, format!("Scene: {}", err));
+                        }
+                        if !changes.is_empty() {
+                            let _ = send.send(Message::Event(CHANGES.to_string(), graph::changes(&changes)));
+                        }
+                    },
+                    Ok(Message::Event(topic, data)) if topic == QUERY => {
+                        let (query, owner) = owner(&data);
+                        match ask(&graph, &owners, owner.unwrap_or("app"), query) {
+                            Ok((reply, answer)) => { let _ = send.send(Message::Event(reply, answer)); },
+                            Err(err) => log(&send, format!("Scene: bad query: {}", err)),
+                        }
+                    },
+                    Ok(Message::Event(topic, data)) if topic == SUBSCRIBERS => {
+                        // catch up a renderer that has just arrived - the others see the scene sent again, which is harmless
+                        if let Some((topic, count)) = data.rsplit_once(' ') {
+                            if topic == CHANGES {
+                                let count = count.parse().unwrap_or(0);
+                                if count > mirrors {
+                                    let _ = send.send(Message::Event(CHANGES.to_string(), graph::changes(&graph.snapshot())));
+                                }
+                                mirrors = count;
+                            }
+                        }
+                    },
+                    Ok(_) => { },
+                    Err(RecvTimeoutError::Timeout) => { },
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+
+                // step when it is time whatever came in, so that a flood of requests cannot hold things still
+                let now = Instant::now();
+                if now >= next {
+                    // by the time that has really gone by, but not so far after a stall that things jump
+                    let dt = now.duration_since(last).as_secs_f32().min(0.25);
+                    last = now;
+                    next += period;
+                    if next < now {
+                        next = now + period;
+                    }
+                    let (changes, events) = motion.step(&mut graph, &owners, dt);
+                    if !changes.is_empty() {
+                        let _ = send.send(Message::Event(CHANGES.to_string(), graph::changes(&changes)));
+                    }
+                    for (topic, event) in tell(&graph, &owners, &events) {
+                        let _ = send.send(Message::Event(topic, event));
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Carry out a request or an array of them from someone on the graph, giving back the changes that were made and the
+/// first thing that went wrong, which stops the rest
+pub fn handle(graph: &mut Graph, owners: &mut Owners, owner: &str, request: &str) -> (Vec<Change>, Result<(), String>) {
+    let mut changes = Vec::new();
+    let json = match Json::parse(request) {
+        Ok(json) => json,
+        Err(err) => return (changes, Err(format!("bad request: {}", err))),
+    };
+    let items = match &json {
+        Json::Array(items) => items.as_slice(),
+        _ => std::slice::from_ref(&json),
+    };
+    for item in items {
+        match owners.request(graph, owner, item) {
+            Ok(done) => changes.extend(done),
+            Err(err) => return (changes, Err(err)),
+        }
+    }
+    (changes, Ok(()))
+}
+
+/// Answer a query from someone, giving back the topic to reply on and the reply - or why it cannot be answered at all
+pub fn ask(graph: &Graph, owners: &Owners, owner: &str, query: &str) -> Result<(String, String), String> {
+    let json = Json::parse(query)?;
+    let id = match json.get("id") {
+        Some(Json::Number(id)) => id.parse::<u64>().map_err(|_| format!("bad id {}", id))?,
+        _ => return Err("a query needs an id".to_string()),
+    };
+    let reply = match json.get("reply") {
+        Some(Json::String(reply)) => owners::reply(reply)?,
+        _ => return Err("a query needs a reply topic".to_string()),
+    };
+    let answer = match query::answer(graph, owners, owner, &json) {
+        Ok(ok) => format!("{{\"id\":{},\"ok\":{}}}", id, ok),
+        Err(err) => format!("{{\"id\":{},\"error\":{}}}", id, quote(&err)),
+    };
+    Ok((reply, answer))
+}
+
+/// Who hears of what happened, as the topic to send on and the event - each app that listens hears what it may see
+pub fn tell(graph: &Graph, owners: &Owners, events: &[Event]) -> Vec<(String, String)> {
+    let mut told = Vec::new();
+    for (owner, topic) in owners.listeners() {
+        for event in events {
+            if event.nodes.iter().all(|id| owners.may_see(graph, &owner, id)) {
+                told.push((topic.clone(), event.to_json()));
+            }
+        }
+    }
+    told
+}
+
+fn log(send: &Sender<Message>, text: String) {
+    println!("{}", text);
+    let _ = send.send(Message::Event("/log".to_string(), text));
+}
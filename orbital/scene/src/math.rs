@@ -0,0 +1,149 @@
+
+// just enough 3d math for the scene - points, rotations as quaternions, and 4x4 matrices stored a column at a time
+
+pub type Vec3 = [f32; 3];
+
+/// A rotation as x, y, z, w
+pub type Quat = [f32; 4];
+
+/// Columns one after another, so the translation is in the last four
+pub type Mat4 = [f32; 16];
+
+pub const IDENTITY: Mat4 = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+pub fn add(a: Vec3, b: Vec3) -> Vec3 { [a[0] + b[0], a[1] + b[1], a[2] + b[2]] }
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 { [a[0] - b[0], a[1] - b[1], a[2] - b[2]] }
+
+pub fn scale(a: Vec3, s: f32) -> Vec3 { [a[0] * s, a[1] * s, a[2] * s] }
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 { a[0] * b[0] + a[1] * b[1] + a[2] * b[2] }
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn length(a: Vec3) -> f32 { dot(a, a).sqrt() }
+
+pub fn normalize(a: Vec3) -> Vec3 {
+    let length = length(a);
+    if length > 0.0 { scale(a, 1.0 / length) } else { a }
+}
+
+pub fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 { add(a, scale(sub(b, a), t)) }
+
+/// A rotation of some radians around an axis
+pub fn axis_angle(axis: Vec3, radians: f32) -> Quat {
+    let axis = normalize(axis);
+    let (s, c) = (radians / 2.0).sin_cos();
+    [axis[0] * s, axis[1] * s, axis[2] * s, c]
+}
+
+/// One rotation and then another - b after a
+pub fn quat_mul(b: Quat, a: Quat) -> Quat {
+    [
+        b[3] * a[0] + b[0] * a[3] + b[1] * a[2] - b[2] * a[1],
+        b[3] * a[1] - b[0] * a[2] + b[1] * a[3] + b[2] * a[0],
+        b[3] * a[2] + b[0] * a[1] - b[1] * a[0] + b[2] * a[3],
+        b[3] * a[3] - b[0] * a[0] - b[1] * a[1] - b[2] * a[2],
+    ]
+}
+
+pub fn quat_normalize(q: Quat) -> Quat {
+    let length = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    if length > 0.0 { [q[0] / length, q[1] / length, q[2] / length, q[3] / length] } else { [0.0, 0.0, 0.0, 1.0] }
+}
+
+/// The matrix that scales, then rotates, then moves
+pub fn compose(position: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+    let [x, y, z, w] = quat_normalize(rotation);
+    let (x2, y2, z2) = (x + x, y + y, z + z);
+    let (xx, xy, xz) = (x * x2, x * y2, x * z2);
+    let (yy, yz, zz) = (y * y2, y * z2, z * z2);
+    let (wx, wy, wz) = (w * x2, w * y2, w * z2);
+    [
+        (1.0 - (yy + zz)) * scale[0], (xy + wz) * scale[0], (xz - wy) * scale[0], 0.0,
+        (xy - wz) * scale[1], (1.0 - (xx + zz)) * scale[1], (yz + wx) * scale[1], 0.0,
+        (xz + wy) * scale[2], (yz - wx) * scale[2], (1.0 - (xx + yy)) * scale[2], 0.0,
+        position[0], position[1], position[2], 1.0,
+    ]
+}
+
+/// a then b, as b * a
+pub fn mul(b: &Mat4, a: &Mat4) -> Mat4 {
+    let mut out = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[column * 4 + row] = (0..4).map(|k| b[k * 4 + row] * a[column * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+pub fn transform_point(m: &Mat4, p: Vec3) -> Vec3 {
+    let w = m[3] * p[0] + m[7] * p[1] + m[11] * p[2] + m[15];
+    let w = if w != 0.0 { w } else { 1.0 };
+    [
+        (m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12]) / w,
+        (m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13]) / w,
+        (m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14]) / w,
+    ]
+}
+
+/// A direction moved by the matrix - turned and stretched but not shifted
+pub fn transform_vector(m: &Mat4, v: Vec3) -> Vec3 {
+    [
+        m[0] * v[0] + m[4] * v[1] + m[8] * v[2],
+        m[1] * v[0] + m[5] * v[1] + m[9] * v[2],
+        m[2] * v[0] + m[6] * v[1] + m[10] * v[2],
+    ]
+}
+
+/// The eight corners of a box
+pub fn corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+    let mut corners = [[0.0; 3]; 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        for axis in 0..3 {
+            corner[axis] = if index & (1 << axis) == 0 { min[axis] } else { max[axis] };
+        }
+    }
+    corners
+}
+
+/// Where the matrix puts the origin
+pub fn translation(m: &Mat4) -> Vec3 { [m[12], m[13], m[14]] }
+
+/// The matrix that undoes this one, if there is one
+pub fn invert(m: &Mat4) -> Option<Mat4> {
+    let mut inv = [0.0f32; 16];
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15] + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15] - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15] + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14] - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15] - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15] + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15] - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14] + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15] + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15] - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15] + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14] - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11] - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11] + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11] - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10] + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    for value in inv.iter_mut() {
+        *value /= det;
+    }
+    Some(inv)
+}
@@ -0,0 +1,379 @@
+
+// a node in the scene and the things it can have - each is optional, so a node with none of them is just a group that
+// moves its children around. in json a node looks like:
+//
+//     {"id":"earth","parent":"sun","position":[2,0,0],"rotation":[0,0,0,1],"scale":[1,1,1],
+//      "mesh":"sphere","material":{"color":[0,0.4,1,1],"shading":"lambert"},"tags":["planet"],"visible":true}
+//
+// and also "text":{"text":"hello","size":0.2,"color":..}, "light":{"kind":"point","color":..,"intensity":1},
+// "camera":{"fov":60,"near":0.1,"far":100} and "behaviors":[..] (see behavior.rs). colors are [r,g,b] or [r,g,b,a]
+// from 0 to 1, "#rrggbb", or a number like 0xffff00 the way scripts tend to write them. a mesh is "cube", "sphere" or
+// "plane", all a unit across, or the name of a gltf file in public/.
+
+use service::interface::{quote, Json};
+
+use crate::behavior::Behavior;
+use crate::math::*;
+
+pub type Color = [f32; 4];
+
+pub const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mesh {
+    Cube,
+    Sphere,
+    Plane,
+    File(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shading {
+    // the color as it is, whatever the lights
+    Unlit,
+    // one shade for each face
+    Flat,
+    // shaded smoothly by how squarely the light falls
+    Lambert,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub color: Color,
+    pub shading: Shading,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub text: String,
+    // how tall a line is, in scene units
+    pub size: f32,
+    pub color: Color,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Ambient,
+    Point,
+    // shines down the node's -z, like a camera looks
+    Directional,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    // how far it sees from top to bottom, in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub parent: Option<String>,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub mesh: Option<Mesh>,
+    pub material: Option<Material>,
+    pub text: Option<Text>,
+    pub light: Option<Light>,
+    pub camera: Option<Camera>,
+    pub tags: Vec<String>,
+    pub visible: bool,
+    // what it does by itself every frame, see behavior.rs
+    pub behaviors: Vec<Behavior>,
+    // the app that made it, set by the scene service - none for the region each app is given
+    pub owner: Option<String>,
+    // kept by the graph, in the order they were added
+    pub children: Vec<String>,
+}
+
+impl Node {
+
+    pub fn new(id: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            parent: None,
+            position: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0, 1.0, 1.0],
+            mesh: None,
+            material: None,
+            text: None,
+            light: None,
+            camera: None,
+            tags: Vec::new(),
+            visible: true,
+            behaviors: Vec::new(),
+            owner: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Where it is relative to its parent
+    pub fn matrix(&self) -> Mat4 {
+        compose(self.position, self.rotation, self.scale)
+    }
+
+    /// What sort of thing it is, by what it has - mesh, text, light, camera or group
+    pub fn kind(&self) -> &'static str {
+        if self.mesh.is_some() { "mesh" }
+        else if self.text.is_some() { "text" }
+        else if self.light.is_some() { "light" }
+        else if self.camera.is_some() { "camera" }
+        else { "group" }
+    }
+
+    /// The box it takes up around its own origin, before it is moved - meshes are a unit across and text runs along x
+    /// from the origin, each letter about 0.6 of the line tall. nothing for lights, cameras and groups.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        match (&self.mesh, &self.text) {
+            (Some(Mesh::Plane), _) => Some(([-0.5, 0.0, -0.5], [0.5, 0.0, 0.5])),
+            (Some(_), _) => Some(([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5])),
+            (None, Some(text)) => Some(([0.0, 0.0, 0.0], [text.text.chars().count() as f32 * text.size * 0.6, text.size, 0.0])),
+            (None, None) => None,
+        }
+    }
+
+    /// Take on whatever fields the json has - anything it leaves out stays as it was, and a component set to null
+    /// is taken away. the id and parent are left to the graph, and the owner to the scene service.
+    pub fn merge(&mut self, json: &Json) -> Result<(), String> {
+        if let Some(position) = json.get("position") {
+            self.position = vector(position, "position")?;
+        }
+        if let Some(rotation) = json.get("rotation") {
+            let q = numbers(rotation, "rotation")?;
+            if q.len() != 4 {
+                return Err("rotation is a quaternion [x,y,z,w]".to_string());
+            }
+            self.rotation = quat_normalize([q[0], q[1], q[2], q[3]]);
+        }
+        if let Some(scale) = json.get("scale") {
+            self.scale = match scale {
+                Json::Number(_) => { let s = number(scale, "scale")?; [s, s, s] },
+                _ => vector(scale, "scale")?,
+            };
+        }
+        if let Some(mesh) = json.get("mesh") {
+            self.mesh = match mesh {
+                Json::Null => None,
+                Json::String(name) => Some(match name.as_str() {
+                    "cube" => Mesh::Cube,
+                    "sphere" => Mesh::Sphere,
+                    "plane" => Mesh::Plane,
+                    "" => return Err("mesh needs a name".to_string()),
+                    file => Mesh::File(file.to_string()),
+                }),
+                _ => return Err("mesh is cube, sphere, plane or a file".to_string()),
+            };
+        }
+        if let Some(material) = json.get("material") {
+            self.material = match material {
+                Json::Null => None,
+                _ => Some(Material {
+                    color: optional(material, "color", color)?.unwrap_or(WHITE),
+                    shading: match material.get("shading") {
+                        None => Shading::Lambert,
+                        Some(Json::String(shading)) => match shading.as_str() {
+                            "unlit" => Shading::Unlit,
+                            "flat" => Shading::Flat,
+                            "lambert" => Shading::Lambert,
+                            _ => return Err(format!("unknown shading {}", shading)),
+                        },
+                        Some(_) => return Err("shading is unlit, flat or lambert".to_string()),
+                    },
+                }),
+            };
+        }
+        if let Some(text) = json.get("text") {
+            self.text = match text {
+                Json::Null => None,
+                Json::String(words) => Some(Text { text: words.clone(), size: 0.1, color: WHITE }),
+                _ => Some(Text {
+                    text: match text.get("text") {
+                        Some(Json::String(words)) => words.clone(),
+                        _ => return Err("text needs some text".to_string()),
+                    },
+                    size: optional(text, "size", number)?.unwrap_or(0.1),
+                    color: optional(text, "color", color)?.unwrap_or(WHITE),
+                }),
+            };
+        }
+        if let Some(light) = json.get("light") {
+            self.light = match light {
+                Json::Null => None,
+                _ => Some(Light {
+                    kind: match light.get("kind") {
+                        None => LightKind::Point,
+                        Some(Json::String(kind)) => match kind.as_str() {
+                            "ambient" => LightKind::Ambient,
+                            "point" => LightKind::Point,
+                            "directional" => LightKind::Directional,
+                            _ => return Err(format!("unknown light {}", kind)),
+                        },
+                        Some(_) => return Err("a light is ambient, point or directional".to_string()),
+                    },
+                    color: optional(light, "color", color)?.unwrap_or(WHITE),
+                    intensity: optional(light, "intensity", number)?.unwrap_or(1.0),
+                }),
+            };
+        }
+        if let Some(camera) = json.get("camera") {
+            self.camera = match camera {
+                Json::Null => None,
+                _ => Some(Camera {
+                    fov: optional(camera, "fov", number)?.unwrap_or(60.0),
+                    near: optional(camera, "near", number)?.unwrap_or(0.1),
+                    far: optional(camera, "far", number)?.unwrap_or(100.0),
+                }),
+            };
+        }
+        if let Some(tags) = json.get("tags") {
+            self.tags = match tags {
+                Json::Array(items) => items.iter().map(|item| match item {
+                    Json::String(tag) => Ok(tag.clone()),
+                    _ => Err("tags are strings".to_string()),
+                }).collect::<Result<Vec<String>, String>>()?,
+                _ => return Err("tags are a list of strings".to_string()),
+            };
+        }
+        if let Some(behaviors) = json.get("behaviors") {
+            self.behaviors = match behaviors {
+                Json::Null => Vec::new(),
+                Json::Array(items) => items.iter().map(Behavior::parse).collect::<Result<Vec<Behavior>, String>>()?,
+                _ => return Err("behaviors are a list".to_string()),
+            };
+        }
+        if let Some(visible) = json.get("visible") {
+            self.visible = match visible {
+                Json::Bool(visible) => *visible,
+                _ => return Err("visible is true or false".to_string()),
+            };
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut fields = vec![format!("\"id\":{}", quote(&self.id))];
+        if let Some(parent) = &self.parent {
+            fields.push(format!("\"parent\":{}", quote(parent)));
+        }
+        fields.push(format!("\"position\":{}", list(&self.position)));
+        fields.push(format!("\"rotation\":{}", list(&self.rotation)));
+        fields.push(format!("\"scale\":{}", list(&self.scale)));
+        if let Some(mesh) = &self.mesh {
+            fields.push(format!("\"mesh\":{}", quote(match mesh {
+                Mesh::Cube => "cube",
+                Mesh::Sphere => "sphere",
+                Mesh::Plane => "plane",
+                Mesh::File(file) => file,
+            })));
+        }
+        if let Some(material) = &self.material {
+            let shading = match material.shading { Shading::Unlit => "unlit", Shading::Flat => "flat", Shading::Lambert => "lambert" };
+            fields.push(format!("\"material\":{{\"color\":{},\"shading\":\"{}\"}}", list(&material.color), shading));
+        }
+        if let Some(text) = &self.text {
+            fields.push(format!("\"text\":{{\"text\":{},\"size\":{},\"color\":{}}}", quote(&text.text), text.size, list(&text.color)));
+        }
+        if let Some(light) = &self.light {
+            let kind = match light.kind { LightKind::Ambient => "ambient", LightKind::Point => "point", LightKind::Directional => "directional" };
+            fields.push(format!("\"light\":{{\"kind\":\"{}\",\"color\":{},\"intensity\":{}}}", kind, list(&light.color), light.intensity));
+        }
+        if let Some(camera) = &self.camera {
+            fields.push(format!("\"camera\":{{\"fov\":{},\"near\":{},\"far\":{}}}", camera.fov, camera.near, camera.far));
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|tag| quote(tag)).collect();
+            fields.push(format!("\"tags\":[{}]", tags.join(",")));
+        }
+        fields.push(format!("\"visible\":{}", self.visible));
+        if !self.behaviors.is_empty() {
+            let behaviors: Vec<String> = self.behaviors.iter().map(|behavior| behavior.to_json()).collect();
+            fields.push(format!("\"behaviors\":[{}]", behaviors.join(",")));
+        }
+        if let Some(owner) = &self.owner {
+            fields.push(format!("\"owner\":{}", quote(owner)));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// A whole node from json, as to_json writes it
+    pub fn parse(json: &Json) -> Result<Node, String> {
+        let mut node = Node::new(&string(json, "id")?);
+        node.parent = optional(json, "parent", string_of)?;
+        node.owner = optional(json, "owner", string_of)?;
+        node.merge(json)?;
+        Ok(node)
+    }
+}
+
+pub(crate) fn list(values: &[f32]) -> String {
+    format!("[{}]", values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(","))
+}
+
+pub(crate) fn string(json: &Json, key: &str) -> Result<String, String> {
+    match json.get(key) {
+        Some(value) => string_of(value, key),
+        None => Err(format!("missing {}", key)),
+    }
+}
+
+pub(crate) fn string_of(json: &Json, what: &str) -> Result<String, String> {
+    match json {
+        Json::String(text) => Ok(text.clone()),
+        _ => Err(format!("{} is a string", what)),
+    }
+}
+
+// a field that may be left out or null
+pub(crate) fn optional<T>(json: &Json, key: &str, read: fn(&Json, &str) -> Result<T, String>) -> Result<Option<T>, String> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => read(value, key).map(Some),
+    }
+}
+
+pub(crate) fn number(json: &Json, what: &str) -> Result<f32, String> {
+    match json {
+        Json::Number(n) => n.parse::<f32>().ok().filter(|n| n.is_finite()).ok_or(format!("{} is not a number: {}", what, n)),
+        _ => Err(format!("{} is a number", what)),
+    }
+}
+
+pub(crate) fn numbers(json: &Json, what: &str) -> Result<Vec<f32>, String> {
+    match json {
+        Json::Array(items) => items.iter().map(|item| number(item, what)).collect(),
+        _ => Err(format!("{} is a list of numbers", what)),
+    }
+}
+
+pub(crate) fn vector(json: &Json, what: &str) -> Result<Vec3, String> {
+    match numbers(json, what)?.as_slice() {
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(format!("{} is [x,y,z]", what)),
+    }
+}
+
+pub(crate) fn color(json: &Json, what: &str) -> Result<Color, String> {
+    let bytes = |rgb: u32| [(rgb >> 16 & 0xff) as f32 / 255.0, (rgb >> 8 & 0xff) as f32 / 255.0, (rgb & 0xff) as f32 / 255.0, 1.0];
+    match json {
+        Json::Number(n) => n.parse::<u32>().map(bytes).map_err(|_| format!("{} is not a color: {}", what, n)),
+        Json::String(text) if text.starts_with('#') && text.len() == 7 => {
+            u32::from_str_radix(&text[1..], 16).map(bytes).map_err(|_| format!("{} is not a color: {}", what, text))
+        },
+        _ => match numbers(json, what)?.as_slice() {
+            [r, g, b] => Ok([*r, *g, *b, 1.0]),
+            [r, g, b, a] => Ok([*r, *g, *b, *a]),
+            _ => Err(format!("{} is [r,g,b] or [r,g,b,a]", what)),
+        },
+    }
+}
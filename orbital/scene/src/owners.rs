@@ -0,0 +1,360 @@
+
+// who may do what to the scene. every node belongs to the app that made it, and only that app may change it, hang
+// things under it or take it away. each app is given a region of its own - a box in the scene none of the others can
+// have - and everything it makes has to fit inside that box, so no app can cover up another or the desktop:
+//
+//     - the region is a node "@<app>" put there by the scene, and what an app makes with no parent goes under it, so an
+//       app places things from the middle of its box. an app may not change its region.
+//     - regions are laid out in a grid and never over the chrome, the part of the scene kept for the desktop.
+//     - lights and cameras reach past any region - they light and look at the whole scene - so only the desktop may
+//       make them.
+//     - the desktop may do anything, and also send {"op":"hide","owner":".."} and {"op":"show","owner":".."} to hide or
+//       show everything an app has made, or {"op":"clear","owner":".."} to take it all away.
+//     - an app may send {"op":"clear"} to take away everything it has made and give back its region.
+//     - an app may send {"op":"listen","reply":".."} to hear of what happens to what it can see, on a topic of its own -
+//       not one that a service listens or speaks on.
+//
+// it is all set in public/scene.manifest:
+//
+//     desktop = desktop ../public/index.js     # who counts as the desktop
+//     region = 4 4 4                           # how big a region is
+//     origin = -6 2 -2                         # the middle of the first region
+//     columns = 4                              # regions are laid out this many across along x
+//     rows = 2                                 # and this many deep, going away along -z
+//     chrome = -8 0 0 8 4 2                    # the corners of the part of the scene kept for the desktop
+//
+
+use std::collections::HashMap;
+
+use service::Manifest;
+use service::interface::Json;
+
+use crate::graph::{Change, Graph};
+use crate::math::*;
+use crate::node::{Node, string};
+
+#[derive(Clone, Debug)]
+pub struct Rules {
+    pub desktop: Vec<String>,
+    pub region: Vec3,
+    pub origin: Vec3,
+    pub columns: usize,
+    pub rows: usize,
+    pub chrome: Option<(Vec3, Vec3)>,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            desktop: vec!["desktop".to_string(), "../public/index.js".to_string()],
+            region: [4.0, 4.0, 4.0],
+            origin: [-6.0, 2.0, -2.0],
+            columns: 4,
+            rows: 2,
+            chrome: Some(([-8.0, 0.0, 0.0], [8.0, 4.0, 2.0])),
+        }
+    }
+}
+
+impl Rules {
+
+    pub fn from_manifest(manifest: &Manifest) -> Result<Rules, String> {
+        let mut rules = Rules::default();
+        if let Some(desktop) = manifest.get("desktop") {
+            rules.desktop = desktop.split_whitespace().map(|owner| owner.to_string()).collect();
+        }
+        if let Some(region) = manifest.get("region") {
+            rules.region = match numbers(region)?.as_slice() {
+                [x, y, z] if *x > 0.0 && *y > 0.0 && *z > 0.0 => [*x, *y, *z],
+                _ => return Err(format!("region is a size x y z, not {}", region)),
+            };
+        }
+        if let Some(origin) = manifest.get("origin") {
+            rules.origin = match numbers(origin)?.as_slice() {
+                [x, y, z] => [*x, *y, *z],
+                _ => return Err(format!("origin is x y z, not {}", origin)),
+            };
+        }
+        rules.columns = manifest.get_or("columns", rules.columns);
+        rules.rows = manifest.get_or("rows", rules.rows);
+        if let Some(chrome) = manifest.get("chrome") {
+            rules.chrome = match numbers(chrome)?.as_slice() {
+                [] => None,
+                [x0, y0, z0, x1, y1, z1] => Some(([x0.min(*x1), y0.min(*y1), z0.min(*z1)], [x0.max(*x1), y0.max(*y1), z0.max(*z1)])),
+                _ => return Err(format!("chrome is two corners x y z x y z, not {}", chrome)),
+            };
+        }
+        Ok(rules)
+    }
+
+    pub fn is_desktop(&self, owner: &str) -> bool {
+        self.desktop.iter().any(|desktop| desktop == owner)
+    }
+
+    /// The middle of a region in the grid
+    pub fn slot(&self, index: usize) -> Vec3 {
+        let (column, row) = (index % self.columns.max(1), index / self.columns.max(1));
+        [self.origin[0] + column as f32 * self.region[0], self.origin[1], self.origin[2] - row as f32 * self.region[2]]
+    }
+
+    // whether a region at a slot would reach into the chrome
+    fn covers_chrome(&self, index: usize) -> bool {
+        match self.chrome {
+            None => false,
+            Some((min, max)) => {
+                let middle = self.slot(index);
+                (0..3).all(|axis| {
+                    let half = self.region[axis] / 2.0;
+                    middle[axis] - half < max[axis] && middle[axis] + half > min[axis]
+                })
+            },
+        }
+    }
+}
+
+fn numbers(text: &str) -> Result<Vec<f32>, String> {
+    text.split_whitespace().map(|number| number.parse::<f32>().map_err(|_| format!("{} is not a number", number))).collect()
+}
+
+// services that act on what they are sent - an app's answers and events never go to one, where they would be taken as
+// asked of it by whoever sent them on
+const SERVICES: &[&str] = &["/scene", "/display", "/audio", "/pixels", "/camera", "/recorder", "/viewsoft"];
+
+/// Check a topic someone wants answers or events sent back on - any but those services listen or speak on
+pub fn reply(topic: &str) -> Result<String, String> {
+    let under = |service: &&str| matches!(topic.strip_prefix(*service), Some(rest) if rest.is_empty() || rest.starts_with('/'));
+    if !topic.starts_with('/') || SERVICES.iter().any(under) || service::reserved(topic) {
+        return Err(format!("{} is not a topic to reply on", topic));
+    }
+    Ok(topic.to_string())
+}
+
+/// The id of the region an app is given
+pub fn region(owner: &str) -> String {
+    format!("@{}", owner)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Owners {
+    pub rules: Rules,
+    // which slot in the grid each app has
+    slots: HashMap<String, usize>,
+    // where each app that listens for events wants them
+    listeners: HashMap<String, String>,
+}
+
+impl Owners {
+
+    pub fn new(rules: Rules) -> Owners {
+        Owners { rules: rules, slots: HashMap::new(), listeners: HashMap::new() }
+    }
+
+    /// The apps with a region, and where they are in the grid
+    pub fn regions(&self) -> Vec<(String, usize)> {
+        let mut regions: Vec<(String, usize)> = self.slots.iter().map(|(owner, slot)| (owner.clone(), *slot)).collect();
+        regions.sort_by_key(|(_, slot)| *slot);
+        regions
+    }
+
+    /// The apps listening for events, and the topic each wants them on
+    pub fn listeners(&self) -> Vec<(String, String)> {
+        let mut listeners: Vec<(String, String)> = self.listeners.iter().map(|(owner, topic)| (owner.clone(), topic.clone())).collect();
+        listeners.sort();
+        listeners
+    }
+
+    /// Whether someone may change a node - their own, or any if they are the desktop
+    pub fn may_change(&self, graph: &Graph, owner: &str, id: &str) -> bool {
+        self.rules.is_desktop(owner) || graph.get(id).map(|node| node.owner.as_deref() == Some(owner)).unwrap_or(false)
+    }
+
+    /// Whether someone may know a node is there - their own and their region, the desktop's, and what other apps have
+    /// tagged "shared" and everything under that. the desktop may see everything.
+    pub fn may_see(&self, graph: &Graph, owner: &str, id: &str) -> bool {
+        if self.rules.is_desktop(owner) || id == region(owner) {
+            return true;
+        }
+        let mut at = graph.get(id);
+        let mut first = true;
+        while let Some(node) = at {
+            match &node.owner {
+                Some(theirs) if first && (theirs == owner || self.rules.is_desktop(theirs)) => return true,
+                _ => { },
+            }
+            if node.tags.iter().any(|tag| tag == "shared") {
+                return true;
+            }
+            first = false;
+            at = node.parent.as_ref().and_then(|parent| graph.get(parent));
+        }
+        false
+    }
+
+    // whether someone may hang things under a node - their own nodes and their own region
+    fn may_parent(&self, graph: &Graph, owner: &str, id: &str) -> bool {
+        self.may_change(graph, owner, id) || (id == region(owner) && graph.get(id).is_some())
+    }
+
+    /// Carry out one request from someone, giving back what changed
+    pub fn request(&mut self, graph: &mut Graph, owner: &str, item: &Json) -> Result<Vec<Change>, String> {
+        let op = string(item, "op")?;
+        let desktop = self.rules.is_desktop(owner);
+        match op.as_str() {
+            "create" => {
+                let id = string(item, "id")?;
+                if !desktop && id.starts_with('@') {
+                    return Err(format!("{} is a name kept for regions", id));
+                }
+                let mut node = Node::parse(item).map_err(|err| format!("{}: {}", id, err))?;
+                node.owner = Some(owner.to_string());
+                let mut changes = Vec::new();
+                if !desktop {
+                    match &node.parent {
+                        Some(parent) if !self.may_parent(graph, owner, parent) => return Err(format!("{} is not yours to add to", parent)),
+                        Some(_) => { },
+                        None => {
+                            if graph.get(&region(owner)).is_none() {
+                                changes.push(self.allocate(graph, owner)?);
+                            }
+                            node.parent = Some(region(owner));
+                        },
+                    }
+                }
+                let created = graph.create(node).and_then(|change| match self.fits(graph, owner, &id) {
+                    Ok(()) => Ok(change),
+                    Err(err) => { let _ = graph.delete(&id); Err(err) },
+                });
+                match created {
+                    Ok(change) => changes.push(change),
+                    Err(err) => {
+                        // a region given just now for this is taken back again
+                        if !changes.is_empty() {
+                            self.clear(graph, owner);
+                        }
+                        return Err(err);
+                    },
+                }
+                Ok(changes)
+            },
+            "update" => {
+                let id = string(item, "id")?;
+                if graph.get(&id).is_none() {
+                    return Err(format!("no node {}", id));
+                }
+                if !self.may_change(graph, owner, &id) {
+                    return Err(format!("{} is not yours to change", id));
+                }
+                let old = graph.get(&id).cloned();
+                let change = match (desktop, item.get("parent")) {
+                    (false, Some(Json::String(parent))) if !self.may_parent(graph, owner, parent) => {
+                        return Err(format!("{} is not yours to add to", parent));
+                    },
+                    // for an app the top is its region
+                    (false, Some(Json::Null)) => {
+                        let mut moved = item.clone();
+                        if let Json::Object(fields) = &mut moved {
+                            for (key, value) in fields.iter_mut() {
+                                if key == "parent" { *value = Json::String(region(owner)); }
+                            }
+                        }
+                        graph.update(&id, &moved)?
+                    },
+                    _ => graph.update(&id, item)?,
+                };
+                if let Err(err) = self.fits(graph, owner, &id) {
+                    if let Some(old) = old {
+                        graph.set(old);
+                    }
+                    return Err(err);
+                }
+                Ok(vec![change])
+            },
+            "delete" => {
+                let id = string(item, "id")?;
+                if graph.get(&id).is_none() {
+                    return Err(format!("no node {}", id));
+                }
+                if !self.may_change(graph, owner, &id) {
+                    return Err(format!("{} is not yours to take away", id));
+                }
+                Ok(vec![graph.delete(&id)?])
+            },
+            "clear" => {
+                let app = match item.get("owner") {
+                    None => owner.to_string(),
+                    Some(_) if !desktop => return Err("only the desktop can clear another app".to_string()),
+                    Some(_) => string(item, "owner")?,
+                };
+                self.listeners.remove(&app);
+                Ok(self.clear(graph, &app))
+            },
+            "listen" => {
+                self.listeners.insert(owner.to_string(), reply(&string(item, "reply")?)?);
+                Ok(Vec::new())
+            },
+            "hide" | "show" => {
+                if !desktop {
+                    return Err(format!("only the desktop can {} an app", op));
+                }
+                let app = string(item, "owner")?;
+                if graph.get(&region(&app)).is_none() {
+                    return Err(format!("{} has nothing in the scene", app));
+                }
+                let visible = if op == "show" { Json::Bool(true) } else { Json::Bool(false) };
+                Ok(vec![graph.update(&region(&app), &Json::Object(vec![("visible".to_string(), visible)]))?])
+            },
+            _ => Err(format!("unknown op {}", op)),
+        }
+    }
+
+    /// Take away everything an app has made, and its region
+    pub fn clear(&mut self, graph: &mut Graph, owner: &str) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let theirs: Vec<String> = graph.walk().iter().filter(|node| node.owner.as_deref() == Some(owner)).map(|node| node.id.clone()).collect();
+        for id in theirs.iter().chain(std::iter::once(&region(owner))) {
+            // a parent taken away first takes its children with it
+            if let Ok(change) = graph.delete(id) {
+                changes.push(change);
+            }
+        }
+        self.slots.remove(owner);
+        changes
+    }
+
+    // give an app the first free region that keeps clear of the chrome
+    fn allocate(&mut self, graph: &mut Graph, owner: &str) -> Result<Change, String> {
+        let slot = (0..self.rules.columns * self.rules.rows)
+            .find(|slot| !self.slots.values().any(|taken| taken == slot) && !self.rules.covers_chrome(*slot))
+            .ok_or("there is no room left in the scene for another app".to_string())?;
+        let mut node = Node::new(&region(owner));
+        node.position = self.rules.slot(slot);
+        node.tags = vec!["region".to_string()];
+        let change = graph.create(node)?;
+        self.slots.insert(owner.to_string(), slot);
+        Ok(change)
+    }
+
+    // whether everything an app has under a node is still inside its region, and none of it is a light or camera
+    pub(crate) fn fits(&self, graph: &Graph, owner: &str, id: &str) -> Result<(), String> {
+        if self.rules.is_desktop(owner) {
+            return Ok(());
+        }
+        let inverse = graph.world(&region(owner)).and_then(|world| invert(&world)).ok_or(format!("{} has no region", owner))?;
+        let half = scale(self.rules.region, 0.5);
+        for inside in graph.subtree(id) {
+            let node = match graph.get(&inside) { Some(node) => node, None => continue };
+            if node.light.is_some() || node.camera.is_some() {
+                return Err(format!("{} is a light or camera, which only the desktop may have", inside));
+            }
+            let matrix = mul(&inverse, &graph.world(&inside).unwrap_or(IDENTITY));
+            let (min, max) = node.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+            for corner in corners(min, max) {
+                let at = transform_point(&matrix, corner);
+                if (0..3).any(|axis| at[axis].abs() > half[axis] + 1e-4) {
+                    return Err(format!("{} would reach outside the region of {}", inside, owner));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+
+// questions about the scene, so that an app can find out what is there rather than keeping track of it all itself.
+// they are asked on /scene/query the way calls are made between services, tagged with who is asking:
+//
+//     {"id":1,"reply":"/myapp/answers","query":"raycast","from":[0,1,5],"direction":[0,0,-1],"far":100} @myapp
+//     {"id":2,"reply":"/myapp/answers","query":"find","tag":"planet","kind":"mesh"} @myapp
+//     {"id":3,"reply":"/myapp/answers","query":"within","min":[-1,-1,-1],"max":[1,1,1]} @myapp
+//     {"id":4,"reply":"/myapp/answers","query":"world","node":"earth"} @myapp
+//     {"id":5,"reply":"/myapp/answers","query":"node","node":"earth"} @myapp
+//
+// and answered on the reply topic with {"id":1,"ok":..} or {"id":1,"error":".."}. the reply topic is the app's own; one
+// a service listens or speaks on, such as /scene/changes, is refused. a raycast gives back what the ray hits nearest
+// first as [{"id":..,"distance":..,"point":[..]}], find and within give back ids, world gives back
+// {"matrix":[..16],"position":[..]} and node the node as it is sent to renderers.
+//
+// an app only ever hears about what it may see - its own nodes and region, the desktop's, and whatever another app
+// has tagged "shared" along with everything under that. the desktop sees everything. a raycast also passes through
+// whatever is not being drawn.
+
+use service::interface::{quote, Json};
+
+use crate::graph::Graph;
+use crate::math::*;
+use crate::node::*;
+use crate::owners::Owners;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub id: String,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+/// Answer a query for someone, as the json that goes in "ok"
+pub fn answer(graph: &Graph, owners: &Owners, owner: &str, query: &Json) -> Result<String, String> {
+    let sees = |id: &str| owners.may_see(graph, owner, id);
+    match string(query, "query")?.as_str() {
+        "raycast" => {
+            let from = vector(query.get("from").ok_or("raycast needs from")?, "from")?;
+            let direction = vector(query.get("direction").ok_or("raycast needs a direction")?, "direction")?;
+            if length(direction) == 0.0 {
+                return Err("the direction goes nowhere".to_string());
+            }
+            let far = optional(query, "far", number)?.unwrap_or(f32::INFINITY);
+            let hits: Vec<String> = raycast(graph, from, direction, far).into_iter().filter(|hit| sees(&hit.id))
+                .map(|hit| format!("{{\"id\":{},\"distance\":{},\"point\":{}}}", quote(&hit.id), hit.distance, list(&hit.point)))
+                .collect();
+            Ok(format!("[{}]", hits.join(",")))
+        },
+        "find" => {
+            let tag = optional(query, "tag", string_of)?;
+            let kind = optional(query, "kind", string_of)?;
+            if tag.is_none() && kind.is_none() {
+                return Err("find needs a tag or a kind".to_string());
+            }
+            Ok(ids(find(graph, tag.as_deref(), kind.as_deref()).into_iter().filter(|id| sees(id))))
+        },
+        "within" => {
+            let min = vector(query.get("min").ok_or("within needs min")?, "min")?;
+            let max = vector(query.get("max").ok_or("within needs max")?, "max")?;
+            Ok(ids(within(graph, min, max).into_iter().filter(|id| sees(id))))
+        },
+        "world" => {
+            let id = string(query, "node")?;
+            let matrix = graph.world(&id).filter(|_| sees(&id)).ok_or(format!("no node {}", id))?;
+            Ok(format!("{{\"matrix\":{},\"position\":{}}}", list(&matrix), list(&translation(&matrix))))
+        },
+        "node" => {
+            let id = string(query, "node")?;
+            Ok(graph.get(&id).filter(|_| sees(&id)).ok_or(format!("no node {}", id))?.to_json())
+        },
+        other => Err(format!("unknown query {}", other)),
+    }
+}
+
+/// What a ray from a point hits before it has gone far, nearest first - only things being drawn, and with meshes as
+/// their shapes and text as its box
+pub fn raycast(graph: &Graph, from: Vec3, direction: Vec3, far: f32) -> Vec<Hit> {
+    let direction = normalize(direction);
+    let mut hits = Vec::new();
+    for node in graph.walk() {
+        let bounds = match node.bounds() { Some(bounds) => bounds, None => continue };
+        if !graph.shown(&node.id) {
+            continue;
+        }
+        let inverse = match graph.world(&node.id).and_then(|world| invert(&world)) { Some(inverse) => inverse, None => continue };
+        // in the node's own space the ray is still a line and a distance along it is the same distance as outside
+        let origin = transform_point(&inverse, from);
+        let along = transform_vector(&inverse, direction);
+        let distance = match node.mesh {
+            Some(Mesh::Sphere) => sphere(origin, along),
+            _ => slab(origin, along, bounds),
+        };
+        if let Some(distance) = distance.filter(|distance| *distance <= far) {
+            hits.push(Hit { id: node.id.clone(), distance: distance, point: add(from, scale(direction, distance)) });
+        }
+    }
+    hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+    hits
+}
+
+// where a ray first meets the unit sphere, if it does ahead of it
+fn sphere(origin: Vec3, along: Vec3) -> Option<f32> {
+    let a = dot(along, along);
+    let b = 2.0 * dot(origin, along);
+    let c = dot(origin, origin) - 0.25;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)].iter().copied().find(|t| *t >= 0.0)
+}
+
+// where a ray first meets a box, if it does ahead of it - from inside the box that is where it starts
+fn slab(origin: Vec3, along: Vec3, (min, max): (Vec3, Vec3)) -> Option<f32> {
+    let (mut near, mut far) = (0.0f32, f32::INFINITY);
+    for axis in 0..3 {
+        if along[axis].abs() < 1e-9 {
+            if origin[axis] < min[axis] - 1e-6 || origin[axis] > max[axis] + 1e-6 {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - origin[axis]) / along[axis];
+        let b = (max[axis] - origin[axis]) / along[axis];
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+        if near > far {
+            return None;
+        }
+    }
+    Some(near)
+}
+
+/// The nodes with a tag, or of a kind (see Node::kind), or both
+pub fn find(graph: &Graph, tag: Option<&str>, kind: Option<&str>) -> Vec<String> {
+    graph.walk().into_iter()
+        .filter(|node| tag.map(|tag| node.tags.iter().any(|has| has == tag)).unwrap_or(true))
+        .filter(|node| kind.map(|kind| node.kind() == kind).unwrap_or(true))
+        .map(|node| node.id.clone())
+        .collect()
+}
+
+/// The nodes that reach into a box in the scene - by their bounds if they have some, otherwise where they are
+pub fn within(graph: &Graph, min: Vec3, max: Vec3) -> Vec<String> {
+    graph.walk().into_iter()
+        .filter(|node| match bounds(graph, &node.id) {
+            Some((low, high)) => (0..3).all(|axis| low[axis] <= max[axis] && high[axis] >= min[axis]),
+            None => false,
+        })
+        .map(|node| node.id.clone())
+        .collect()
+}
+
+/// The box a node takes up in the scene, lined up with the axes
+pub fn bounds(graph: &Graph, id: &str) -> Option<(Vec3, Vec3)> {
+    let world = graph.world(id)?;
+    let (min, max) = graph.get(id)?.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+    let mut low = [f32::INFINITY; 3];
+    let mut high = [f32::NEG_INFINITY; 3];
+    for corner in corners(min, max) {
+        let at = transform_point(&world, corner);
+        for axis in 0..3 {
+            low[axis] = low[axis].min(at[axis]);
+            high[axis] = high[axis].max(at[axis]);
+        }
+    }
+    Some((low, high))
+}
+
+fn ids(ids: impl Iterator<Item = String>) -> String {
+    format!("[{}]", ids.map(|id| quote(&id)).collect::<Vec<String>>().join(","))
+}
@@ -0,0 +1,379 @@
+
+// the scene graph on its own, and kept by the service with a renderer mirroring it
+
+use crossbeam::channel::*;
+use std::time::{Duration, Instant};
+
+use broker::*;
+use scene::*;
+use scene::behavior::*;
+use scene::graph::*;
+use scene::math::*;
+use scene::node::*;
+use scene::owners::*;
+use service::{Message, SID};
+use service::interface::Json;
+
+const PROBE: SID = 1;
+const SCENE_SID: SID = 2;
+
+// as the desktop, which may do anything anywhere
+fn request(graph: &mut Graph, text: &str) -> Result<(), String> {
+    handle(graph, &mut Owners::default(), "desktop", text).1
+}
+
+fn close(a: Vec3, b: Vec3) -> bool {
+    length(sub(a, b)) < 1e-5
+}
+
+#[test]
+fn nodes_hang_together() {
+    let mut graph = Graph::new();
+    request(&mut graph, r#"[
+        {"op":"create","id":"sun","rotation":[0,0.7071068,0,0.7071068],"mesh":"sphere"},
+        {"op":"create","id":"earth","parent":"sun","position":[2,0,0],"scale":0.5,"mesh":"sphere"},
+        {"op":"create","id":"moon","parent":"earth","position":[1,0,0]}
+    ]"#).unwrap();
+    assert_eq!(graph.roots(), ["sun".to_string()]);
+    assert_eq!(graph.subtree("sun"), vec!["sun", "earth", "moon"]);
+    assert_eq!(graph.get("moon").unwrap().kind(), "group");
+
+    // the sun is turned a quarter around y, so x becomes -z, and the earth is half size
+    let moon = graph.world("moon").unwrap();
+    assert!(close(translation(&moon), [0.0, 0.0, -2.5]), "{:?}", translation(&moon));
+
+    // ids are unique, parents must exist and a node cannot go under itself
+    assert!(request(&mut graph, r#"{"op":"create","id":"earth"}"#).is_err());
+    assert!(request(&mut graph, r#"{"op":"create","id":"mars","parent":"nowhere"}"#).is_err());
+    assert!(request(&mut graph, r#"{"op":"update","id":"sun","parent":"moon"}"#).is_err());
+    assert!(request(&mut graph, r#"{"op":"update","id":"earth","position":"far"}"#).is_err());
+
+    // moving a node out to the top takes its children with it
+    request(&mut graph, r#"{"op":"update","id":"earth","parent":null,"visible":false}"#).unwrap();
+    assert_eq!(graph.roots(), ["sun".to_string(), "earth".to_string()]);
+    assert!(close(translation(&graph.world("moon").unwrap()), [2.5, 0.0, 0.0]));
+    assert!(!graph.shown("moon") && graph.shown("sun"));
+
+    // a bad request in an array stops the rest, but what came before it stands
+    let (changes, result) = handle(&mut graph, &mut Owners::default(), "desktop", r#"[{"op":"delete","id":"earth"},{"op":"delete","id":"earth"},{"op":"delete","id":"sun"}]"#);
+    assert!(result.is_err());
+    assert_eq!(changes, vec![Change::Delete("earth".to_string())]);
+    assert!(graph.get("moon").is_none());
+    assert_eq!(graph.len(), 1);
+}
+
+#[test]
+fn nodes_travel_as_json() {
+    let mut node = Node::new("sign");
+    node.merge(&Json::parse(r##"{"position":[1,2,3],"mesh":"cube","material":{"color":16776960,"shading":"flat"},
+        "text":{"text":"say \"hi\"","size":0.5,"color":"#ff0000"},"light":{"kind":"directional","intensity":2},
+        "camera":{"fov":45},"tags":["a","b"],"visible":false}"##).unwrap()).unwrap();
+    assert_eq!(node.material.as_ref().unwrap().color, [1.0, 1.0, 0.0, 1.0]);
+    assert_eq!(node.material.as_ref().unwrap().shading, Shading::Flat);
+    assert_eq!(node.text.as_ref().unwrap().color, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(node.light.as_ref().unwrap().kind, LightKind::Directional);
+    assert_eq!(node.camera.as_ref().unwrap().far, 100.0);
+    assert_eq!(Node::parse(&Json::parse(&node.to_json()).unwrap()).unwrap(), node);
+
+    // null takes a component away
+    node.merge(&Json::parse(r#"{"mesh":null,"light":null}"#).unwrap()).unwrap();
+    assert_eq!(node.kind(), "text");
+    assert!(node.merge(&Json::parse(r#"{"material":{"shading":"shiny"}}"#).unwrap()).is_err());
+}
+
+#[test]
+fn renderers_mirror_the_service() {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Scene::new();
+    brokersend.send(Message::Channel(SCENE_SID,"scene".to_string(),localsend)).unwrap();
+    instance.start("scene".to_string(),SCENE_SID,brokersend.clone(),localrecv);
+
+    // wait until the service is listening before asking it for anything
+    brokersend.send(Message::Subscribe(PROBE,service::SUBSCRIBERS.to_string())).unwrap();
+    loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no scene service") {
+            Message::Event(_,data) if data == format!("{} 1",SCENE) => break,
+            _ => { },
+        }
+    }
+    brokersend.send(Message::Event(SCENE.to_string(),r#"[{"op":"create","id":"box","mesh":"cube"},{"op":"create","id":"lid","parent":"box"}] @one.js"#.to_string())).unwrap();
+
+    // a renderer arriving late is sent the whole scene, and then the changes as they happen
+    brokersend.send(Message::Subscribe(PROBE,CHANGES.to_string())).unwrap();
+    let mut mirror = Graph::new();
+    let next = || loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no changes") {
+            Message::Event(topic,data) if topic == CHANGES => break data,
+            _ => { },
+        }
+    };
+    // the snapshot may come before or after the creates, and either way the mirror ends up the same
+    while mirror.len() < 2 {
+        mirror.apply(&next()).unwrap();
+    }
+    brokersend.send(Message::Event(SCENE.to_string(),r#"{"op":"update","id":"lid","position":[0,1,0]} @one.js"#.to_string())).unwrap();
+    brokersend.send(Message::Event(SCENE.to_string(),r#"{"op":"delete","id":"box"} @one.js"#.to_string())).unwrap();
+    brokersend.send(Message::Event(SCENE.to_string(),r#"{"op":"create","id":"ball","mesh":"sphere"}"#.to_string())).unwrap();
+    while mirror.get("ball").is_none() {
+        mirror.apply(&next()).unwrap();
+    }
+    assert!(mirror.get("box").is_none() && mirror.get("lid").is_none());
+    assert_eq!(mirror.get("ball").unwrap().parent.as_deref(), Some("@app"));
+
+    // an app that goes away gives back its region
+    brokersend.send(Message::Event(SCENE.to_string(),r#"{"op":"clear"} @one.js"#.to_string())).unwrap();
+    while mirror.get("@one.js").is_some() {
+        mirror.apply(&next()).unwrap();
+    }
+    assert_eq!(mirror.walk().len(), 2);
+}
+
+#[test]
+fn behaviors_keep_going_however_busy_the_scene_is() {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,CHANGES.to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Scene::new();
+    brokersend.send(Message::Channel(SCENE_SID,"scene".to_string(),localsend.clone())).unwrap();
+    instance.start("scene".to_string(),SCENE_SID,brokersend.clone(),localrecv);
+    let spin = r#"{"op":"create","id":"spin","behaviors":[{"kind":"rotate","axis":[0,0,1],"speed":1}]} @desktop"#;
+    localsend.send(Message::Event(SCENE.to_string(),spin.to_string())).unwrap();
+
+    // keep the scene's queue from ever running dry for a while
+    let flood = std::thread::spawn(move || {
+        let until = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < until {
+            if localsend.len() < 1000 {
+                for _ in 0..100 {
+                    localsend.send(Message::Event(SCENE.to_string(),"[] @desktop".to_string())).unwrap();
+                }
+            } else {
+                std::thread::yield_now();
+            }
+        }
+    });
+
+    // the spin is made, maybe sent again to the probe as it arrives, and then turns frame after frame all the same
+    let mut turns = 0;
+    while turns < 5 {
+        match proberecv.recv_timeout(Duration::from_secs(1)).expect("the scene stood still") {
+            Message::Event(_,data) if data.contains("\"spin\"") => turns += 1,
+            _ => { },
+        }
+    }
+    flood.join().unwrap();
+}
+
+#[test]
+fn apps_keep_to_their_own() {
+    let mut graph = Graph::new();
+    let mut owners = Owners::new(Rules::default());
+    let mut ask = |owner: &str, text: &str| handle(&mut graph, &mut owners, owner, text);
+
+    // each app is given a region of its own, in the grid and out of the chrome, and what it makes goes in the middle
+    let (changes, result) = ask("a.js", r#"{"op":"create","id":"box","mesh":"cube"}"#);
+    assert!(result.is_ok() && changes.len() == 2);
+    ask("b.js", r#"{"op":"create","id":"ball","mesh":"sphere","position":[1,1,1]}"#).1.unwrap();
+
+    // no touching what another app made, or its region
+    assert!(ask("b.js", r#"{"op":"update","id":"box","position":[0,1,0]}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"delete","id":"box"}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"create","id":"hat","parent":"box"}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"update","id":"ball","parent":"@a.js"}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"update","id":"@b.js","visible":false}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"create","id":"@c.js"}"#).1.is_err());
+    assert!(ask("b.js", r#"{"op":"hide","owner":"a.js"}"#).1.is_err());
+
+    // nor reaching out of its region, however it is done
+    assert!(ask("a.js", r#"{"op":"create","id":"far","position":[3,0,0]}"#).1.is_err());
+    assert!(ask("a.js", r#"{"op":"update","id":"box","scale":5}"#).1.is_err());
+    ask("a.js", r#"{"op":"create","id":"arm","parent":"box","position":[1,0,0],"mesh":"cube","scale":0.5}"#).1.unwrap();
+    assert!(ask("a.js", r#"{"op":"update","id":"box","position":[1,0,0]}"#).1.is_err());
+    ask("a.js", r#"{"op":"update","id":"box","position":[0,1,0]}"#).1.unwrap();
+
+    // or lighting and looking at the whole scene
+    assert!(ask("a.js", r#"{"op":"create","id":"lamp","light":{"kind":"point"}}"#).1.is_err());
+    assert!(ask("a.js", r#"{"op":"update","id":"box","camera":{"fov":90}}"#).1.is_err());
+
+    // the desktop can hide and show an app, and do as it likes
+    ask("desktop", r#"{"op":"hide","owner":"a.js"}"#).1.unwrap();
+    ask("desktop", r#"{"op":"create","id":"menu","position":[0,1,1],"mesh":"plane","scale":20}"#).1.unwrap();
+    assert!(ask("a.js", r#"{"op":"update","id":"menu","visible":false}"#).1.is_err());
+
+    // regions run out, and an app that could not have one leaves nothing behind
+    for app in ["c", "d", "e", "f", "g", "h"] {
+        ask(app, &format!(r#"{{"op":"create","id":"{}","mesh":"cube"}}"#, app)).1.unwrap();
+    }
+    assert!(ask("i", r#"{"op":"create","id":"i"}"#).1.is_err());
+
+    assert!(close(translation(&graph.world("box").unwrap()), [-6.0, 3.0, -2.0]));
+    assert!(close(translation(&graph.world("ball").unwrap()), [-1.0, 3.0, -1.0]));
+    assert_eq!(graph.get("box").unwrap().owner.as_deref(), Some("a.js"));
+    assert_eq!(graph.get("box").unwrap().scale, [1.0, 1.0, 1.0]);
+    assert!(graph.get("far").is_none() && graph.get("@i").is_none());
+    assert!(graph.get("lamp").is_none() && graph.get("box").unwrap().camera.is_none());
+    assert!(!graph.shown("arm") && graph.shown("ball") && graph.shown("menu"));
+
+    // and come back when an app clears up
+    handle(&mut graph, &mut owners, "desktop", r#"{"op":"show","owner":"a.js"}"#).1.unwrap();
+    assert!(graph.shown("arm"));
+    handle(&mut graph, &mut owners, "a.js", r#"{"op":"clear"}"#).1.unwrap();
+    assert!(graph.get("box").is_none() && graph.get("@a.js").is_none());
+    handle(&mut graph, &mut owners, "i", r#"{"op":"create","id":"i"}"#).1.unwrap();
+    assert!(close(translation(&graph.world("i").unwrap()), [-6.0, 2.0, -2.0]));
+}
+
+#[test]
+fn queries_see_only_what_they_may() {
+    let mut graph = Graph::new();
+    let mut owners = Owners::new(Rules::default());
+    handle(&mut graph, &mut owners, "desktop", r#"{"op":"create","id":"dock","position":[0,1,1],"mesh":"cube","tags":["chrome"]}"#).1.unwrap();
+    handle(&mut graph, &mut owners, "desktop", r#"{"op":"create","id":"lamp","position":[0,3,0],"light":{"kind":"point"}}"#).1.unwrap();
+    handle(&mut graph, &mut owners, "a.js", r#"[{"op":"create","id":"near","position":[0,0,1],"mesh":"cube","tags":["rock"]},
+        {"op":"create","id":"far","position":[0,0,-1],"mesh":"sphere","scale":0.5,"tags":["rock"]}]"#).1.unwrap();
+    handle(&mut graph, &mut owners, "b.js", r#"[{"op":"create","id":"secret","mesh":"cube","tags":["rock"]},
+        {"op":"create","id":"sign","position":[0,1,0],"text":"hi","tags":["shared"]},
+        {"op":"create","id":"dot","parent":"sign","mesh":"cube","scale":0.1}]"#).1.unwrap();
+
+    // a ray down the middle of a's region from in front hits the near cube, then the far sphere, nearest first
+    let hits = query::raycast(&graph, [-6.0, 2.0, 5.0], [0.0, 0.0, -2.0], 100.0);
+    let ids: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+    assert_eq!(ids, vec!["near", "far"]);
+    assert!((hits[0].distance - 5.5).abs() < 1e-4 && (hits[1].distance - 7.75).abs() < 1e-4, "{:?}", hits);
+    assert!(close(hits[1].point, [-6.0, 2.0, -2.75]));
+    assert!(query::raycast(&graph, [-6.0, 2.0, 5.0], [0.0, 0.0, -1.0], 5.0).is_empty());
+    assert!(query::raycast(&graph, [-6.0, 2.0, 5.0], [0.0, 0.0, 1.0], 100.0).is_empty());
+
+    let answer = |owner: &str, text: &str| {
+        let (reply, answer) = ask(&graph, &owners, owner, text).unwrap();
+        assert_eq!(reply, "/answers");
+        Json::parse(&answer).unwrap()
+    };
+    let ok = |json: Json| json.get("ok").cloned().unwrap_or_else(|| panic!("{:?}", json));
+    let strings = |json: Json| match ok(json) {
+        Json::Array(items) => items.iter().map(|item| match item { Json::String(id) => id.clone(), _ => panic!() }).collect::<Vec<String>>(),
+        other => panic!("{:?}", other),
+    };
+
+    // each app finds its own rocks and not the other's, the desktop finds them all
+    assert_eq!(strings(answer("a.js", r#"{"id":1,"reply":"/answers","query":"find","tag":"rock"}"#)), vec!["near", "far"]);
+    assert_eq!(strings(answer("b.js", r#"{"id":2,"reply":"/answers","query":"find","tag":"rock"}"#)), vec!["secret"]);
+    assert_eq!(strings(answer("desktop", r#"{"id":3,"reply":"/answers","query":"find","tag":"rock"}"#)).len(), 3);
+    assert_eq!(strings(answer("a.js", r#"{"id":4,"reply":"/answers","query":"find","kind":"light"}"#)), vec!["lamp"]);
+
+    // what b shares, and the desktop's chrome, everyone can see
+    assert_eq!(strings(answer("a.js", r#"{"id":5,"reply":"/answers","query":"find","kind":"mesh"}"#)), vec!["dock", "near", "far", "dot"]);
+    assert_eq!(strings(answer("a.js", r#"{"id":6,"reply":"/answers","query":"within","min":[-3,2.5,-3],"max":[-1,3.5,-1]}"#)), vec!["sign", "dot"]);
+    assert_eq!(strings(answer("b.js", r#"{"id":7,"reply":"/answers","query":"within","min":[-7,1,-3],"max":[-5,3,-1]}"#)), Vec::<String>::new());
+
+    let world = ok(answer("a.js", r#"{"id":8,"reply":"/answers","query":"world","node":"near"}"#));
+    assert_eq!(world.get("position"), Some(&Json::parse("[-6,2,-1]").unwrap()));
+    let node = answer("b.js", r#"{"id":9,"reply":"/answers","query":"node","node":"near"}"#);
+    assert_eq!(node.get("error"), Some(&Json::String("no node near".to_string())));
+    assert_eq!(node.get("id"), Some(&Json::Number("9".to_string())));
+    assert!(ask(&graph, &owners, "a.js", r#"{"query":"find","tag":"rock"}"#).is_err());
+
+    // and answers only ever go back to the app, never to a service that would take them as asked of it
+    for reply in ["/scene", "/scene/changes", "/display", "/subscribers", "/camera/status", "answers"] {
+        let query = format!(r#"{{"id":10,"reply":"{}","query":"find","tag":"rock"}}"#, reply);
+        assert!(ask(&graph, &owners, "a.js", &query).is_err(), "{}", reply);
+    }
+    assert!(ask(&graph, &owners, "a.js", r#"{"id":11,"reply":"/scenery","query":"find","tag":"rock"}"#).is_ok());
+}
+
+#[test]
+fn behaviors_move_things_along() {
+    let mut graph = Graph::new();
+    let mut owners = Owners::new(Rules::default());
+    let mut motion = Motion::new();
+    request(&mut graph, r#"[
+        {"op":"create","id":"spin","behaviors":[{"kind":"3d/behavior/rotate","axis":[0,0,1],"speed":1.5707964}]},
+        {"op":"create","id":"slide","behaviors":[{"kind":"tween","to":[2,0,0],"seconds":1}]},
+        {"op":"create","id":"walk","behaviors":[{"kind":"path","points":[[0,0,0],[1,0,0],[1,1,0]],"speed":1,"loop":true}]},
+        {"op":"create","id":"bob","position":[0,5,0],"behaviors":[{"kind":"oscillate","offset":[0,1,0],"period":4}]}
+    ]"#).unwrap();
+    assert!(request(&mut graph, r#"{"op":"create","id":"bad","behaviors":[{"kind":"dance"}]}"#).is_err());
+
+    // half a second in everything is part of the way there
+    let (changes, events) = motion.step(&mut graph, &owners, 0.5);
+    assert_eq!(changes.len(), 4);
+    assert!(events.is_empty());
+    let spin = graph.world("spin").unwrap();
+    assert!(close(transform_vector(&spin, [1.0, 0.0, 0.0]), [0.70710677, 0.70710677, 0.0]));
+    assert!(close(graph.get("slide").unwrap().position, [1.0, 0.0, 0.0]));
+    assert!(close(graph.get("walk").unwrap().position, [0.5, 0.0, 0.0]));
+    assert!(close(graph.get("bob").unwrap().position, [0.0, 5.70710677, 0.0]));
+
+    // the tween gets there, says so and is done, and the path carries on round the corner
+    let (_, events) = motion.step(&mut graph, &owners, 1.0);
+    assert_eq!(json(&events), vec![r#"{"event":"arrived","node":"slide"}"#.to_string()]);
+    assert!(close(graph.get("slide").unwrap().position, [2.0, 0.0, 0.0]));
+    assert!(graph.get("slide").unwrap().behaviors.is_empty());
+    assert!(close(graph.get("walk").unwrap().position, [1.0, 0.5, 0.0]));
+    assert!(close(graph.get("bob").unwrap().position, [0.0, 5.70710677, 0.0]));
+    let (changes, _) = motion.step(&mut graph, &owners, 1.0);
+    assert_eq!(changes.len(), 3);
+    assert!(close(graph.get("walk").unwrap().position, [0.6464466, 0.6464466, 0.0]));
+    assert!(close(graph.get("bob").unwrap().position, [0.0, 4.2928934, 0.0]));
+
+    // an app's nodes cannot wander out of its region, and colliding nodes say when they meet and part
+    handle(&mut graph, &mut owners, "a.js", r#"[{"op":"create","id":"ship","mesh":"cube","behaviors":[{"kind":"tween","to":[1,0,0],"seconds":1},{"kind":"collide"}]},
+        {"op":"create","id":"rock","position":[1.5,0,0],"mesh":"cube","scale":0.5,"behaviors":[{"kind":"collide"}]},
+        {"op":"create","id":"wanderer","behaviors":[{"kind":"tween","to":[10,0,0],"seconds":1}]}]"#).1.unwrap();
+    let (_, events) = motion.step(&mut graph, &owners, 0.5);
+    assert!(json(&events).contains(&r#"{"event":"blocked","node":"wanderer"}"#.to_string()), "{:?}", events);
+    assert!(graph.get("wanderer").unwrap().behaviors.is_empty());
+    let (_, events) = motion.step(&mut graph, &owners, 0.5);
+    assert_eq!(json(&events), vec![r#"{"event":"arrived","node":"ship"}"#.to_string(), r#"{"event":"collide","nodes":["rock","ship"]}"#.to_string()]);
+    handle(&mut graph, &mut owners, "a.js", r#"{"op":"update","id":"rock","visible":false}"#).1.unwrap();
+    let (_, events) = motion.step(&mut graph, &owners, 0.1);
+    assert_eq!(json(&events), vec![r#"{"event":"separate","nodes":["rock","ship"]}"#.to_string()]);
+}
+
+fn json(events: &[Event]) -> Vec<String> {
+    events.iter().map(|event| event.to_json()).collect()
+}
+
+#[test]
+fn events_go_to_those_who_listen_and_may_see() {
+    let mut graph = Graph::new();
+    let mut owners = Owners::new(Rules::default());
+    let mut motion = Motion::new();
+    handle(&mut graph, &mut owners, "a.js", r#"[{"op":"create","id":"ship","mesh":"cube","behaviors":[{"kind":"tween","to":[1,0,0],"seconds":1},{"kind":"collide"}]},
+        {"op":"listen","reply":"/a/events"}]"#).1.unwrap();
+    // b has a rock of its own off in its region, and c shares a moon
+    handle(&mut graph, &mut owners, "b.js", r#"[{"op":"create","id":"rock","mesh":"cube","behaviors":[{"kind":"collide"}]},
+        {"op":"listen","reply":"/b/events"}]"#).1.unwrap();
+    handle(&mut graph, &mut owners, "c.js", r#"{"op":"create","id":"moon","tags":["shared"],"behaviors":[{"kind":"tween","to":[1,0,0],"seconds":1}]}"#).1.unwrap();
+    handle(&mut graph, &mut owners, "desktop", r#"[{"op":"listen","reply":"/desktop/events"},
+        {"op":"create","id":"a-rock","parent":"ship","mesh":"cube","behaviors":[{"kind":"collide"}]}]"#).1.unwrap();
+    assert!(handle(&mut graph, &mut owners, "c.js", r#"{"op":"listen"}"#).1.is_err());
+    assert!(handle(&mut graph, &mut owners, "c.js", r#"{"op":"listen","reply":"/scene/changes"}"#).1.is_err());
+    assert_eq!(owners.listeners().iter().map(|(_, topic)| topic.as_str()).collect::<Vec<&str>>(), vec!["/a/events", "/b/events", "/desktop/events"]);
+
+    let (_, events) = motion.step(&mut graph, &owners, 1.0);
+    assert_eq!(json(&events), vec![
+        r#"{"event":"arrived","node":"ship"}"#.to_string(),
+        r#"{"event":"arrived","node":"moon"}"#.to_string(),
+        r#"{"event":"collide","nodes":["a-rock","ship"]}"#.to_string(),
+    ]);
+    let told = tell(&graph, &owners, &events);
+    let heard = |topic: &str| told.iter().filter(|(to, _)| to == topic).map(|(_, event)| event.as_str()).collect::<Vec<&str>>();
+    // a sees its own ship, the shared moon and the desktop's rock on its ship; b sees only the shared moon
+    assert_eq!(heard("/a/events"), vec![r#"{"event":"arrived","node":"ship"}"#, r#"{"event":"arrived","node":"moon"}"#, r#"{"event":"collide","nodes":["a-rock","ship"]}"#]);
+    assert_eq!(heard("/b/events"), vec![r#"{"event":"arrived","node":"moon"}"#]);
+    assert_eq!(heard("/desktop/events").len(), 3);
+
+    // an app that clears up stops hearing anything
+    handle(&mut graph, &mut owners, "a.js", r#"{"op":"clear"}"#).1.unwrap();
+    assert_eq!(owners.listeners().len(), 2);
+}
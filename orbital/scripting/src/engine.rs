@@ -0,0 +1,553 @@
+
+///
+/// Engine: a thin wrapper over the raw quickjs runtime
+///
+/// quick-js (the crate we used to use) hides the runtime pointer, which means there is no way to install an interrupt handler
+/// and a runaway `while(true)` in a script would pin the scripting thread forever. so here we talk to quickjs directly.
+///
+/// - every entry from rust into javascript (eval or call) gets a fresh cpu time budget
+/// - time spent inside rust callbacks (such as sleep) does not count against that budget
+/// - another thread can stop the engine for good through its Interrupter, say to tear a script down for a reload
+/// - when the budget is exhausted quickjs raises an uncatchable exception and the call unwinds back to us
+/// - quickjs allocates through us, so running out of memory is known from the allocation that was refused rather than from
+///   the message of whatever exception comes back - a script can throw "out of memory" itself
+/// - quickjs measures stack use from wherever the runtime was made, so make an engine at the top of the thread that drives it
+///
+
+use std::cell::Cell;
+use std::ffi::CString;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::alloc::{self, Layout};
+use std::os::raw::{c_int, c_void};
+use std::time::{Duration, Instant};
+
+use libquickjs_sys as q;
+
+///
+/// Limits: resources a single script is allowed to use
+///
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+	pub memory: usize,
+	pub time: Duration,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			memory: 4 * 1024 * 1024,
+			time: Duration::from_millis(2000),
+		}
+	}
+}
+
+///
+/// ScriptError: ways a script can fail
+///
+
+#[derive(Debug)]
+pub enum ScriptError {
+	Exception(Exception),
+	Interrupted(Duration),
+	Stopped,
+	OutOfMemory(usize),
+	Internal(String),
+}
+
+impl fmt::Display for ScriptError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ScriptError::Exception(exception) => write!(f, "{}", exception),
+			ScriptError::Interrupted(budget) => write!(f, "interrupted: exceeded cpu budget of {}ms", budget.as_millis()),
+			ScriptError::Stopped => write!(f, "stopped"),
+			ScriptError::OutOfMemory(limit) => write!(f, "out of memory: exceeded limit of {} bytes", limit),
+			ScriptError::Internal(message) => write!(f, "internal error: {}", message),
+		}
+	}
+}
+
+impl std::error::Error for ScriptError {}
+
+///
+/// Exception: a javascript exception along with where it was raised
+///
+
+#[derive(Clone, Debug)]
+pub struct Exception {
+	pub message: String,
+	pub file: String,
+	pub line: u32,
+	pub stack: String,
+}
+
+impl fmt::Display for Exception {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.file, self.line, self.message)?;
+		if !self.stack.is_empty() {
+			write!(f, "\n{}", self.stack.trim_end())?;
+		}
+		Ok(())
+	}
+}
+
+///
+/// A callback exposed to javascript; arguments arrive as strings and an Err becomes a javascript exception
+///
+
+pub type Callback = Box<dyn Fn(Vec<String>) -> Result<Option<String>, String>>;
+
+///
+/// Interrupter: stops an engine from another thread - whatever javascript is running unwinds and nothing runs after
+///
+
+#[derive(Clone, Default)]
+pub struct Interrupter {
+	stopped: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Interrupter {
+
+	pub fn new() -> Interrupter {
+		Interrupter::default()
+	}
+
+	pub fn interrupt(&self) {
+		let (stopped, wake) = &*self.stopped;
+		*stopped.lock().unwrap() = true;
+		wake.notify_all();
+	}
+
+	pub fn interrupted(&self) -> bool {
+		*self.stopped.0.lock().unwrap()
+	}
+
+	/// Wait for a while, or until interrupted; true if the whole time went by
+	pub fn sleep(&self, duration: Duration) -> bool {
+		let (stopped, wake) = &*self.stopped;
+		let stopped = stopped.lock().unwrap();
+		let (stopped, _) = wake.wait_timeout_while(stopped, duration, |stopped| !*stopped).unwrap();
+		!*stopped
+	}
+}
+
+// cpu time accounting - paused while rust callbacks run so that sleep() and friends are not charged to the script
+struct Budget {
+	limit: Duration,
+	spent: Cell<Duration>,
+	resumed: Cell<Option<Instant>>,
+	tripped: Cell<bool>,
+}
+
+impl Budget {
+	fn reset(&self) {
+		self.spent.set(Duration::from_millis(0));
+		self.resumed.set(Some(Instant::now()));
+		self.tripped.set(false);
+	}
+	fn pause(&self) {
+		if let Some(resumed) = self.resumed.take() {
+			self.spent.set(self.spent.get() + resumed.elapsed());
+		}
+	}
+	fn resume(&self) {
+		self.resumed.set(Some(Instant::now()));
+	}
+	fn elapsed(&self) -> Duration {
+		match self.resumed.get() {
+			Some(resumed) => self.spent.get() + resumed.elapsed(),
+			None => self.spent.get(),
+		}
+	}
+}
+
+// state reachable from quickjs via the context, runtime and allocator opaque pointers; boxed so that it never moves
+struct Shared {
+	budget: Budget,
+	callbacks: Vec<Callback>,
+	interrupter: Interrupter,
+	// whether an allocation went over the memory limit since javascript was last entered
+	refused: Cell<bool>,
+}
+
+pub struct Engine {
+	runtime: *mut q::JSRuntime,
+	context: *mut q::JSContext,
+	limits: Limits,
+	shared: Box<Shared>,
+}
+
+impl Engine {
+
+	pub fn new(limits: Limits) -> Result<Engine, ScriptError> {
+		let shared = Box::new(Shared {
+			budget: Budget {
+				limit: limits.time,
+				spent: Cell::new(Duration::from_millis(0)),
+				resumed: Cell::new(None),
+				tripped: Cell::new(false),
+			},
+			callbacks: Vec::new(),
+			interrupter: Interrupter::new(),
+			refused: Cell::new(false),
+		});
+		let opaque = &*shared as *const Shared as *mut c_void;
+		unsafe {
+			let runtime = q::JS_NewRuntime2(&ALLOCATOR, opaque);
+			if runtime.is_null() {
+				return Err(ScriptError::Internal("could not create runtime".to_string()));
+			}
+			q::JS_SetMemoryLimit(runtime, limits.memory as _);
+
+			let context = q::JS_NewContext(runtime);
+			if context.is_null() {
+				q::JS_FreeRuntime(runtime);
+				return Err(ScriptError::OutOfMemory(limits.memory));
+			}
+
+			q::JS_SetContextOpaque(context, opaque);
+			q::JS_SetInterruptHandler(runtime, Some(interrupt_handler), opaque);
+
+			Ok(Engine { runtime, context, limits, shared })
+		}
+	}
+
+	/// Expose a rust closure to javascript as a global function
+	pub fn add_callback<F>(&mut self, name: &str, callback: F) -> Result<(), ScriptError>
+		where F: Fn(Vec<String>) -> Result<Option<String>, String> + 'static
+	{
+		let index = self.shared.callbacks.len() as c_int;
+		self.shared.callbacks.push(Box::new(callback));
+		let name = cstring(name)?;
+		unsafe {
+			let func = q::JS_NewCFunctionData(self.context, Some(callback_trampoline), 0, index, 0, std::ptr::null_mut());
+			let global = q::JS_GetGlobalObject(self.context);
+			let res = q::JS_SetPropertyStr(self.context, global, name.as_ptr(), func);
+			q::JS_FreeValue(self.context, global);
+			if res < 0 {
+				return Err(self.take_exception());
+			}
+		}
+		Ok(())
+	}
+
+	/// Evaluate some source within a fresh cpu budget; the final expression is returned as a string
+	pub fn eval(&self, source: &str, filename: &str) -> Result<String, ScriptError> {
+		if self.shared.interrupter.interrupted() {
+			return Err(ScriptError::Stopped);
+		}
+		let source_c = cstring(source)?;
+		let filename_c = cstring(filename)?;
+		self.enter();
+		let value = unsafe {
+			q::JS_Eval(self.context, source_c.as_ptr(), source.len() as _, filename_c.as_ptr(), q::JS_EVAL_TYPE_GLOBAL as i32)
+		};
+		self.shared.budget.pause();
+		self.finish(value).map_err(|err| match err {
+			// a bare value was thrown so there is no stack to say where; it was at least somewhere in this file
+			ScriptError::Exception(mut exception) if exception.line == 0 && exception.stack.is_empty() => {
+				exception.file = filename.to_string();
+				ScriptError::Exception(exception)
+			},
+			err => err,
+		})
+	}
+
+	/// Call a global javascript function with string arguments within a fresh cpu budget; Ok(None) if there is no such function
+	pub fn call(&self, name: &str, args: &[&str]) -> Result<Option<String>, ScriptError> {
+		if self.shared.interrupter.interrupted() {
+			return Err(ScriptError::Stopped);
+		}
+		let name_c = cstring(name)?;
+		unsafe {
+			let global = q::JS_GetGlobalObject(self.context);
+			let func = q::JS_GetPropertyStr(self.context, global, name_c.as_ptr());
+			if q::JS_IsFunction(self.context, func) == 0 {
+				q::JS_FreeValue(self.context, func);
+				q::JS_FreeValue(self.context, global);
+				return Ok(None);
+			}
+			let mut argv: Vec<q::JSValue> = args.iter().map(|arg| new_string(self.context, arg)).collect();
+			self.enter();
+			let value = q::JS_Call(self.context, func, global, argv.len() as c_int, argv.as_mut_ptr());
+			self.shared.budget.pause();
+			for arg in argv {
+				q::JS_FreeValue(self.context, arg);
+			}
+			q::JS_FreeValue(self.context, func);
+			q::JS_FreeValue(self.context, global);
+			self.finish(value).map(Some)
+		}
+	}
+
+	/// Stop this engine with the given interrupter rather than its own
+	pub fn set_interrupter(&mut self, interrupter: Interrupter) {
+		self.shared.interrupter = interrupter;
+	}
+
+	pub fn interrupter(&self) -> Interrupter {
+		self.shared.interrupter.clone()
+	}
+
+	/// Bytes currently allocated by this runtime
+	pub fn memory_usage(&self) -> usize {
+		unsafe {
+			let mut usage: q::JSMemoryUsage = std::mem::zeroed();
+			q::JS_ComputeMemoryUsage(self.runtime, &mut usage);
+			usage.memory_used_size as usize
+		}
+	}
+
+	pub fn limits(&self) -> Limits {
+		self.limits
+	}
+
+	// every entry into javascript starts with a fresh cpu budget and a clean slate for memory
+	fn enter(&self) {
+		self.shared.budget.reset();
+		self.shared.refused.set(false);
+	}
+
+	// consume a value returned from javascript, converting exceptions into errors
+	fn finish(&self, value: q::JSValue) -> Result<String, ScriptError> {
+		unsafe {
+			if q::JS_IsException(value) {
+				return Err(self.take_exception());
+			}
+			let result = to_string(self.context, value);
+			q::JS_FreeValue(self.context, value);
+			result.ok_or_else(|| self.take_exception())
+		}
+	}
+
+	fn take_exception(&self) -> ScriptError {
+		if self.shared.interrupter.interrupted() {
+			unsafe { q::JS_FreeValue(self.context, q::JS_GetException(self.context)); }
+			return ScriptError::Stopped;
+		}
+		if self.shared.budget.tripped.get() {
+			unsafe { q::JS_FreeValue(self.context, q::JS_GetException(self.context)); }
+			return ScriptError::Interrupted(self.limits.time);
+		}
+		// once quickjs has been refused memory the script is over its limit, whatever it went on to throw
+		if self.shared.refused.get() {
+			unsafe { q::JS_FreeValue(self.context, q::JS_GetException(self.context)); }
+			return ScriptError::OutOfMemory(self.limits.memory);
+		}
+		unsafe {
+			let exception = q::JS_GetException(self.context);
+			let message = to_string(self.context, exception).unwrap_or_else(|| "unknown exception".to_string());
+
+			// errors carry a stack; anything else that was thrown (a bare string say) does not
+			let mut stack = String::new();
+			if q::JS_IsError(self.context, exception) != 0 {
+				stack = property(self.context, exception, "stack").unwrap_or_default();
+			}
+			q::JS_FreeValue(self.context, exception);
+
+			let (file, line) = location(&stack).unwrap_or_else(|| ("<unknown>".to_string(), 0));
+			ScriptError::Exception(Exception { message, file, line, stack })
+		}
+	}
+}
+
+impl Drop for Engine {
+	fn drop(&mut self) {
+		unsafe {
+			q::JS_FreeContext(self.context);
+			q::JS_FreeRuntime(self.runtime);
+		}
+	}
+}
+
+//////////////////////////////////////////////////////////////////////////
+// quickjs glue
+
+fn cstring(s: &str) -> Result<CString, ScriptError> {
+	CString::new(s).map_err(|_| ScriptError::Internal("string contains a zero byte".to_string()))
+}
+
+const UNDEFINED: q::JSValue = q::JSValue { u: q::JSValueUnion { int32: 0 }, tag: q::JS_TAG_UNDEFINED as i64 };
+
+// stringify any javascript value; None means the conversion itself threw
+unsafe fn to_string(context: *mut q::JSContext, value: q::JSValue) -> Option<String> {
+	let mut len: q::size_t = 0;
+	let ptr = q::JS_ToCStringLen2(context, &mut len, value, 0);
+	if ptr.is_null() {
+		return None;
+	}
+	let bytes = std::slice::from_raw_parts(ptr as *const u8, len as usize);
+	let s = String::from_utf8_lossy(bytes).into_owned();
+	q::JS_FreeCString(context, ptr);
+	Some(s)
+}
+
+unsafe fn property(context: *mut q::JSContext, value: q::JSValue, name: &str) -> Option<String> {
+	let name = CString::new(name).ok()?;
+	let prop = q::JS_GetPropertyStr(context, value, name.as_ptr());
+	let result = if q::JS_IsUndefined(prop) || q::JS_IsException(prop) { None } else { to_string(context, prop) };
+	q::JS_FreeValue(context, prop);
+	result
+}
+
+// the innermost script frame of a quickjs stack, which looks like "    at fn (file.js:12)" or for syntax errors "    at file.js:12"
+// quickjs sometimes leaves the line off a frame, in which case the nearest frame that has one is used
+fn location(stack: &str) -> Option<(String, u32)> {
+	let mut fallback = None;
+	for frame in stack.lines() {
+		let frame = frame.trim().trim_start_matches("at ");
+		let place = match (frame.rfind('('), frame.rfind(')')) {
+			(Some(start), Some(end)) if end > start => &frame[start+1..end],
+			_ => frame,
+		};
+		if place == "native" {
+			continue;
+		}
+		if let Some(colon) = place.rfind(':') {
+			if let Ok(line) = place[colon+1..].parse::<u32>() {
+				return Some((place[..colon].to_string(), line));
+			}
+		}
+		fallback = fallback.or_else(|| Some((place.to_string(), 0)));
+	}
+	fallback
+}
+
+unsafe fn new_string(context: *mut q::JSContext, s: &str) -> q::JSValue {
+	q::JS_NewStringLen(context, s.as_ptr() as *const _, s.len() as _)
+}
+
+// quickjs allocations, kept to the memory limit the same way quickjs's own allocator does - but noting when one is refused.
+// each block has its size in a header in front of it so that it can be freed and measured
+const HEADER: usize = 16;
+
+const ALLOCATOR: q::JSMallocFunctions = q::JSMallocFunctions {
+	js_malloc: Some(js_malloc),
+	js_free: Some(js_free),
+	js_realloc: Some(js_realloc),
+	js_malloc_usable_size: Some(js_malloc_usable_size),
+};
+
+fn block(size: usize) -> Option<Layout> {
+	Layout::from_size_align(size.checked_add(HEADER)?, HEADER).ok()
+}
+
+// whether size more bytes fit under the limit, noting it for the engine if not
+unsafe fn fits(state: &q::JSMallocState, size: usize) -> bool {
+	let fits = matches!((state.malloc_size as usize).checked_add(size), Some(total) if total <= state.malloc_limit as usize);
+	if !fits {
+		(*(state.opaque as *const Shared)).refused.set(true);
+	}
+	fits
+}
+
+unsafe extern "C" fn js_malloc(state: *mut q::JSMallocState, size: q::size_t) -> *mut c_void {
+	let state = &mut *state;
+	let size = size as usize;
+	let layout = match block(size) {
+		Some(layout) if fits(state, size) => layout,
+		_ => return std::ptr::null_mut(),
+	};
+	let start = alloc::alloc(layout);
+	if start.is_null() {
+		return std::ptr::null_mut();
+	}
+	*(start as *mut usize) = size;
+	state.malloc_count += 1;
+	state.malloc_size += layout.size() as q::size_t;
+	start.add(HEADER) as *mut c_void
+}
+
+unsafe extern "C" fn js_free(state: *mut q::JSMallocState, ptr: *mut c_void) {
+	if ptr.is_null() {
+		return;
+	}
+	let state = &mut *state;
+	let start = (ptr as *mut u8).sub(HEADER);
+	let layout = block(*(start as *mut usize)).unwrap();
+	state.malloc_count -= 1;
+	state.malloc_size -= layout.size() as q::size_t;
+	alloc::dealloc(start, layout);
+}
+
+unsafe extern "C" fn js_realloc(state: *mut q::JSMallocState, ptr: *mut c_void, size: q::size_t) -> *mut c_void {
+	if ptr.is_null() {
+		return if size == 0 { std::ptr::null_mut() } else { js_malloc(state, size) };
+	}
+	if size == 0 {
+		js_free(state, ptr);
+		return std::ptr::null_mut();
+	}
+	let state = &mut *state;
+	let size = size as usize;
+	let start = (ptr as *mut u8).sub(HEADER);
+	let old = block(*(start as *mut usize)).unwrap();
+	let layout = match block(size) {
+		Some(layout) if size <= old.size() - HEADER || fits(state, size - (old.size() - HEADER)) => layout,
+		_ => return std::ptr::null_mut(),
+	};
+	let start = alloc::realloc(start, old, layout.size());
+	if start.is_null() {
+		return std::ptr::null_mut();
+	}
+	*(start as *mut usize) = size;
+	state.malloc_size = state.malloc_size - old.size() as q::size_t + layout.size() as q::size_t;
+	start.add(HEADER) as *mut c_void
+}
+
+unsafe extern "C" fn js_malloc_usable_size(ptr: *const c_void) -> q::size_t {
+	if ptr.is_null() {
+		return 0;
+	}
+	*((ptr as *const u8).sub(HEADER) as *const usize) as q::size_t
+}
+
+unsafe extern "C" fn interrupt_handler(_runtime: *mut q::JSRuntime, opaque: *mut c_void) -> c_int {
+	let shared = &*(opaque as *const Shared);
+	if shared.interrupter.interrupted() {
+		return 1;
+	}
+	if shared.budget.elapsed() > shared.budget.limit {
+		shared.budget.tripped.set(true);
+		return 1;
+	}
+	0
+}
+
+unsafe extern "C" fn callback_trampoline(
+	context: *mut q::JSContext,
+	_this: q::JSValue,
+	argc: c_int,
+	argv: *mut q::JSValue,
+	magic: c_int,
+	_data: *mut q::JSValue,
+) -> q::JSValue {
+	let shared = &*(q::JS_GetContextOpaque(context) as *const Shared);
+
+	let mut args = Vec::with_capacity(argc as usize);
+	for i in 0..argc as isize {
+		match to_string(context, *argv.offset(i)) {
+			Some(arg) => args.push(arg),
+			None => return q::JSValue { u: q::JSValueUnion { int32: 0 }, tag: q::JS_TAG_EXCEPTION as i64 },
+		}
+	}
+
+	// a panic must not unwind across quickjs, so it is reported to the script like any other callback error
+	shared.budget.pause();
+	let callback = &shared.callbacks[magic as usize];
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(args)))
+		.unwrap_or_else(|_| Err("callback panicked".to_string()));
+	shared.budget.resume();
+
+	match result {
+		Ok(Some(value)) => new_string(context, &value),
+		Ok(None) => UNDEFINED,
+		Err(message) => {
+			let error = q::JS_NewError(context);
+			let key = CString::new("message").unwrap();
+			q::JS_SetPropertyStr(context, error, key.as_ptr(), new_string(context, &message));
+			q::JS_Throw(context, error)
+		}
+	}
+}
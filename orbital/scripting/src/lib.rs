@@ -1,78 +1,312 @@
 
 use std::fs;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use crossbeam::channel::*;
 use service::*;
 
-use quick_js::{Context, JsValue, console::Level };
+mod engine;
+pub use engine::{Engine, Exception, Interrupter, Limits, ScriptError};
+
+// how often loaded scripts are checked for changes on disk
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Clone)]
-pub struct Scripting {}
+pub struct Scripting {
+	limits: Limits,
+	scripts: Vec<String>,
+}
 impl Scripting {
 	pub fn new() -> Box<dyn Serviceable> {
-		Box::new(Self{})
+		Box::new(Self{ limits: Limits::default(), scripts: vec!["../public/index.js".to_string()] })
+	}
+	pub fn with_limits(limits: Limits) -> Box<dyn Serviceable> {
+		Box::new(Self{ limits: limits, scripts: vec!["../public/index.js".to_string()] })
+	}
+	pub fn with_scripts(limits: Limits, scripts: &[&str]) -> Box<dyn Serviceable> {
+		Box::new(Self{ limits: limits, scripts: scripts.iter().map(|s| s.to_string()).collect() })
 	}
 }
 impl Serviceable for Scripting {
 	fn name(&self) -> &str { "Scripting" }
 	fn stop(&self) {}
-	fn start(&self, _name: String, _sid: SID, send: Sender<Message>, recv: Receiver<Message> ) {
+	fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message> ) {
 		let send = send.clone();
 		let recv = recv.clone();
 		let name = self.name();
+		let limits = self.limits;
+		let paths = self.scripts.clone();
 		let _thread = std::thread::Builder::new().name(name.to_string()).spawn(move || {
 
-			// Start javascript engine
-			let context = Context::builder()
-				.memory_limit(100000)
-				.console(|level: Level, args: Vec<JsValue>| { println!("{}: {:?}", level, args); })
-				.build()
-				.unwrap();
-
-			// javascript sleep helper
-			// TODO later can do something like this : https://www.programmersought.com/article/13424789131/
-			let orbital_sleep = move |duration:i32| {
-				println!("javascript asked for sleep of duration {}",duration);
-	            std::thread::sleep(std::time::Duration::from_millis(duration as u64));
-				12341234
-			};
-			context.add_callback("sleep", orbital_sleep ).unwrap();
-
-			// javascript message pipeline helper
-			let orbital_message = move |_a:String| {
-				let message = Message::Event("/display".to_string(),_a.to_string());
-				let send2 = send.clone();
-				send2.send(message).expect("error");
-				12341234
-			};
-			context.add_callback("orbital_message", orbital_message ).unwrap();
+			// each script runs in its own javascript context on its own thread so that it can be torn down and reloaded by itself
+			let mut scripts: Vec<Script> = paths.iter().map(|path| Script::start(path, limits, sid, &send)).collect();
 
-			// add some other special helpers to the context as well - these happen to be written in js
-			let contents = fs::read_to_string("../public/index.js").expect("Something went wrong reading the file");
-			let value = context.eval_as::<String>(&contents).unwrap();
-			println!("result is {}",&value);
+			// wait for traffic for any of the scripts, and every so often - however much traffic there is - watch the scripts on disk
+			let mut next = Instant::now() + WATCH_INTERVAL;
+			loop {
+				match recv.recv_timeout(next.saturating_duration_since(Instant::now())) {
+					Ok(Message::Event(topic,data)) => {
+						for script in &scripts {
+							if script.topics.lock().unwrap().contains(&topic) {
+								let _ = script.send.send(Message::Event(topic.clone(),data.clone()));
+							}
+						}
+					},
+					Ok(_) => { },
+					Err(RecvTimeoutError::Timeout) => { },
+					Err(RecvTimeoutError::Disconnected) => break,
+				}
 
-			// unused stuff - tbd
-
-//            let contents = fs::read_to_string("scripts/bootsupport.js").expect("Something went wrong reading the file");
-
-			// wait for commands and then load and run those arbitrary scripts
-			// TODO right now this is not inside of a message handler - move there later
-			// TODO remove hardcoded test
-/*
-			let mut contents = fs::read_to_string("../apps/test3d/weathercard.js").expect("Something went wrong reading the file").to_owned();
-			let str2:String =  "orbital_script_parser(0,0,root);\n\"done\";\n".to_owned();
-			contents.push_str(&str2);
-			let value2 = context.eval_as::<String>(&contents).unwrap();
-			println!("result is {}",&value2);
-*/
-			// um... ? not sure if I should do anything here... if js file returns is it done? what about on_event handling?
-
-			while let Ok(message) = recv.recv() {
-				match message {
-					_ => { },
+				if Instant::now() < next {
+					continue;
+				}
+				next = Instant::now() + WATCH_INTERVAL;
+				for i in 0..scripts.len() {
+					if scripts[i].modified != modified(&scripts[i].path) {
+						println!("Scripting: reloading {}",scripts[i].path);
+						let old = scripts.remove(i);
+						let path = old.path.clone();
+						old.stop(sid, &send, &scripts);
+						scripts.insert(i, Script::start(&path, limits, sid, &send));
+					}
 				}
 			}
 		});
 	}
 }
 
+///
+/// Script: one running script file along with everything it has asked of the system
+///
+
+struct Script {
+	path: String,
+	modified: Option<SystemTime>,
+	send: Sender<Message>,
+	thread: Option<std::thread::JoinHandle<()>>,
+	interrupter: Interrupter,
+	topics: Arc<Mutex<HashSet<String>>>,
+	created: Arc<AtomicBool>,
+}
+
+impl Script {
+
+	fn start(path: &str, limits: Limits, sid: SID, send: &Sender<Message>) -> Script {
+		let (localsend,localrecv) = unbounded::<Message>();
+		let mut script = Script {
+			path: path.to_string(),
+			modified: modified(path),
+			send: localsend,
+			thread: None,
+			interrupter: Interrupter::new(),
+			topics: Arc::new(Mutex::new(HashSet::new())),
+			created: Arc::new(AtomicBool::new(false)),
+		};
+		let path = path.to_string();
+		let send = send.clone();
+		let topics = script.topics.clone();
+		let created = script.created.clone();
+		let interrupter = script.interrupter.clone();
+		script.thread = std::thread::Builder::new().name(format!("Scripting {}",path)).spawn(move || {
+
+			// the engine has to be built here at the top of the thread - quickjs measures stack use from where its runtime was made
+			let mut context = match Engine::new(limits) {
+				Ok(context) => context,
+				Err(err) => {
+					diagnostic(&send, format!("Scripting: {} could not start: {}", path, err));
+					return;
+				}
+			};
+			context.set_interrupter(interrupter);
+			run(context, &path, limits, sid, &send, &localrecv, topics, created);
+		}).ok();
+		script
+	}
+
+	// tear down the context, drop subscriptions that no remaining script still wants, and clear what it drew and played
+	// - the script is interrupted first, so one that is asleep or spinning does not hold up every other script while it is joined
+	fn stop(self, sid: SID, send: &Sender<Message>, remaining: &[Script]) {
+		self.interrupter.interrupt();
+		drop(self.send);
+		if let Some(thread) = self.thread {
+			let _ = thread.join();
+		}
+		for topic in self.topics.lock().unwrap().iter() {
+			if !remaining.iter().any(|script| script.topics.lock().unwrap().contains(topic)) {
+				let _ = send.send(Message::Unsubscribe(sid,topic.clone()));
+			}
+		}
+		if self.created.load(Ordering::SeqCst) {
+			let _ = send.send(Message::Event("/display".to_string(),format!("clear @{}",self.path)));
+		}
+		let _ = send.send(Message::Event("/audio".to_string(),format!("stop @{}",self.path)));
+		let _ = send.send(Message::Event("/scene".to_string(),format!("{{\"op\":\"clear\"}} @{}",self.path)));
+		let _ = send.send(Message::Event("/pixels".to_string(),format!("{{\"op\":\"clear\"}} @{}",self.path)));
+	}
+}
+
+// load a script into a fresh context and then hand it any traffic it subscribed to until it is stopped
+fn run(mut context: Engine, path: &str, limits: Limits, sid: SID, send: &Sender<Message>, recv: &Receiver<Message>,
+	topics: Arc<Mutex<HashSet<String>>>, created: Arc<AtomicBool>) {
+
+	let contents = match fs::read_to_string(path) {
+		Ok(contents) => contents,
+		Err(err) => {
+			log(send, format!("Scripting: cannot read {}: {}", path, err));
+			return;
+		}
+	};
+
+	// javascript console helper - the console object itself is written in js below
+	context.add_callback("__orbital_console", |args: Vec<String>| {
+		if let Some((level,args)) = args.split_first() {
+			println!("{}: {}", level, args.join(" "));
+		}
+		Ok(None)
+	}).unwrap();
+	context.eval(CONSOLE, "console.js").unwrap();
+
+	// javascript sleep helper - cut short if the script is stopped
+	// TODO later can do something like this : https://www.programmersought.com/article/13424789131/
+	let interrupter = context.interrupter();
+	let orbital_sleep = move |args: Vec<String>| {
+		let duration = args.first().and_then(|s| s.parse::<f64>().ok()).ok_or("sleep expects a duration in milliseconds")?;
+		println!("javascript asked for sleep of duration {}",duration);
+		if !interrupter.sleep(std::time::Duration::from_millis(duration as u64)) {
+			return Err("script stopped".to_string());
+		}
+		Ok(Some("12341234".to_string()))
+	};
+	context.add_callback("sleep", orbital_sleep ).unwrap();
+
+	// javascript message pipeline helper
+	// anything a script puts on the display is tagged with the script so that it can be cleared away on reload
+	let send2 = send.clone();
+	let owner = path.to_string();
+	let orbital_message = move |args: Vec<String>| {
+		let data = args.first().cloned().ok_or("orbital_message expects a message")?;
+		let message = Message::Event("/display".to_string(),format!("{} @{}",data,owner));
+		send2.send(message).expect("error");
+		created.store(true, Ordering::SeqCst);
+		Ok(Some("12341234".to_string()))
+	};
+	context.add_callback("orbital_message", orbital_message ).unwrap();
+
+	// javascript sound helper - orbital_sound("play ding.wav") or orbital_sound("tone 880 100"), tagged with
+	// the script like the display is so that its sounds can be stopped on reload
+	let send2 = send.clone();
+	let owner = path.to_string();
+	let orbital_sound = move |args: Vec<String>| {
+		let command = args.first().cloned().ok_or("orbital_sound expects a command")?;
+		send2.send(Message::Event("/audio".to_string(),format!("{} @{}",command,owner))).expect("error");
+		Ok(None)
+	};
+	context.add_callback("orbital_sound", orbital_sound ).unwrap();
+
+	// javascript scene helper - orbital_scene('{"op":"create","id":"box","mesh":"cube"}'), tagged with the script
+	let send2 = send.clone();
+	let owner = path.to_string();
+	let orbital_scene = move |args: Vec<String>| {
+		let request = args.first().cloned().ok_or("orbital_scene expects a request")?;
+		send2.send(Message::Event("/scene".to_string(),format!("{} @{}",request,owner))).expect("error");
+		Ok(None)
+	};
+	context.add_callback("orbital_scene", orbital_scene ).unwrap();
+
+	// javascript scene query helper - orbital_scene_query('{"id":1,"reply":"/mine","query":"find","tag":"planet"}') with the
+	// answer coming back on the reply topic, which the script subscribes to first
+	let send2 = send.clone();
+	let owner = path.to_string();
+	let orbital_scene_query = move |args: Vec<String>| {
+		let query = args.first().cloned().ok_or("orbital_scene_query expects a query")?;
+		send2.send(Message::Event("/scene/query".to_string(),format!("{} @{}",query,owner))).expect("error");
+		Ok(None)
+	};
+	context.add_callback("orbital_scene_query", orbital_scene_query ).unwrap();
+
+	// javascript 2d drawing helper - orbital_pixels('{"op":"rect","id":"panel","x":10,"y":10,"w":100,"h":60}'), tagged with
+	// the script so that what it drew is cleared away on reload
+	let send2 = send.clone();
+	let owner = path.to_string();
+	let orbital_pixels = move |args: Vec<String>| {
+		let request = args.first().cloned().ok_or("orbital_pixels expects a request")?;
+		send2.send(Message::Event("/pixels".to_string(),format!("{} @{}",request,owner))).expect("error");
+		Ok(None)
+	};
+	context.add_callback("orbital_pixels", orbital_pixels ).unwrap();
+
+	// javascript subscription helper - traffic on the topic is handed to the scripts global on_message(topic,data)
+	let send2 = send.clone();
+	let orbital_subscribe = move |args: Vec<String>| {
+		let topic = args.first().cloned().ok_or("orbital_subscribe expects a topic")?;
+		send2.send(Message::Subscribe(sid,topic.clone())).expect("error");
+		topics.lock().unwrap().insert(topic);
+		Ok(None)
+	};
+	context.add_callback("orbital_subscribe", orbital_subscribe ).unwrap();
+
+	// add some other special helpers to the context as well - these happen to be written in js
+	// a script that throws is reported on /log with where it happened; the context survives for any callbacks it set up
+	// a script that runs away or blows its memory limit is torn down and reported on /diagnostics
+	match context.eval(&contents, path) {
+		Ok(value) => {
+			println!("result is {}",&value);
+			diagnostic(send, format!("Scripting: {} using {} of {} bytes", path, context.memory_usage(), limits.memory));
+		},
+		Err(ScriptError::Exception(exception)) => {
+			log(send, format!("Scripting: error at {}", exception));
+		},
+		// stopped for a reload, which says so itself
+		Err(ScriptError::Stopped) => return,
+		Err(err) => {
+			diagnostic(send, format!("Scripting: {} terminated: {}", path, err));
+			return;
+		},
+	}
+
+	while let Ok(message) = recv.recv() {
+		match message {
+			Message::Event(topic,data) => {
+				match context.call("on_message", &[&topic, &data]) {
+					Ok(_) => { },
+					Err(ScriptError::Exception(exception)) => {
+						log(send, format!("Scripting: error in on_message at {}", exception));
+					},
+					Err(ScriptError::Stopped) => return,
+					Err(err) => {
+						diagnostic(send, format!("Scripting: {} terminated: {}", path, err));
+						return;
+					},
+				}
+			},
+			_ => { },
+		}
+	}
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// publish a script error for the desktop to show
+fn log(send: &Sender<Message>, text: String) {
+	println!("{}",text);
+	let _ = send.send(Message::Event("/log".to_string(),text));
+}
+
+// publish a message about script health - resource use, terminations and so on
+fn diagnostic(send: &Sender<Message>, text: String) {
+	println!("{}",text);
+	let _ = send.send(Message::Event("/diagnostics".to_string(),text));
+}
+
+const CONSOLE: &str = r#"
+	globalThis.console = {
+		log: (...args) => __orbital_console("log", ...args),
+		info: (...args) => __orbital_console("info", ...args),
+		warn: (...args) => __orbital_console("warn", ...args),
+		error: (...args) => __orbital_console("error", ...args),
+	};
+"#;
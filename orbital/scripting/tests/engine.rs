@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant};
+
+use scripting::{Engine, Interrupter, Limits, ScriptError};
+
+fn limits(memory: usize, time: u64) -> Limits {
+	Limits { memory: memory, time: Duration::from_millis(time) }
+}
+
+#[test]
+fn a_runaway_script_is_interrupted() {
+	let engine = Engine::new(limits(4 * 1024 * 1024, 100)).unwrap();
+	let started = Instant::now();
+	match engine.eval("while(true) {}", "spin.js") {
+		Err(ScriptError::Interrupted(budget)) => assert_eq!(budget, Duration::from_millis(100)),
+		other => panic!("expected an interruption, got {:?}", other),
+	}
+	assert!(started.elapsed() < Duration::from_secs(2));
+
+	// and it cannot be caught
+	let caught = engine.eval("try { while(true) {} } catch(e) { 'caught' }", "spin.js");
+	assert!(matches!(caught, Err(ScriptError::Interrupted(_))));
+
+	// the next entry gets a fresh budget
+	assert_eq!(engine.eval("1 + 1", "sum.js").unwrap(), "2");
+	engine.eval("function spin() { while(true) {} }", "spin.js").unwrap();
+	assert!(matches!(engine.call("spin", &[]), Err(ScriptError::Interrupted(_))));
+}
+
+#[test]
+fn time_in_callbacks_is_not_charged() {
+	let mut engine = Engine::new(limits(4 * 1024 * 1024, 100)).unwrap();
+	engine.add_callback("sleep", |args: Vec<String>| {
+		std::thread::sleep(Duration::from_millis(args[0].parse().unwrap()));
+		Ok(None)
+	}).unwrap();
+	assert_eq!(engine.eval("for (let i = 0; i < 5; i++) sleep(50); 'rested'", "sleep.js").unwrap(), "rested");
+}
+
+#[test]
+fn running_out_of_memory_is_told_from_the_allocator() {
+	let engine = Engine::new(limits(1024 * 1024, 2000)).unwrap();
+	match engine.eval("let hoard = []; while(true) hoard.push(new Array(1000).fill(1));", "hoard.js") {
+		Err(ScriptError::OutOfMemory(limit)) => assert_eq!(limit, 1024 * 1024),
+		other => panic!("expected out of memory, got {:?}", other),
+	}
+	// even if the script catches it and throws something else
+	let rethrown = engine.eval("try { new Array(1e7).fill(1) } catch(e) { throw new Error('fine') }", "hoard.js");
+	assert!(matches!(rethrown, Err(ScriptError::OutOfMemory(_))), "{:?}", rethrown);
+
+	// while a script that only says so is an ordinary exception
+	let engine = Engine::new(limits(1024 * 1024, 2000)).unwrap();
+	for source in &["throw new InternalError('out of memory')", "throw 'out of memory'", "throw new Error('InternalError: out of memory')"] {
+		match engine.eval(source, "liar.js") {
+			Err(ScriptError::Exception(exception)) => assert!(exception.message.contains("out of memory")),
+			other => panic!("{} should be an exception, got {:?}", source, other),
+		}
+	}
+	assert_eq!(engine.eval("'still here'", "liar.js").unwrap(), "still here");
+}
+
+#[test]
+fn exceptions_say_where_they_happened() {
+	let engine = Engine::new(Limits::default()).unwrap();
+	let source = "let a = 1;\n\nfunction broken() {\n\tthrow new Error('nope');\n}\nbroken();\n";
+	match engine.eval(source, "broken.js") {
+		Err(ScriptError::Exception(exception)) => {
+			assert_eq!((exception.file.as_str(), exception.line), ("broken.js", 4));
+			assert_eq!(exception.message, "Error: nope");
+			assert!(exception.stack.contains("broken"));
+		},
+		other => panic!("expected an exception, got {:?}", other),
+	}
+
+	// a syntax error points at its line
+	match engine.eval("let b = 1;\nlet = ;\n", "syntax.js") {
+		Err(ScriptError::Exception(exception)) => assert_eq!((exception.file.as_str(), exception.line), ("syntax.js", 2)),
+		other => panic!("expected an exception, got {:?}", other),
+	}
+
+	// a bare value has no stack but is still put in its file
+	match engine.eval("throw 'bare'", "bare.js") {
+		Err(ScriptError::Exception(exception)) => assert_eq!((exception.file.as_str(), exception.message.as_str()), ("bare.js", "bare")),
+		other => panic!("expected an exception, got {:?}", other),
+	}
+
+	// and a callback that fails throws into the script
+	let mut engine = Engine::new(Limits::default()).unwrap();
+	engine.add_callback("fail", |_| Err("callback said no".to_string())).unwrap();
+	assert_eq!(engine.eval("try { fail() } catch(e) { e.message }", "catch.js").unwrap(), "callback said no");
+}
+
+#[test]
+fn reloading_replaces_what_was_there() {
+	// a reload is a fresh engine for the new source, with nothing left over from the old one
+	let engine = Engine::new(Limits::default()).unwrap();
+	engine.eval("function on_message(topic, data) { return 'old ' + data }", "app.js").unwrap();
+	assert_eq!(engine.call("on_message", &["/t", "x"]).unwrap(), Some("old x".to_string()));
+	drop(engine);
+
+	let engine = Engine::new(Limits::default()).unwrap();
+	assert_eq!(engine.call("on_message", &["/t", "x"]).unwrap(), None);
+	engine.eval("function on_message(topic, data) { return 'new ' + data }", "app.js").unwrap();
+	assert_eq!(engine.call("on_message", &["/t", "x"]).unwrap(), Some("new x".to_string()));
+
+	// and a script that fails part way leaves behind what it had defined
+	let engine = Engine::new(Limits::default()).unwrap();
+	assert!(engine.eval("function on_message() { return 'half' }\nthrow new Error('late')", "app.js").is_err());
+	assert_eq!(engine.call("on_message", &[]).unwrap(), Some("half".to_string()));
+}
+
+#[test]
+fn another_thread_can_stop_an_engine() {
+	let interrupter = Interrupter::new();
+	let stopper = interrupter.clone();
+	let started = Instant::now();
+	let engine = std::thread::spawn(move || {
+		let mut engine = Engine::new(limits(4 * 1024 * 1024, 60_000)).unwrap();
+		engine.set_interrupter(interrupter);
+		let sleeper = engine.interrupter();
+		engine.add_callback("sleep", move |args: Vec<String>| {
+			match sleeper.sleep(Duration::from_millis(args[0].parse().unwrap())) {
+				true => Ok(None),
+				false => Err("stopped".to_string()),
+			}
+		}).unwrap();
+		// asleep, and then spinning if the sleep is caught
+		let stopped = engine.eval("try { sleep(60000) } catch(e) { } while(true) {}", "stuck.js");
+		let after = engine.call("anything", &[]);
+		(stopped, after)
+	});
+	std::thread::sleep(Duration::from_millis(100));
+	stopper.interrupt();
+	let (stopped, after) = engine.join().unwrap();
+	assert!(started.elapsed() < Duration::from_secs(5));
+	assert!(matches!(stopped, Err(ScriptError::Stopped)), "{:?}", stopped);
+	assert!(matches!(after, Err(ScriptError::Stopped)), "{:?}", after);
+	assert!(!stopper.sleep(Duration::from_secs(60)));
+}
@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+use crossbeam::channel::*;
+
+use scripting::{Limits, Scripting};
+use service::*;
+
+#[test]
+fn scripts_are_reloaded_however_busy_the_service_is() {
+	let dir = std::env::temp_dir().join(format!("orbital-scripting-service-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("busy.js");
+	std::fs::write(&path, "orbital_message('one')").unwrap();
+	let path = path.to_string_lossy().to_string();
+
+	// the service talks straight to us in place of a broker
+	let (send,out) = unbounded::<Message>();
+	let (local,recv) = unbounded::<Message>();
+	Scripting::with_scripts(Limits::default(), &[&path]).start("scripting".to_string(), 1, send, recv);
+	let shown = |what: &str| format!("{} @{}", what, path);
+	let deadline = Instant::now() + Duration::from_secs(10);
+	loop {
+		match out.recv_timeout(deadline.saturating_duration_since(Instant::now())).expect("script never ran") {
+			Message::Event(topic,data) if topic == "/display" && data == shown("one") => break,
+			_ => { },
+		}
+	}
+
+	// changed while traffic never stops coming
+	std::thread::sleep(Duration::from_millis(50));
+	std::fs::write(&path, "orbital_message('two')").unwrap();
+	let deadline = Instant::now() + Duration::from_secs(10);
+	loop {
+		assert!(Instant::now() < deadline, "script was not reloaded");
+		while local.len() < 1000 {
+			local.send(Message::Event("/elsewhere".to_string(), "noise".to_string())).unwrap();
+		}
+		match out.try_recv() {
+			Ok(Message::Event(topic,data)) if topic == "/display" && data == shown("two") => break,
+			_ => { },
+		}
+	}
+	drop(local);
+	let _ = std::fs::remove_dir_all(&dir);
+}
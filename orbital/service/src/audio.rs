@@ -0,0 +1,103 @@
+
+///
+/// Audio: sound as it travels between services
+///
+/// a buffer is a run of f32 frames between -1 and 1, with one sample per channel in each frame one after the other -
+/// so a stereo buffer goes left, right, left, right. buffers go by as Message::Audio(topic, stream, samples), where the
+/// stream says whose sound it is so that the mixer can keep several apart.
+///
+
+use std::sync::Arc;
+
+use crate::Message;
+
+/// Sound captured from the input, ie the microphone or a wav file standing in for one
+pub const AUDIO_IN: &str = "/audio/in";
+
+/// Sound to be played, mixed with everything else being played
+pub const AUDIO_OUT: &str = "/audio/out";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Samples {
+    pub rate: u32,
+    pub channels: u16,
+    pub frames: Vec<f32>,
+}
+
+impl Samples {
+
+    pub fn new(rate: u32, channels: u16, frames: Vec<f32>) -> Samples {
+        Samples { rate: rate, channels: channels.max(1), frames: frames }
+    }
+
+    pub fn silence(rate: u32, channels: u16, count: usize) -> Samples {
+        Samples::new(rate, channels, vec![0.0; count * channels.max(1) as usize])
+    }
+
+    /// How many frames there are - each is one sample for every channel
+    pub fn len(&self) -> usize {
+        self.frames.len() / self.channels as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many seconds it lasts
+    pub fn duration(&self) -> f64 {
+        self.len() as f64 / self.rate as f64
+    }
+
+    /// The same sound at another rate and number of channels - going down to mono averages the channels, and going
+    /// up from mono copies it into all of them. the rate is changed by drawing a straight line between samples.
+    pub fn convert(&self, rate: u32, channels: u16) -> Samples {
+        let channels = channels.max(1);
+        if rate == self.rate && channels == self.channels {
+            return self.clone();
+        }
+        let from = self.channels as usize;
+        let to = channels as usize;
+
+        // the channels first, at the old rate
+        let mut mixed = Vec::with_capacity(self.len() * to);
+        for frame in self.frames.chunks(from) {
+            for channel in 0..to {
+                let sample = if from == to {
+                    frame[channel]
+                } else if to == 1 {
+                    frame.iter().sum::<f32>() / from as f32
+                } else if from == 1 {
+                    frame[0]
+                } else {
+                    // between two layouts that are neither mono - keep the channels they share and leave the rest quiet
+                    frame.get(channel).cloned().unwrap_or(0.0)
+                };
+                mixed.push(sample);
+            }
+        }
+        if rate == self.rate || self.is_empty() {
+            return Samples::new(rate, channels, mixed);
+        }
+
+        let count = (self.len() as u64 * rate as u64 / self.rate as u64) as usize;
+        let step = self.rate as f64 / rate as f64;
+        let last = self.len() - 1;
+        let mut frames = Vec::with_capacity(count * to);
+        for index in 0..count {
+            let at = index as f64 * step;
+            let before = (at as usize).min(last);
+            let after = (before + 1).min(last);
+            let fraction = (at - before as f64) as f32;
+            for channel in 0..to {
+                let (a, b) = (mixed[before * to + channel], mixed[after * to + channel]);
+                frames.push(a + (b - a) * fraction);
+            }
+        }
+        Samples::new(rate, channels, frames)
+    }
+
+    /// Wrap up for sending on a topic, from a stream
+    pub fn message(self, topic: &str, stream: &str) -> Message {
+        Message::Audio(topic.to_string(), stream.to_string(), Arc::new(self))
+    }
+}
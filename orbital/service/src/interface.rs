@@ -0,0 +1,520 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// Typed interfaces between services
+//
+// a service can offer functions with typed parameters and results that any other service - wasm or native - can call
+// through the broker. the types are deliberately few:
+//
+//     bool u8 s32 s64 f32 f64 string list<T> and named records of those
+//
+// an app declares what it offers in its manifest, ie:
+//
+//     record.rect = x: s32, y: s32, w: s32, h: s32
+//     export.detect_faces = (image: list<u8>, width: s32, height: s32) -> list<rect>
+//
+// and an app that wants to call something elsewhere declares that too, named for the app it lives in:
+//
+//     import.faces.detect_faces = (image: list<u8>, width: s32, height: s32) -> list<rect>
+//
+// on the wire a call is an Event on /call/<app>/<function> with a json payload of {"id":..,"reply":"..","args":[..]}
+// and the answer is an Event on the reply topic of {"id":..,"ok":..} or {"id":..,"error":".."}. records travel as json
+// objects, lists as arrays.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use std::fmt;
+
+use crate::Manifest;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Bool,
+    U8,
+    S32,
+    S64,
+    F32,
+    F64,
+    String,
+    List(Box<Type>),
+    Record(String, Vec<(String,Type)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    S32(i32),
+    S64(i64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    List(Vec<Value>),
+    Record(Vec<(String,Value)>),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::U8 => write!(f, "u8"),
+            Type::S32 => write!(f, "s32"),
+            Type::S64 => write!(f, "s64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::String => write!(f, "string"),
+            Type::List(of) => write!(f, "list<{}>", of),
+            Type::Record(name,_) => write!(f, "{}", name),
+        }
+    }
+}
+
+///
+/// Function: one callable thing an interface offers
+///
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<(String,Type)>,
+    pub result: Option<Type>,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|(name,ty)| format!("{}: {}", name, ty)).collect();
+        write!(f, "{}({})", self.name, params.join(", "))?;
+        if let Some(result) = &self.result {
+            write!(f, " -> {}", result)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Interface: what an app offers to others and what it wants from others, as read from its manifest
+///
+
+#[derive(Clone, Debug, Default)]
+pub struct Interface {
+    pub exports: Vec<Function>,
+    // keyed by the app the function lives in
+    pub imports: Vec<(String,Function)>,
+}
+
+impl Interface {
+
+    pub fn from_manifest(manifest: &Manifest) -> Result<Interface, String> {
+        let records = records(manifest)?;
+        let mut interface = Interface::default();
+        for (name,signature) in manifest.prefixed("export") {
+            interface.exports.push(Function::parse(&name, &signature, &records)?);
+        }
+        for (name,signature) in manifest.prefixed("import") {
+            let (app,name) = name.split_once('.').ok_or_else(|| format!("import.{} should be import.<app>.<function>", name))?;
+            interface.imports.push((app.to_string(), Function::parse(name, &signature, &records)?));
+        }
+        Ok(interface)
+    }
+
+    pub fn export(&self, name: &str) -> Option<&Function> {
+        self.exports.iter().find(|function| function.name == name)
+    }
+
+    pub fn import(&self, app: &str, name: &str) -> Option<&Function> {
+        self.imports.iter().find(|(a,function)| a == app && function.name == name).map(|(_,function)| function)
+    }
+}
+
+// records may refer to records declared before them
+fn records(manifest: &Manifest) -> Result<Vec<(String,Type)>, String> {
+    let mut records: Vec<(String,Type)> = Vec::new();
+    for (name,fields) in manifest.prefixed("record") {
+        let fields = parse_fields(&fields, &records).map_err(|err| format!("record.{}: {}", name, err))?;
+        records.push((name.clone(), Type::Record(name, fields)));
+    }
+    Ok(records)
+}
+
+impl Function {
+
+    /// Parse a signature like "(image: list<u8>) -> list<rect>" using records that have already been declared
+    pub fn parse(name: &str, signature: &str, records: &[(String,Type)]) -> Result<Function, String> {
+        let error = |err: String| format!("{}: {}", name, err);
+        let signature = signature.trim();
+        let close = signature.find(')').filter(|_| signature.starts_with('(')).ok_or_else(|| error("signature should look like (a: s32) -> s32".to_string()))?;
+        let params = parse_fields(&signature[1..close], records).map_err(error)?;
+        let rest = signature[close+1..].trim();
+        let result = if rest.is_empty() {
+            None
+        } else {
+            let rest = rest.strip_prefix("->").ok_or_else(|| error(format!("expected -> before the result but found '{}'", rest)))?;
+            Some(parse_type(rest, records).map_err(error)?)
+        };
+        Ok(Function { name: name.to_string(), params: params, result: result })
+    }
+
+    pub fn topic(&self, app: &str) -> String {
+        call_topic(app, &self.name)
+    }
+}
+
+pub fn call_topic(app: &str, function: &str) -> String {
+    format!("/call/{}/{}", app, function)
+}
+
+fn parse_fields(text: &str, records: &[(String,Type)]) -> Result<Vec<(String,Type)>, String> {
+    let mut fields = Vec::new();
+    for field in split_top(text) {
+        let (name,ty) = field.split_once(':').ok_or_else(|| format!("'{}' should be name: type", field))?;
+        fields.push((name.trim().to_string(), parse_type(ty, records)?));
+    }
+    Ok(fields)
+}
+
+// split on commas that are not inside list<...>
+fn split_top(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut part = String::new();
+    for c in text.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(part.trim().to_string());
+                part.clear();
+                continue;
+            },
+            _ => { },
+        }
+        part.push(c);
+    }
+    if !part.trim().is_empty() {
+        parts.push(part.trim().to_string());
+    }
+    parts
+}
+
+pub fn parse_type(text: &str, records: &[(String,Type)]) -> Result<Type, String> {
+    let text = text.trim();
+    Ok(match text {
+        "bool" => Type::Bool,
+        "u8" => Type::U8,
+        "s32" => Type::S32,
+        "s64" => Type::S64,
+        "f32" => Type::F32,
+        "f64" => Type::F64,
+        "string" => Type::String,
+        _ if text.starts_with("list<") && text.ends_with('>') => Type::List(Box::new(parse_type(&text[5..text.len()-1], records)?)),
+        _ => match records.iter().find(|(name,_)| name == text) {
+            Some((_,record)) => record.clone(),
+            None => return Err(format!("unknown type '{}'", text)),
+        },
+    })
+}
+
+//////////////////////////////////////////////////////////////////////////
+// values as json text - how they travel on the broker
+//////////////////////////////////////////////////////////////////////////
+
+impl Value {
+
+    pub fn to_json(&self) -> String {
+        match self {
+            Value::Bool(v) => v.to_string(),
+            Value::U8(v) => v.to_string(),
+            Value::S32(v) => v.to_string(),
+            Value::S64(v) => v.to_string(),
+            Value::F32(v) => v.to_string(),
+            Value::F64(v) => v.to_string(),
+            Value::String(v) => quote(v),
+            Value::List(values) => format!("[{}]", values.iter().map(|v| v.to_json()).collect::<Vec<String>>().join(",")),
+            Value::Record(fields) => format!("{{{}}}", fields.iter().map(|(name,v)| format!("{}:{}", quote(name), v.to_json())).collect::<Vec<String>>().join(",")),
+        }
+    }
+
+    /// Read a value of a known type out of json
+    pub fn from_json(json: &Json, ty: &Type) -> Result<Value, String> {
+        let mismatch = || format!("expected {} but got {}", ty, json.to_text());
+        Ok(match (ty, json) {
+            (Type::Bool, Json::Bool(v)) => Value::Bool(*v),
+            (Type::U8, Json::Number(n)) => Value::U8(n.parse().map_err(|_| mismatch())?),
+            (Type::S32, Json::Number(n)) => Value::S32(n.parse().map_err(|_| mismatch())?),
+            (Type::S64, Json::Number(n)) => Value::S64(n.parse().map_err(|_| mismatch())?),
+            (Type::F32, Json::Number(n)) => Value::F32(n.parse().map_err(|_| mismatch())?),
+            (Type::F64, Json::Number(n)) => Value::F64(n.parse().map_err(|_| mismatch())?),
+            (Type::String, Json::String(v)) => Value::String(v.clone()),
+            (Type::List(of), Json::Array(items)) => Value::List(items.iter().map(|item| Value::from_json(item, of)).collect::<Result<Vec<Value>,String>>()?),
+            (Type::Record(_,fields), Json::Object(members)) => {
+                let mut values = Vec::new();
+                for (name,ty) in fields {
+                    let member = members.iter().find(|(key,_)| key == name).map(|(_,v)| v).ok_or_else(|| format!("{} is missing field {}", ty, name))?;
+                    values.push((name.clone(), Value::from_json(member, ty)?));
+                }
+                Value::Record(values)
+            },
+            _ => return Err(mismatch()),
+        })
+    }
+}
+
+/// Text as a json string, escaped
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+///
+/// Json: just enough json to carry calls - numbers are kept as their text so no precision is lost before the type is known
+///
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String,Json)>),
+}
+
+impl Json {
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut at = 0;
+        let json = parse_json(&chars, &mut at)?;
+        skip_space(&chars, &mut at);
+        if at != chars.len() {
+            return Err(format!("unexpected text at {}", at));
+        }
+        Ok(json)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k,_)| k == key).map(|(_,v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(v) => v.to_string(),
+            Json::Number(n) => n.clone(),
+            Json::String(s) => quote(s),
+            Json::Array(items) => format!("[{}]", items.iter().map(|v| v.to_text()).collect::<Vec<String>>().join(",")),
+            Json::Object(members) => format!("{{{}}}", members.iter().map(|(k,v)| format!("{}:{}", quote(k), v.to_text())).collect::<Vec<String>>().join(",")),
+        }
+    }
+}
+
+fn skip_space(chars: &[char], at: &mut usize) {
+    while *at < chars.len() && chars[*at].is_whitespace() {
+        *at += 1;
+    }
+}
+
+fn expect(chars: &[char], at: &mut usize, word: &str) -> Result<(), String> {
+    for c in word.chars() {
+        if chars.get(*at) != Some(&c) {
+            return Err(format!("expected '{}' at {}", word, at));
+        }
+        *at += 1;
+    }
+    Ok(())
+}
+
+fn parse_json(chars: &[char], at: &mut usize) -> Result<Json, String> {
+    skip_space(chars, at);
+    match chars.get(*at) {
+        None => Err("unexpected end of json".to_string()),
+        Some('n') => expect(chars, at, "null").map(|_| Json::Null),
+        Some('t') => expect(chars, at, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect(chars, at, "false").map(|_| Json::Bool(false)),
+        Some('"') => parse_string(chars, at).map(Json::String),
+        Some('[') => {
+            *at += 1;
+            let mut items = Vec::new();
+            skip_space(chars, at);
+            if chars.get(*at) == Some(&']') {
+                *at += 1;
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_json(chars, at)?);
+                skip_space(chars, at);
+                match chars.get(*at) {
+                    Some(',') => *at += 1,
+                    Some(']') => { *at += 1; return Ok(Json::Array(items)); },
+                    _ => return Err(format!("expected , or ] at {}", at)),
+                }
+            }
+        },
+        Some('{') => {
+            *at += 1;
+            let mut members = Vec::new();
+            skip_space(chars, at);
+            if chars.get(*at) == Some(&'}') {
+                *at += 1;
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_space(chars, at);
+                let key = parse_string(chars, at)?;
+                skip_space(chars, at);
+                expect(chars, at, ":")?;
+                members.push((key, parse_json(chars, at)?));
+                skip_space(chars, at);
+                match chars.get(*at) {
+                    Some(',') => *at += 1,
+                    Some('}') => { *at += 1; return Ok(Json::Object(members)); },
+                    _ => return Err(format!("expected , or }} at {}", at)),
+                }
+            }
+        },
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let start = *at;
+            while *at < chars.len() && (chars[*at].is_ascii_digit() || "+-.eE".contains(chars[*at])) {
+                *at += 1;
+            }
+            Ok(Json::Number(chars[start..*at].iter().collect()))
+        },
+        Some(c) => Err(format!("unexpected '{}' at {}", c, at)),
+    }
+}
+
+fn parse_string(chars: &[char], at: &mut usize) -> Result<String, String> {
+    expect(chars, at, "\"")?;
+    let mut text = String::new();
+    loop {
+        match chars.get(*at) {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => { *at += 1; return Ok(text); },
+            Some('\\') => {
+                *at += 1;
+                match chars.get(*at) {
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('u') => {
+                        let hex: String = chars.get(*at+1..*at+5).ok_or("short \\u escape")?.iter().collect();
+                        let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("bad \\u escape {}", hex))?;
+                        text.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        *at += 4;
+                    },
+                    Some(c) => text.push(*c),
+                    None => return Err("unterminated string".to_string()),
+                }
+                *at += 1;
+            },
+            Some(c) => { text.push(*c); *at += 1; },
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////
+// calls and replies
+//////////////////////////////////////////////////////////////////////////
+
+///
+/// Call: a request for a function somewhere else, answered on the reply topic
+///
+
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub id: u64,
+    pub reply: String,
+    pub args: Vec<Value>,
+}
+
+impl Call {
+
+    pub fn to_json(&self) -> String {
+        let args: Vec<String> = self.args.iter().map(|arg| arg.to_json()).collect();
+        format!("{{\"id\":{},\"reply\":{},\"args\":[{}]}}", self.id, quote(&self.reply), args.join(","))
+    }
+
+    /// Read a call to a known function, checking the arguments against its parameters
+    /// - if the envelope can be read but the arguments are bad the id and reply topic still come back so the caller can be told
+    pub fn parse(text: &str, function: &Function) -> Result<Call, (Option<(u64,String)>, String)> {
+        let json = Json::parse(text).map_err(|err| (None, err))?;
+        let id = match json.get("id") { Some(Json::Number(n)) => n.parse::<u64>().ok(), _ => None };
+        let reply = match json.get("reply") { Some(Json::String(s)) => Some(s.clone()), _ => None };
+        let (id,reply) = match (id,reply) {
+            (Some(id),Some(reply)) => (id,reply),
+            _ => return Err((None, "call needs an id and a reply topic".to_string())),
+        };
+        let bad = |err: String| (Some((id,reply.clone())), format!("{}: {}", function.name, err));
+        let args = match json.get("args") {
+            Some(Json::Array(args)) => args,
+            _ => return Err(bad("call needs an args array".to_string())),
+        };
+        if args.len() != function.params.len() {
+            return Err(bad(format!("takes {} argument(s) but was given {}", function.params.len(), args.len())));
+        }
+        let mut values = Vec::new();
+        for (arg,(name,ty)) in args.iter().zip(function.params.iter()) {
+            values.push(Value::from_json(arg, ty).map_err(|err| bad(format!("{}: {}", name, err)))?);
+        }
+        Ok(Call { id: id, reply: reply, args: values })
+    }
+}
+
+///
+/// Reply: the answer to a call
+///
+
+#[derive(Clone, Debug)]
+pub struct Reply {
+    pub id: u64,
+    pub result: Result<Option<Value>, String>,
+}
+
+impl Reply {
+
+    pub fn to_json(&self) -> String {
+        match &self.result {
+            Ok(Some(value)) => format!("{{\"id\":{},\"ok\":{}}}", self.id, value.to_json()),
+            Ok(None) => format!("{{\"id\":{},\"ok\":null}}", self.id),
+            Err(err) => format!("{{\"id\":{},\"error\":{}}}", self.id, quote(err)),
+        }
+    }
+
+    /// Read a reply to a known function, checking the result against its result type
+    pub fn parse(text: &str, function: &Function) -> Result<Reply, String> {
+        let json = Json::parse(text)?;
+        let id = match json.get("id") { Some(Json::Number(n)) => n.parse::<u64>().map_err(|_| "bad reply id".to_string())?, _ => return Err("reply needs an id".to_string()) };
+        if let Some(Json::String(err)) = json.get("error") {
+            return Ok(Reply { id: id, result: Err(err.clone()) });
+        }
+        let result = match (json.get("ok"), &function.result) {
+            (Some(Json::Null), None) | (None, None) => None,
+            (Some(ok), Some(ty)) => Some(Value::from_json(ok, ty).map_err(|err| format!("{} result: {}", function.name, err))?),
+            _ => return Err(format!("{} reply does not match its result type", function.name)),
+        };
+        Ok(Reply { id: id, result: Ok(result) })
+    }
+
+    /// Just the id of a reply, for matching it to the call it answers before its type is known
+    pub fn id(text: &str) -> Option<u64> {
+        match Json::parse(text).ok()?.get("id") {
+            Some(Json::Number(n)) => n.parse::<u64>().ok(),
+            _ => None,
+        }
+    }
+}
@@ -3,8 +3,43 @@ use crossbeam::channel::*;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+mod manifest;
+pub use manifest::Manifest;
+
+pub mod audio;
+pub mod interface;
+pub mod vision;
+
 pub type SID = u64;
 
+/// The broker says "<topic> <count>" here whenever the number of subscribers to a topic changes, and tells anyone who
+/// starts listening the counts as they stand - so a service can do expensive work only while someone wants it
+pub const SUBSCRIBERS: &str = "/subscribers";
+
+/// Topics that services speak on - what is said on one of these, or anything under it, is taken to come from the
+/// service, so nothing an app sends may go out on them
+pub const RESERVED: &[&str] = &[
+    "/scene/changes", SUBSCRIBERS, "/log", "/diagnostics", "/camera", "/faces", "/tracks", "/codes",
+    "/audio/status", "/recorder/status", "/viewsoft/status",
+];
+
+/// Whether a topic is one that only services speak on
+pub fn reserved(topic: &str) -> bool {
+    RESERVED.iter().any(|reserved| matches!(topic.strip_prefix(reserved), Some(rest) if rest.is_empty() || rest.starts_with('/')))
+}
+
+/// Split "request @owner" into the request and who sent it, if it says. services that act for apps are sent requests
+/// tagged this way by whatever runs the app, and trust the tag, so an app's own words must never reach them untagged.
+/// an @ inside json or before the last word is part of the request, not a tag
+pub fn owner(data: &str) -> (&str, Option<&str>) {
+    match data.rfind(" @") {
+        Some(at) if !data[at + 2..].trim().contains(|c: char| c.is_whitespace() || c == '"' || c == '}' || c == ']') => {
+            (data[..at].trim(), Some(data[at + 2..].trim()))
+        },
+        _ => (data.trim(), None),
+    }
+}
+
 ///
 /// Message: all the messages we can send between services
 /// MOVE TODO
@@ -12,7 +47,8 @@ pub type SID = u64;
 
 #[derive(Clone)]
 pub enum Message {
-    Share(Arc<Mutex<Box<[u32;921600]>>>),
+    // Share a frame of video with any traffic matching a string, without copying it - the camera shares on /frames
+    Share(String,Arc<Mutex<Box<[u32;921600]>>>),
 
     // register a new channel that can receive traffic
     Channel(SID,String,Sender<Message>),
@@ -27,6 +63,9 @@ pub enum Message {
     // Send an event to any traffic matching a string
     Event(String,String),
 
+    // Send sound to any traffic matching a string, from a named stream - see audio.rs
+    Audio(String,String,Arc<audio::Samples>),
+
     // Dynamically build a service at runtime in the broker (not used right now)
     // Add(ServiceBuilder),
 
@@ -0,0 +1,55 @@
+
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+///
+/// Manifest: the settings an app ships with, as plain "key = value" lines
+///
+/// blank lines and lines starting with # are ignored. a key may appear more than once; get() returns the last one.
+/// keys are free form - by convention a dotted prefix groups related settings, ie "env.HOME = /"
+///
+
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    entries: Vec<(String,String)>,
+}
+
+impl Manifest {
+
+    pub fn load(path: &str) -> io::Result<Manifest> {
+        Manifest::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Manifest> {
+        let mut entries = Vec::new();
+        for (number,line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key,value)) => entries.push((key.trim().to_string(),value.trim().to_string())),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("manifest line {} is not key = value: {}", number + 1, line))),
+            }
+        }
+        Ok(Manifest { entries: entries })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().rev().find(|(k,_)| k == key).map(|(_,v)| v.as_str())
+    }
+
+    /// A value parsed as some type, or the default if it is missing or does not parse
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> T {
+        self.get(key).and_then(|v| v.parse::<T>().ok()).unwrap_or(default)
+    }
+
+    /// Every entry under a dotted prefix with the prefix taken off, ie prefixed("env") on "env.HOME = /" gives ("HOME","/")
+    pub fn prefixed(&self, prefix: &str) -> Vec<(String,String)> {
+        let prefix = format!("{}.",prefix);
+        self.entries.iter()
+            .filter_map(|(k,v)| k.strip_prefix(&prefix).map(|k| (k.to_string(),v.clone())))
+            .collect()
+    }
+}
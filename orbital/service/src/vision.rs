@@ -0,0 +1,231 @@
+
+///
+/// Vision: what the vision services publish about frames of video, as json on the broker
+///
+/// the camera shares its frames on /frames, 1280x720 with red in the low byte of each pixel, then green, blue and alpha.
+///
+/// faces go out on /faces, one message per frame looked at - an empty list means nothing was found, so anyone drawing
+/// boxes can clear the old ones. positions are in pixels of the frame, which is width by height.
+///
+/// the tracker follows those faces from frame to frame and publishes them on /tracks, each with an id that stays the
+/// same for as long as it is in view. when a track is first sure of something it goes out on /tracks/enter, and when
+/// it loses it for good it goes out on /tracks/leave with the last place it was seen.
+///
+/// qr codes and barcodes go out on /codes, with what they say and the corners they were found at, going clockwise
+/// from the top left of the code as it was printed.
+///
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::interface::{quote, Json};
+
+pub const FRAMES: &str = "/frames";
+pub const FACES: &str = "/faces";
+pub const TRACKS: &str = "/tracks";
+pub const ENTER: &str = "/tracks/enter";
+pub const LEAVE: &str = "/tracks/leave";
+pub const CODES: &str = "/codes";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Face {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Faces {
+    // when the frame arrived, in milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub width: u32,
+    pub height: u32,
+    pub faces: Vec<Face>,
+}
+
+impl Faces {
+
+    pub fn to_json(&self) -> String {
+        let faces: Vec<String> = self.faces.iter().map(|face| {
+            format!("{{\"x\":{},\"y\":{},\"w\":{},\"h\":{},\"score\":{}}}", face.x, face.y, face.w, face.h, face.score)
+        }).collect();
+        format!("{{\"timestamp\":{},\"width\":{},\"height\":{},\"faces\":[{}]}}", self.timestamp, self.width, self.height, faces.join(","))
+    }
+
+    pub fn parse(text: &str) -> Result<Faces, String> {
+        let json = Json::parse(text)?;
+        let mut faces = Vec::new();
+        match json.get("faces") {
+            Some(Json::Array(items)) => {
+                for item in items {
+                    faces.push(Face {
+                        x: number(item, "x")?,
+                        y: number(item, "y")?,
+                        w: number(item, "w")?,
+                        h: number(item, "h")?,
+                        score: number(item, "score")?,
+                    });
+                }
+            },
+            _ => return Err("faces needs a faces array".to_string()),
+        }
+        Ok(Faces {
+            timestamp: number(&json, "timestamp")?,
+            width: number(&json, "width")?,
+            height: number(&json, "height")?,
+            faces: faces,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub id: u64,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tracks {
+    // the timestamp of the frame the tracks were last updated from
+    pub timestamp: u64,
+    pub width: u32,
+    pub height: u32,
+    pub tracks: Vec<Track>,
+}
+
+impl Tracks {
+
+    pub fn to_json(&self) -> String {
+        let tracks: Vec<String> = self.tracks.iter().map(|track| {
+            format!("{{\"id\":{},\"x\":{},\"y\":{},\"w\":{},\"h\":{},\"score\":{}}}", track.id, track.x, track.y, track.w, track.h, track.score)
+        }).collect();
+        format!("{{\"timestamp\":{},\"width\":{},\"height\":{},\"tracks\":[{}]}}", self.timestamp, self.width, self.height, tracks.join(","))
+    }
+
+    pub fn parse(text: &str) -> Result<Tracks, String> {
+        let json = Json::parse(text)?;
+        let mut tracks = Vec::new();
+        match json.get("tracks") {
+            Some(Json::Array(items)) => {
+                for item in items {
+                    tracks.push(Track {
+                        id: number(item, "id")?,
+                        x: number(item, "x")?,
+                        y: number(item, "y")?,
+                        w: number(item, "w")?,
+                        h: number(item, "h")?,
+                        score: number(item, "score")?,
+                    });
+                }
+            },
+            _ => return Err("tracks needs a tracks array".to_string()),
+        }
+        Ok(Tracks {
+            timestamp: number(&json, "timestamp")?,
+            width: number(&json, "width")?,
+            height: number(&json, "height")?,
+            tracks: tracks,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Code {
+    // qr, ean13, ean8 or upca
+    pub kind: String,
+    pub payload: String,
+    pub corners: Vec<(i32,i32)>,
+}
+
+impl Code {
+    /// The payload if it is a web address, which the desktop offers to go to
+    pub fn url(&self) -> Option<&str> {
+        if self.payload.starts_with("http://") || self.payload.starts_with("https://") {
+            Some(&self.payload)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Codes {
+    pub timestamp: u64,
+    pub width: u32,
+    pub height: u32,
+    pub codes: Vec<Code>,
+}
+
+impl Codes {
+
+    pub fn to_json(&self) -> String {
+        let codes: Vec<String> = self.codes.iter().map(|code| {
+            let corners: Vec<String> = code.corners.iter().map(|(x,y)| format!("[{},{}]", x, y)).collect();
+            format!("{{\"kind\":{},\"payload\":{},\"corners\":[{}]}}", quote(&code.kind), quote(&code.payload), corners.join(","))
+        }).collect();
+        format!("{{\"timestamp\":{},\"width\":{},\"height\":{},\"codes\":[{}]}}", self.timestamp, self.width, self.height, codes.join(","))
+    }
+
+    pub fn parse(text: &str) -> Result<Codes, String> {
+        let json = Json::parse(text)?;
+        let mut codes = Vec::new();
+        match json.get("codes") {
+            Some(Json::Array(items)) => {
+                for item in items {
+                    let mut corners = Vec::new();
+                    match item.get("corners") {
+                        Some(Json::Array(points)) => {
+                            for point in points {
+                                match point {
+                                    Json::Array(xy) if xy.len() == 2 => {
+                                        match (&xy[0], &xy[1]) {
+                                            (Json::Number(x), Json::Number(y)) => corners.push((
+                                                x.parse::<i32>().map_err(|_| format!("bad corner {}", x))?,
+                                                y.parse::<i32>().map_err(|_| format!("bad corner {}", y))?,
+                                            )),
+                                            _ => return Err("corners are pairs of numbers".to_string()),
+                                        }
+                                    },
+                                    _ => return Err("corners are pairs of numbers".to_string()),
+                                }
+                            }
+                        },
+                        _ => return Err("a code needs corners".to_string()),
+                    }
+                    codes.push(Code { kind: string(item, "kind")?, payload: string(item, "payload")?, corners: corners });
+                }
+            },
+            _ => return Err("codes needs a codes array".to_string()),
+        }
+        Ok(Codes {
+            timestamp: number(&json, "timestamp")?,
+            width: number(&json, "width")?,
+            height: number(&json, "height")?,
+            codes: codes,
+        })
+    }
+}
+
+fn string(json: &Json, key: &str) -> Result<String, String> {
+    match json.get(key) {
+        Some(Json::String(text)) => Ok(text.clone()),
+        _ => Err(format!("missing string {}", key)),
+    }
+}
+
+pub(crate) fn number<T: std::str::FromStr>(json: &Json, key: &str) -> Result<T, String> {
+    match json.get(key) {
+        Some(Json::Number(n)) => n.parse::<T>().map_err(|_| format!("{} is not the right kind of number: {}", key, n)),
+        _ => Err(format!("missing number {}", key)),
+    }
+}
+
+/// Milliseconds since the unix epoch, for stamping frames as they arrive
+pub fn timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis() as u64).unwrap_or(0)
+}
@@ -0,0 +1,111 @@
+use service::interface::*;
+
+fn records() -> Vec<(String,Type)> {
+    let rect = Type::Record("rect".to_string(), vec![("x".to_string(), Type::S32), ("label".to_string(), Type::String)]);
+    vec![("rect".to_string(), rect)]
+}
+
+fn function(signature: &str) -> Function {
+    Function::parse("f", signature, &records()).unwrap()
+}
+
+#[test]
+fn every_type_reads_back_as_it_was_written() {
+    let records = records();
+    for ty in &["bool", "u8", "s32", "s64", "f32", "f64", "string", "list<u8>", "list<list<string>>", "rect", "list<rect>"] {
+        assert_eq!(&parse_type(ty, &records).unwrap().to_string(), ty);
+    }
+    assert_eq!(parse_type("list<thing>", &records), Err("unknown type 'thing'".to_string()));
+
+    let rect = |x, label: &str| Value::Record(vec![("x".to_string(), Value::S32(x)), ("label".to_string(), Value::String(label.to_string()))]);
+    let values = vec![
+        ("bool", Value::Bool(false)),
+        ("u8", Value::U8(255)),
+        ("s32", Value::S32(i32::MIN)),
+        ("s64", Value::S64(i64::MAX)),
+        ("f32", Value::F32(-1.25)),
+        ("f64", Value::F64(1e300)),
+        ("string", Value::String("plain".to_string())),
+        ("list<u8>", Value::List(vec![])),
+        ("list<list<string>>", Value::List(vec![Value::List(vec![Value::String("a".to_string())]), Value::List(vec![])])),
+        ("list<rect>", Value::List(vec![rect(1, "one"), rect(-2, "two")])),
+    ];
+    for (ty,value) in values {
+        let ty = parse_type(ty, &records).unwrap();
+        let json = Json::parse(&value.to_json()).unwrap();
+        assert_eq!(Value::from_json(&json, &ty), Ok(value));
+    }
+}
+
+#[test]
+fn values_of_the_wrong_type_are_refused() {
+    let records = records();
+    let read = |text: &str, ty: &str| Value::from_json(&Json::parse(text).unwrap(), &parse_type(ty, &records).unwrap());
+    assert_eq!(read("256", "u8"), Err("expected u8 but got 256".to_string()));
+    assert!(read("1.5", "s32").is_err());
+    assert!(read("\"1\"", "s32").is_err());
+    assert!(read("[1,\"2\"]", "list<s32>").is_err());
+    assert_eq!(read("{\"x\":1}", "rect"), Err("string is missing field label".to_string()));
+}
+
+#[test]
+fn malformed_or_truncated_json_is_an_error() {
+    for text in &["", "{\"a\":1", "[1,2", "\"abc", "tru", "[1,]", "{\"a\" 1}", "{a:1}", "1 2", "[1]]", "\"\\u12\"", "\"\\uzzzz\""] {
+        assert!(Json::parse(text).is_err(), "{} should not parse", text);
+    }
+    assert_eq!(Json::parse("\"\\u12"), Err("short \\u escape".to_string()));
+    assert_eq!(Json::parse(" [ 1 , {\"a\" : null} ] "), Ok(Json::Array(vec![Json::Number("1".to_string()), Json::Object(vec![("a".to_string(), Json::Null)])])));
+}
+
+#[test]
+fn strings_survive_escaping() {
+    let text = "quote \" slash \\ newline \n return \r tab \t bell \u{7} snowman \u{2603}";
+    let quoted = quote(text);
+    assert!(!quoted.contains('\n') && !quoted.contains('\u{7}'));
+    assert!(quoted.contains("\\u0007"));
+    assert_eq!(Json::parse(&quoted), Ok(Json::String(text.to_string())));
+    // escapes written by someone else
+    assert_eq!(Json::parse("\"\\/\\u0041\\u2603\""), Ok(Json::String("/A\u{2603}".to_string())));
+}
+
+#[test]
+fn calls_and_replies_round_trip() {
+    let f = function("(name: string, at: rect) -> list<u8>");
+    let call = Call { id: 7, reply: "/reply/\"odd\"".to_string(), args: vec![
+        Value::String("a\"b".to_string()),
+        Value::Record(vec![("x".to_string(), Value::S32(3)), ("label".to_string(), Value::String("".to_string()))]),
+    ] };
+    let back = Call::parse(&call.to_json(), &f).unwrap();
+    assert_eq!((back.id, back.reply, back.args), (call.id, call.reply, call.args));
+
+    let reply = Reply { id: 7, result: Ok(Some(Value::List(vec![Value::U8(1)]))) };
+    let back = Reply::parse(&reply.to_json(), &f).unwrap();
+    assert_eq!((back.id, back.result), (reply.id, reply.result));
+    let reply = Reply { id: 8, result: Err("no \"luck\"".to_string()) };
+    assert_eq!(Reply::parse(&reply.to_json(), &f).unwrap().result, reply.result);
+    assert_eq!(Reply::id("{\"id\":8,\"ok\":[]}"), Some(8));
+    assert_eq!(Reply::id("{\"id\":-8}"), None);
+}
+
+#[test]
+fn bad_calls_say_what_is_wrong_and_who_to_tell() {
+    let f = function("(n: s32)");
+    assert!(matches!(Call::parse("{\"id\":1", &f), Err((None, _))));
+    assert_eq!(Call::parse("{\"args\":[1]}", &f).unwrap_err(), (None, "call needs an id and a reply topic".to_string()));
+    let who = Some((1, "/r".to_string()));
+    assert_eq!(Call::parse("{\"id\":1,\"reply\":\"/r\"}", &f).unwrap_err(), (who.clone(), "f: call needs an args array".to_string()));
+    assert_eq!(Call::parse("{\"id\":1,\"reply\":\"/r\",\"args\":[1,2]}", &f).unwrap_err(), (who.clone(), "f: takes 1 argument(s) but was given 2".to_string()));
+    assert_eq!(Call::parse("{\"id\":1,\"reply\":\"/r\",\"args\":[true]}", &f).unwrap_err(), (who, "f: n: expected s32 but got true".to_string()));
+    assert!(Reply::parse("{\"id\":1,\"ok\":3}", &f).is_err());
+    assert!(Reply::parse("{\"ok\":null}", &f).is_err());
+}
+
+#[test]
+fn signatures_must_be_well_formed() {
+    let records = records();
+    assert!(Function::parse("f", "a: s32", &records).is_err());
+    assert!(Function::parse("f", "(a s32)", &records).is_err());
+    assert!(Function::parse("f", "(a: s32) s32", &records).is_err());
+    let f = function("(a: list<rect>, b: f64) -> bool");
+    assert_eq!(f.to_string().replace(' ', ""), "f(a:list<rect>,b:f64)->bool");
+}
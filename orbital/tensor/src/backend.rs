@@ -0,0 +1,66 @@
+
+///
+/// Backend: something that can run a model file over tensors
+///
+/// tensors go in and out as plain f32 arrays with a shape, so a model does not care what engine is underneath it.
+/// tract is the default - it is pure rust and runs on the cpu, so there is no gpu or native library to go looking for.
+///
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Array {
+	pub shape: Vec<usize>,
+	pub data: Vec<f32>,
+}
+
+impl Array {
+	pub fn new(shape: Vec<usize>, data: Vec<f32>) -> Array {
+		Array { shape: shape, data: data }
+	}
+}
+
+pub trait Backend {
+	fn run(&mut self, inputs: Vec<Array>) -> Result<Vec<Array>, String>;
+}
+
+/// Load a model file with the named backend
+pub fn load(kind: &str, file: &str) -> Result<Box<dyn Backend>, String> {
+	match kind {
+		"tract" => Ok(Box::new(Tract::load(file)?)),
+		_ => Err(format!("unknown backend {}", kind)),
+	}
+}
+
+use tract_onnx::prelude::*;
+
+pub struct Tract {
+	plan: TypedRunnableModel<TypedModel>,
+}
+
+impl Tract {
+	pub fn load(file: &str) -> Result<Tract, String> {
+		let plan = tract_onnx::onnx()
+			.model_for_path(file)
+			.and_then(|model| model.into_optimized())
+			.and_then(|model| model.into_runnable())
+			.map_err(|err| format!("cannot load {}: {}", file, err))?;
+		Ok(Tract { plan: plan })
+	}
+}
+
+impl Backend for Tract {
+	fn run(&mut self, inputs: Vec<Array>) -> Result<Vec<Array>, String> {
+		let mut values = TVec::new();
+		for input in inputs {
+			let tensor = tract_onnx::prelude::Tensor::from_shape(&input.shape, &input.data).map_err(|err| err.to_string())?;
+			values.push(tensor.into());
+		}
+		let outputs = self.plan.run(values).map_err(|err| err.to_string())?;
+		let mut arrays = Vec::new();
+		for output in outputs {
+			let tensor = output.into_tensor().cast_to::<f32>().map_err(|err| err.to_string())?.into_owned();
+			let data = tensor.as_slice::<f32>().map_err(|err| err.to_string())?.to_vec();
+			arrays.push(Array::new(tensor.shape().to_vec(), data));
+		}
+		Ok(arrays)
+	}
+}
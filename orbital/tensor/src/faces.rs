@@ -0,0 +1,55 @@
+
+// the built in face detector - seetaface by way of rustface, which brings its own model and its own engine
+
+use service::Manifest;
+use service::vision::*;
+
+use rustface::{Detector, ImageData};
+
+use crate::frame::gray;
+use crate::model::Model;
+
+pub struct FaceModel {
+	detector: Box<dyn Detector>,
+	buffer: Vec<u8>,
+}
+
+impl FaceModel {
+	pub fn load(manifest: &Manifest) -> Result<FaceModel, String> {
+		let file = manifest.get("file").unwrap_or("../public/resources/seeta_fd_frontal_v1.0.bin");
+		let mut detector = rustface::create_detector(file).map_err(|err| format!("cannot load face detector {}: {}", file, err))?;
+		detector.set_min_face_size(manifest.get_or("min_face_size", 20));
+		detector.set_score_thresh(manifest.get_or("score_thresh", 2.0));
+		detector.set_pyramid_scale_factor(manifest.get_or("pyramid_scale_factor", 0.8));
+		let step = manifest.get_or("slide_window_step", 4);
+		detector.set_slide_window_step(step, step);
+		Ok(FaceModel { detector: detector, buffer: Vec::new() })
+	}
+}
+
+impl Model for FaceModel {
+
+	fn topic(&self) -> &str { FACES }
+
+	fn look(&mut self, pixels: &[u32], width: usize, height: usize, timestamp: u64) -> Result<String, String> {
+
+		// the detector looks at every other pixel of every other row
+		self.buffer.resize(width / 2 * height / 2, 0);
+		for y in 0..height / 2 {
+			for x in 0..width / 2 {
+				self.buffer[y * width / 2 + x] = gray(pixels[y * width * 2 + x * 2]);
+			}
+		}
+		let mut image = ImageData::new(&mut self.buffer, (width / 2) as u32, (height / 2) as u32);
+
+		// say what was found, even if that is nothing, in full frame pixels
+		let faces = self.detector.detect(&mut image).into_iter().map(|face| Face {
+			x: 2 * face.bbox().x(),
+			y: 2 * face.bbox().y(),
+			w: 2 * face.bbox().width() as i32,
+			h: 2 * face.bbox().height() as i32,
+			score: face.score(),
+		}).collect();
+		Ok(Faces { timestamp: timestamp, width: width as u32, height: height as u32, faces: faces }.to_json())
+	}
+}
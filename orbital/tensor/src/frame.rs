@@ -0,0 +1,127 @@
+
+///
+/// Frame: turning a shared camera frame into what a model wants to look at
+///
+/// camera frames are 1280x720 u32 pixels with red in the low byte, then green, then blue.
+/// a model says in its manifest how big its input is, what order the channels go in, how to normalize them
+/// and whether channels come before or after rows and columns:
+///
+///     input_width = 224
+///     input_height = 224
+///     channels = rgb          # rgb, bgr or gray
+///     layout = nchw           # nchw or nhwc
+///     mean = 0.485 0.456 0.406   # subtracted from each channel after it is scaled to 0..1
+///     std = 0.229 0.224 0.225    # and then divided by
+///
+
+use service::Manifest;
+
+use crate::backend::Array;
+
+pub const FRAME_WIDTH: usize = 1280;
+pub const FRAME_HEIGHT: usize = 720;
+
+pub fn red(pixel: u32) -> u8 { pixel as u8 }
+pub fn green(pixel: u32) -> u8 { (pixel >> 8) as u8 }
+pub fn blue(pixel: u32) -> u8 { (pixel >> 16) as u8 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channels {
+	Rgb,
+	Bgr,
+	Gray,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+	pub width: usize,
+	pub height: usize,
+	pub channels: Channels,
+	pub nchw: bool,
+	pub mean: Vec<f32>,
+	pub std: Vec<f32>,
+}
+
+impl Input {
+
+	pub fn from_manifest(manifest: &Manifest) -> Result<Input, String> {
+		let channels = match manifest.get("channels").unwrap_or("rgb") {
+			"rgb" => Channels::Rgb,
+			"bgr" => Channels::Bgr,
+			"gray" => Channels::Gray,
+			other => return Err(format!("channels should be rgb, bgr or gray, not {}", other)),
+		};
+		let nchw = match manifest.get("layout").unwrap_or("nchw") {
+			"nchw" => true,
+			"nhwc" => false,
+			other => return Err(format!("layout should be nchw or nhwc, not {}", other)),
+		};
+		let depth = if channels == Channels::Gray { 1 } else { 3 };
+		Ok(Input {
+			width: manifest.get_or("input_width", 224),
+			height: manifest.get_or("input_height", 224),
+			channels: channels,
+			nchw: nchw,
+			mean: per_channel(manifest, "mean", 0.0, depth)?,
+			std: per_channel(manifest, "std", 1.0, depth)?,
+		})
+	}
+
+	pub fn depth(&self) -> usize {
+		if self.channels == Channels::Gray { 1 } else { 3 }
+	}
+
+	pub fn shape(&self) -> Vec<usize> {
+		if self.nchw {
+			vec![1, self.depth(), self.height, self.width]
+		} else {
+			vec![1, self.height, self.width, self.depth()]
+		}
+	}
+
+	/// Scale a whole frame down (or up) to the input size by picking the nearest pixel, and normalize it
+	pub fn prepare(&self, pixels: &[u32], width: usize, height: usize) -> Array {
+		let depth = self.depth();
+		let plane = self.width * self.height;
+		let mut data = vec![0.0f32; plane * depth];
+		for y in 0..self.height {
+			let sy = y * height / self.height;
+			for x in 0..self.width {
+				let sx = x * width / self.width;
+				let pixel = pixels[sy * width + sx];
+				let values = match self.channels {
+					Channels::Rgb => [red(pixel), green(pixel), blue(pixel)],
+					Channels::Bgr => [blue(pixel), green(pixel), red(pixel)],
+					Channels::Gray => [gray(pixel), 0, 0],
+				};
+				for c in 0..depth {
+					let value = (values[c] as f32 / 255.0 - self.mean[c]) / self.std[c];
+					let index = if self.nchw { c * plane + y * self.width + x } else { (y * self.width + x) * depth + c };
+					data[index] = value;
+				}
+			}
+		}
+		Array::new(self.shape(), data)
+	}
+}
+
+pub fn gray(pixel: u32) -> u8 {
+	((red(pixel) as u32 * 299 + green(pixel) as u32 * 587 + blue(pixel) as u32 * 114) / 1000) as u8
+}
+
+// one value for every channel, or a single value for all of them
+fn per_channel(manifest: &Manifest, key: &str, default: f32, depth: usize) -> Result<Vec<f32>, String> {
+	let text = match manifest.get(key) {
+		Some(text) => text,
+		None => return Ok(vec![default; depth]),
+	};
+	let mut values = Vec::new();
+	for word in text.split_whitespace() {
+		values.push(word.parse::<f32>().map_err(|_| format!("{} has a value that is not a number: {}", key, word))?);
+	}
+	match values.len() {
+		1 => Ok(vec![values[0]; depth]),
+		n if n == depth => Ok(values),
+		n => Err(format!("{} needs 1 or {} values but has {}", key, depth, n)),
+	}
+}
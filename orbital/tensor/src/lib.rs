@@ -1,18 +1,32 @@
 
+///
+/// Tensor: runs a model over camera frames and publishes what it finds
+///
+/// the model is described by public/tensor.manifest - see model.rs for the kinds there are, runner.rs for how a
+/// model file is fed and read back, and backend.rs for what runs it.
+///
+
 use crossbeam::channel::*;
 use service::*;
 
-extern crate rustface;
-use rustface::{Detector, ImageData};
-const BUFSIZE : usize = 1280*720/4;
-
+pub mod backend;
+pub mod faces;
+pub mod frame;
+pub mod model;
+pub mod runner;
 
+use frame::{FRAME_WIDTH, FRAME_HEIGHT};
 
 #[derive(Clone)]
-pub struct Tensor {}
+pub struct Tensor {
+	manifest: String,
+}
 impl Tensor {
 	pub fn new() -> Box<dyn Serviceable> {
-		Box::new(Self{})
+		Self::with_manifest("../public/tensor.manifest")
+	}
+	pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+		Box::new(Self{ manifest: manifest.to_string() })
 	}
 }
 impl Serviceable for Tensor {
@@ -22,87 +36,57 @@ impl Serviceable for Tensor {
 		let send = send.clone();
 		let recv = recv.clone();
 		let name = self.name();
+		let manifest = self.manifest.clone();
 		let _thread = std::thread::Builder::new().name(name.to_string()).spawn(move || {
 
-
-			// start a detector
-
-	        let mut detector = rustface::create_detector("../public/resources/seeta_fd_frontal_v1.0.bin").unwrap();
-	        detector.set_min_face_size(20);
-	        detector.set_score_thresh(2.0);
-	        detector.set_pyramid_scale_factor(0.8);
-	        detector.set_slide_window_step(4, 4);
-	        println!("loaded face detector");
-
-	        let mut buffer = Box::new([0u8;BUFSIZE]);
-
-
-	
-			// in this sketch the pretend tensor module listens to ALL camera frames and looks for faces as a built in capability (like recognizing qr codes)
+			// model settings come from the manifest - anything left out gets the face detector as it always was
+			let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+				println!("Tensor: no manifest at {} ({}), using defaults",manifest,err);
+				Manifest::default()
+			});
+			let every = manifest.get_or("every",100);
+
+			let mut model = match model::load(&manifest) {
+				Ok(model) => model,
+				Err(err) => {
+					println!("Tensor: {}",err);
+					return;
+				}
+			};
+			println!("Tensor: loaded model, publishing on {}",model.topic());
+
+			// in this sketch the tensor module listens to ALL camera frames as a built in capability (like recognizing qr codes)
 			// TODO arguably like the camera service this should only work on a given frame and only pipe back to the a specified caller
-			//let message = Message::Subscribe(_sid,"/frames".to_string());
-		    //send.send(message).expect("error");
-
-		    // listen to view
-			//send.send(Message::Subscribe(_sid,"/view".to_string())).expect("tensor: failed to subscribe");
-
-// TODO
-// if it waits for every frame then it will get pretty far behind
-// throw away frames more elegantly
+			let message = Message::Subscribe(_sid,"/frames".to_string());
+		    send.send(message).expect("error");
 
+			// if it waited for every frame then it would get pretty far behind, so it only looks at one every so often
 			let mut count:i32 = 0;
 
 	        while let Ok(message) = recv.recv() {
 			    match message {
-			    	Message::Event(topic,data) => {
-			    		println!("Face: Got raw data: {} {}",topic, data);
-			    		let message = Message::Event("/view".to_string(),"[Face->Display: here is a face]".to_string());
-						send.send(message).expect("error");
-			    	},
+	                Message::Share(_,sharedmemory) => {
 
-	                Message::Share(sharedmemory) => {
-
-	                	// TODO gah
 	                	count = count + 1;
-	                	if(count > 100 ) {
+	                	if count >= every {
 
 	                		count = 0;
+	                		let timestamp = vision::timestamp();
 
-		                	// get memory
-		                    let mut ptr = sharedmemory.lock().unwrap();
-
-		                    // copy it
-						    for y in 0..360{
-						        for x in 0..640{
-						            let pixel = ptr[y*1280*2+x*2];
-						            //let pixel = pixel.swap_bytes(); //.rotate_right(8);  // target format is ARGB ignoring A, and src format is probaby RGBA
-						            let pixel = pixel as u8;
-						            buffer[y*640+x]=pixel;
-						        }
-						    }
-						    let mut image = ImageData::new(buffer.as_mut(), 640, 360);
-
-						    // detect face
+		                	// hold the frame only as long as the model is looking at it
+		                    let ptr = sharedmemory.lock().unwrap();
+		                    let result = model.look(&ptr[..],FRAME_WIDTH,FRAME_HEIGHT,timestamp);
+						    drop(ptr);
 
-						    for face in detector.detect(&mut image).into_iter() {
-						        let x = 2 * face.bbox().x() as usize;
-						        let y = 2 * face.bbox().y() as usize;
-						        let w = 2 * face.bbox().width() as usize;
-						        let h = 2 * face.bbox().height() as usize;
-							    println!("done face detection");
+						    match result {
+						    	Ok(json) => send.send(Message::Event(model.topic().to_string(),json)).expect("error"),
+						    	Err(err) => send.send(Message::Event("/log".to_string(),format!("Tensor: {}",err))).expect("error"),
 						    }
 						}
-
-
 	                },
-
-
 			        _ => { },
 			    }
 	        }
 		});
 	}
 }
-
-
-
@@ -0,0 +1,29 @@
+
+///
+/// Model: something that looks at a frame and has something to say about it
+///
+/// the manifest picks one:
+///
+///     model = rustface     # the built in seetaface detector, publishing vision::Faces on /faces
+///     model = onnx         # any onnx model file, run by a backend - see runner.rs
+///
+
+use service::Manifest;
+
+use crate::faces::FaceModel;
+use crate::runner::Runner;
+
+pub trait Model {
+	/// Where what this model finds gets published
+	fn topic(&self) -> &str;
+	/// Look at one frame, returning json to publish
+	fn look(&mut self, pixels: &[u32], width: usize, height: usize, timestamp: u64) -> Result<String, String>;
+}
+
+pub fn load(manifest: &Manifest) -> Result<Box<dyn Model>, String> {
+	match manifest.get("model").unwrap_or("rustface") {
+		"rustface" => Ok(Box::new(FaceModel::load(manifest)?)),
+		"onnx" => Ok(Box::new(Runner::load(manifest)?)),
+		other => Err(format!("unknown model {} - should be rustface or onnx", other)),
+	}
+}
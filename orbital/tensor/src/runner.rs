@@ -0,0 +1,128 @@
+
+///
+/// Runner: a generic model - frame in, tensors through a backend, decoded outputs out
+///
+///     model = onnx
+///     file = ../public/resources/mobilenetv2.onnx
+///     backend = tract          # the default, pure rust on the cpu
+///     topic = /tensor          # where results are published
+///     decode = classes         # raw, classes or boxes
+///
+/// plus the input settings in frame.rs. each decoding publishes json stamped with when the frame arrived:
+///
+///     raw        every output as is: {"timestamp":..,"outputs":[{"shape":[1,1000],"data":[..]}]}
+///     classes    the top scores of the first output, named from a labels file with one label per line
+///                (labels = .., top = 5): {"timestamp":..,"classes":[{"index":281,"label":"tabby","score":0.8}]}
+///     boxes      rows of the first output read as x1 y1 x2 y2 score [class], in 0..1 of the input, kept when
+///                score >= threshold and scaled to frame pixels: {"timestamp":..,"width":..,"height":..,"boxes":[{"x":..,"y":..,"w":..,"h":..,"score":..,"class":..}]}
+///
+
+use service::Manifest;
+use service::interface::quote;
+
+use crate::backend::{self, Array, Backend};
+use crate::frame::Input;
+use crate::model::Model;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decode {
+	Raw,
+	Classes { labels: Vec<String>, top: usize },
+	Boxes { threshold: f32 },
+}
+
+pub struct Runner {
+	backend: Box<dyn Backend>,
+	input: Input,
+	decode: Decode,
+	topic: String,
+}
+
+impl Runner {
+
+	pub fn load(manifest: &Manifest) -> Result<Runner, String> {
+		let file = manifest.get("file").ok_or("an onnx model needs a file")?;
+		let backend = backend::load(manifest.get("backend").unwrap_or("tract"), file)?;
+		Ok(Runner {
+			backend: backend,
+			input: Input::from_manifest(manifest)?,
+			decode: decode_from_manifest(manifest)?,
+			topic: manifest.get("topic").unwrap_or("/tensor").to_string(),
+		})
+	}
+}
+
+impl Model for Runner {
+
+	fn topic(&self) -> &str { &self.topic }
+
+	fn look(&mut self, pixels: &[u32], width: usize, height: usize, timestamp: u64) -> Result<String, String> {
+		let outputs = self.backend.run(vec![self.input.prepare(pixels, width, height)])?;
+		decode(&self.decode, &outputs, width, height, timestamp)
+	}
+}
+
+fn decode_from_manifest(manifest: &Manifest) -> Result<Decode, String> {
+	match manifest.get("decode").unwrap_or("raw") {
+		"raw" => Ok(Decode::Raw),
+		"classes" => {
+			let labels = match manifest.get("labels") {
+				Some(file) => std::fs::read_to_string(file)
+					.map_err(|err| format!("cannot read labels {}: {}", file, err))?
+					.lines()
+					.map(|line| line.trim().to_string())
+					.collect(),
+				None => Vec::new(),
+			};
+			Ok(Decode::Classes { labels: labels, top: manifest.get_or("top", 5) })
+		},
+		"boxes" => Ok(Decode::Boxes { threshold: manifest.get_or("threshold", 0.5) }),
+		other => Err(format!("decode should be raw, classes or boxes, not {}", other)),
+	}
+}
+
+pub fn decode(decode: &Decode, outputs: &[Array], width: usize, height: usize, timestamp: u64) -> Result<String, String> {
+	match decode {
+		Decode::Raw => {
+			let outputs: Vec<String> = outputs.iter().map(|output| {
+				format!("{{\"shape\":[{}],\"data\":[{}]}}", join(&output.shape), join(&output.data))
+			}).collect();
+			Ok(format!("{{\"timestamp\":{},\"outputs\":[{}]}}", timestamp, outputs.join(",")))
+		},
+		Decode::Classes { labels, top } => {
+			let output = outputs.first().ok_or("the model has no outputs")?;
+			let mut scores: Vec<(usize, f32)> = output.data.iter().cloned().enumerate().collect();
+			scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+			let classes: Vec<String> = scores.iter().take(*top).map(|(index, score)| {
+				let label = labels.get(*index).map(|label| label.as_str()).unwrap_or("");
+				format!("{{\"index\":{},\"label\":{},\"score\":{}}}", index, quote(label), score)
+			}).collect();
+			Ok(format!("{{\"timestamp\":{},\"classes\":[{}]}}", timestamp, classes.join(",")))
+		},
+		Decode::Boxes { threshold } => {
+			let output = outputs.first().ok_or("the model has no outputs")?;
+			let columns = *output.shape.last().unwrap_or(&0);
+			if columns < 5 {
+				return Err(format!("boxes need rows of at least 5 values but the output is shaped {:?}", output.shape));
+			}
+			let mut boxes = Vec::new();
+			for row in output.data.chunks(columns) {
+				let score = row[4];
+				if score < *threshold {
+					continue;
+				}
+				let x = (row[0] * width as f32) as i32;
+				let y = (row[1] * height as f32) as i32;
+				let w = ((row[2] - row[0]) * width as f32) as i32;
+				let h = ((row[3] - row[1]) * height as f32) as i32;
+				let class = if columns > 5 { row[5] as i64 } else { 0 };
+				boxes.push(format!("{{\"x\":{},\"y\":{},\"w\":{},\"h\":{},\"score\":{},\"class\":{}}}", x, y, w, h, score, class));
+			}
+			Ok(format!("{{\"timestamp\":{},\"width\":{},\"height\":{},\"boxes\":[{}]}}", timestamp, width, height, boxes.join(",")))
+		},
+	}
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+	values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(",")
+}
@@ -0,0 +1,73 @@
+use service::interface::Json;
+use tensor::backend::Array;
+use tensor::runner::*;
+
+fn number(json: &Json, key: &str) -> f64 {
+	match json.get(key) {
+		Some(Json::Number(n)) => n.parse().unwrap(),
+		other => panic!("{} is {:?}", key, other),
+	}
+}
+
+fn list<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+	match json.get(key) {
+		Some(Json::Array(items)) => items,
+		other => panic!("{} is {:?}", key, other),
+	}
+}
+
+#[test]
+fn raw_outputs_as_they_are() {
+	let outputs = vec![Array::new(vec![1, 2], vec![0.5, -1.0]), Array::new(vec![1], vec![3.0])];
+	let json = decode(&Decode::Raw, &outputs, 1280, 720, 42).unwrap();
+	assert_eq!(json, "{\"timestamp\":42,\"outputs\":[{\"shape\":[1,2],\"data\":[0.5,-1]},{\"shape\":[1],\"data\":[3]}]}");
+}
+
+#[test]
+fn classes_are_the_top_scores_named() {
+	let labels = vec!["cat".to_string(), "a \"dog\"\\wolf".to_string(), "bird".to_string()];
+	let outputs = vec![Array::new(vec![1, 4], vec![0.1, 0.7, 0.15, 0.05])];
+	let json = decode(&Decode::Classes { labels: labels, top: 3 }, &outputs, 1280, 720, 7).unwrap();
+	let json = Json::parse(&json).unwrap();
+	assert_eq!(number(&json, "timestamp"), 7.0);
+	let classes = list(&json, "classes");
+	let picked: Vec<(f64, Option<&Json>)> = classes.iter().map(|class| (number(class, "index"), class.get("label"))).collect();
+	assert_eq!(picked, vec![
+		(1.0, Some(&Json::String("a \"dog\"\\wolf".to_string()))),
+		(2.0, Some(&Json::String("bird".to_string()))),
+		(0.0, Some(&Json::String("cat".to_string()))),
+	]);
+	assert!((number(&classes[0], "score") - 0.7).abs() < 1e-6);
+
+	// past the end of the labels there is no name
+	let json = decode(&Decode::Classes { labels: vec![], top: 1 }, &outputs, 1280, 720, 7).unwrap();
+	assert!(json.contains("\"label\":\"\""));
+}
+
+#[test]
+fn boxes_are_scaled_to_the_frame() {
+	let rows = vec![
+		0.25, 0.5, 0.75, 1.0, 0.9, 3.0,
+		0.0, 0.0, 0.1, 0.1, 0.2, 1.0,
+	];
+	let outputs = vec![Array::new(vec![1, 2, 6], rows)];
+	let json = decode(&Decode::Boxes { threshold: 0.5 }, &outputs, 1280, 720, 1).unwrap();
+	let json = Json::parse(&json).unwrap();
+	assert_eq!((number(&json, "width"), number(&json, "height")), (1280.0, 720.0));
+	let boxes = list(&json, "boxes");
+	assert_eq!(boxes.len(), 1);
+	let found: Vec<f64> = ["x", "y", "w", "h", "class"].iter().map(|key| number(&boxes[0], key)).collect();
+	assert_eq!(found, vec![320.0, 360.0, 640.0, 360.0, 3.0]);
+
+	// without a class column everything is class 0
+	let outputs = vec![Array::new(vec![1, 5], vec![0.0, 0.0, 1.0, 1.0, 0.5])];
+	assert!(decode(&Decode::Boxes { threshold: 0.5 }, &outputs, 10, 10, 1).unwrap().contains("\"w\":10,\"h\":10,\"score\":0.5,\"class\":0"));
+}
+
+#[test]
+fn outputs_that_do_not_fit_are_errors() {
+	let narrow = vec![Array::new(vec![1, 4], vec![0.0; 4])];
+	assert_eq!(decode(&Decode::Boxes { threshold: 0.5 }, &narrow, 10, 10, 1), Err("boxes need rows of at least 5 values but the output is shaped [1, 4]".to_string()));
+	assert_eq!(decode(&Decode::Boxes { threshold: 0.5 }, &[], 10, 10, 1), Err("the model has no outputs".to_string()));
+	assert_eq!(decode(&Decode::Classes { labels: vec![], top: 5 }, &[], 10, 10, 1), Err("the model has no outputs".to_string()));
+}
@@ -0,0 +1,66 @@
+use service::Manifest;
+use tensor::frame::*;
+
+fn pixel(r: u8, g: u8, b: u8) -> u32 {
+	r as u32 | (g as u32) << 8 | (b as u32) << 16
+}
+
+fn input(manifest: &str) -> Input {
+	Input::from_manifest(&Manifest::parse(manifest).unwrap()).unwrap()
+}
+
+fn close(a: &[f32], b: &[f32]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-4)
+}
+
+// a 4x2 frame where every pixel is different
+fn frame() -> Vec<u32> {
+	vec![
+		pixel(255, 0, 0), pixel(0, 255, 0), pixel(0, 0, 255), pixel(255, 255, 255),
+		pixel(0, 0, 0), pixel(51, 102, 153), pixel(10, 20, 30), pixel(40, 50, 60),
+	]
+}
+
+#[test]
+fn shrinks_by_the_nearest_pixel_into_planes() {
+	let input = input("input_width = 2\ninput_height = 1");
+	assert_eq!(input.shape(), vec![1, 3, 1, 2]);
+	let array = input.prepare(&frame(), 4, 2);
+	assert_eq!(array.shape, vec![1, 3, 1, 2]);
+	// the first and third pixels of the top row, red plane then green then blue
+	assert!(close(&array.data, &[1.0, 0.0, 0.0, 0.0, 0.0, 1.0]), "{:?}", array.data);
+}
+
+#[test]
+fn channels_last_in_bgr_normalized() {
+	let input = input("input_width = 2\ninput_height = 2\nchannels = bgr\nlayout = nhwc\nmean = 0.5\nstd = 0.5 0.25 0.5");
+	assert_eq!(input.shape(), vec![1, 2, 2, 3]);
+	let array = input.prepare(&frame(), 4, 2);
+	let scale = |value: f32, std: f32| (value / 255.0 - 0.5) / std;
+	let expect: Vec<f32> = [(0.0, 0.0, 255.0), (255.0, 0.0, 0.0), (0.0, 0.0, 0.0), (30.0, 20.0, 10.0)].iter()
+		.flat_map(|(b, g, r)| vec![scale(*b, 0.5), scale(*g, 0.25), scale(*r, 0.5)])
+		.collect();
+	assert!(close(&array.data, &expect), "{:?}", array.data);
+}
+
+#[test]
+fn gray_and_growing() {
+	let input = input("input_width = 3\ninput_height = 2\nchannels = gray");
+	assert_eq!(input.shape(), vec![1, 1, 2, 3]);
+	let one = pixel(200, 100, 50);
+	let array = input.prepare(&[one], 1, 1);
+	let gray = (200 * 299 + 100 * 587 + 50 * 114) / 1000;
+	assert_eq!(tensor::frame::gray(one), gray as u8);
+	assert!(close(&array.data, &[gray as f32 / 255.0; 6]), "{:?}", array.data);
+}
+
+#[test]
+fn bad_settings_are_refused() {
+	let error = |manifest: &str| Input::from_manifest(&Manifest::parse(manifest).unwrap()).unwrap_err();
+	assert_eq!(error("channels = rgba"), "channels should be rgb, bgr or gray, not rgba");
+	assert_eq!(error("layout = chw"), "layout should be nchw or nhwc, not chw");
+	assert_eq!(error("mean = 0.1 0.2"), "mean needs 1 or 3 values but has 2");
+	assert_eq!(error("std = 1 one 1"), "std has a value that is not a number: one");
+	assert_eq!(input("channels = gray\nmean = 0.5").mean, vec![0.5]);
+	assert_eq!(input("").std, vec![1.0, 1.0, 1.0]);
+}
@@ -0,0 +1,82 @@
+
+///
+/// Tracker: gives faces found on /faces an identity that lasts from frame to frame
+///
+/// publishes every confirmed track on /tracks after each set of faces, and /tracks/enter and /tracks/leave as
+/// things come and go - see service/src/vision.rs. settings are in public/tracker.manifest.
+///
+
+use crossbeam::channel::*;
+use service::*;
+use service::vision::*;
+
+pub mod track;
+
+use track::{Detection, Settings};
+
+#[derive(Clone)]
+pub struct Tracker {
+    manifest: String,
+}
+
+impl Tracker {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/tracker.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for Tracker {
+    fn name(&self) -> &str { "Tracker" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("Tracker: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let defaults = Settings::default();
+            let settings = Settings {
+                iou: manifest.get_or("iou", defaults.iou),
+                confirm: manifest.get_or("confirm", defaults.confirm),
+                misses: manifest.get_or("misses", defaults.misses),
+                process_noise: manifest.get_or("process_noise", defaults.process_noise),
+                measurement_noise: manifest.get_or("measurement_noise", defaults.measurement_noise),
+            };
+            let mut tracker = track::Tracker::new(settings);
+
+            send.send(Message::Subscribe(sid, FACES.to_string())).expect("error");
+
+            while let Ok(message) = recv.recv() {
+                match message {
+                    Message::Event(topic, data) if topic == FACES => {
+                        let faces = match Faces::parse(&data) {
+                            Ok(faces) => faces,
+                            Err(err) => {
+                                println!("Tracker: cannot read faces: {}", err);
+                                continue;
+                            }
+                        };
+                        let detections: Vec<Detection> = faces.faces.iter().map(|face| Detection::new(face.x, face.y, face.w, face.h, face.score)).collect();
+                        let update = tracker.update(faces.timestamp, &detections);
+
+                        let tracks = |tracks: Vec<Track>| Tracks { timestamp: faces.timestamp, width: faces.width, height: faces.height, tracks: tracks }.to_json();
+                        if !update.entered.is_empty() {
+                            send.send(Message::Event(ENTER.to_string(), tracks(update.entered))).expect("error");
+                        }
+                        if !update.left.is_empty() {
+                            send.send(Message::Event(LEAVE.to_string(), tracks(update.left))).expect("error");
+                        }
+                        send.send(Message::Event(TRACKS.to_string(), tracks(update.tracks))).expect("error");
+                    },
+                    _ => { },
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,265 @@
+
+///
+/// Following detections from one frame to the next
+///
+/// each track keeps a kalman filter on the center and size of its box, assuming they move at a steady speed.
+/// every new set of detections is first compared against where each track expects to be by now; the pairs that
+/// overlap best (by intersection over union) are matched greedily, matched tracks fold in their detection, and
+/// whatever detections are left over start new tracks.
+///
+/// a new track has to be seen a few times in a row before it counts - one stray detection should not be "someone arrived" -
+/// and a track that goes unseen for too many detections in a row is dropped.
+///
+
+use service::vision::Track;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    // how much two boxes have to overlap to be the same thing
+    pub iou: f64,
+    // how many detections before a track is announced
+    pub confirm: u32,
+    // how many detections a track can miss before it is dropped
+    pub misses: u32,
+    // how much a box is expected to wander per second, in pixels
+    pub process_noise: f64,
+    // how far off a detection is expected to be, in pixels
+    pub measurement_noise: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { iou: 0.3, confirm: 2, misses: 3, process_noise: 50.0, measurement_noise: 10.0 }
+    }
+}
+
+// one number and how fast it is changing
+#[derive(Clone, Debug)]
+struct Filter {
+    value: f64,
+    rate: f64,
+    // covariance of value and rate
+    p: [[f64; 2]; 2],
+}
+
+impl Filter {
+
+    fn new(value: f64, settings: &Settings) -> Filter {
+        let r = settings.measurement_noise * settings.measurement_noise;
+        Filter { value: value, rate: 0.0, p: [[r, 0.0], [0.0, r * 10.0]] }
+    }
+
+    fn predict(&mut self, dt: f64, settings: &Settings) {
+        self.value += self.rate * dt;
+        let p = self.p;
+        let q = settings.process_noise * settings.process_noise;
+        self.p[0][0] = p[0][0] + dt * (p[1][0] + p[0][1]) + dt * dt * p[1][1] + q * dt * dt * dt / 3.0;
+        self.p[0][1] = p[0][1] + dt * p[1][1] + q * dt * dt / 2.0;
+        self.p[1][0] = p[1][0] + dt * p[1][1] + q * dt * dt / 2.0;
+        self.p[1][1] = p[1][1] + q * dt;
+    }
+
+    fn correct(&mut self, measured: f64, settings: &Settings) {
+        let r = settings.measurement_noise * settings.measurement_noise;
+        let s = self.p[0][0] + r;
+        let k0 = self.p[0][0] / s;
+        let k1 = self.p[1][0] / s;
+        let error = measured - self.value;
+        self.value += k0 * error;
+        self.rate += k1 * error;
+        let p = self.p;
+        self.p[0][0] = (1.0 - k0) * p[0][0];
+        self.p[0][1] = (1.0 - k0) * p[0][1];
+        self.p[1][0] = p[1][0] - k1 * p[0][0];
+        self.p[1][1] = p[1][1] - k1 * p[0][1];
+    }
+}
+
+// a box as center and size, which is what moves smoothly
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+    pub score: f64,
+}
+
+impl Detection {
+    pub fn new(x: i32, y: i32, w: i32, h: i32, score: f64) -> Detection {
+        Detection { x: x as f64, y: y as f64, w: w as f64, h: h as f64, score: score }
+    }
+}
+
+struct Tracked {
+    id: u64,
+    cx: Filter,
+    cy: Filter,
+    w: Filter,
+    h: Filter,
+    score: f64,
+    hits: u32,
+    misses: u32,
+    confirmed: bool,
+}
+
+impl Tracked {
+
+    fn new(id: u64, detection: &Detection, settings: &Settings) -> Tracked {
+        Tracked {
+            id: id,
+            cx: Filter::new(detection.x + detection.w / 2.0, settings),
+            cy: Filter::new(detection.y + detection.h / 2.0, settings),
+            w: Filter::new(detection.w, settings),
+            h: Filter::new(detection.h, settings),
+            score: detection.score,
+            hits: 1,
+            misses: 0,
+            confirmed: settings.confirm <= 1,
+        }
+    }
+
+    fn predict(&mut self, dt: f64, settings: &Settings) {
+        for filter in [&mut self.cx, &mut self.cy, &mut self.w, &mut self.h].iter_mut() {
+            filter.predict(dt, settings);
+        }
+    }
+
+    fn correct(&mut self, detection: &Detection, settings: &Settings) {
+        self.cx.correct(detection.x + detection.w / 2.0, settings);
+        self.cy.correct(detection.y + detection.h / 2.0, settings);
+        self.w.correct(detection.w, settings);
+        self.h.correct(detection.h, settings);
+        self.score = detection.score;
+        self.hits += 1;
+        self.misses = 0;
+    }
+
+    fn bounds(&self) -> Detection {
+        let w = self.w.value.max(0.0);
+        let h = self.h.value.max(0.0);
+        Detection { x: self.cx.value - w / 2.0, y: self.cy.value - h / 2.0, w: w, h: h, score: self.score }
+    }
+
+    fn track(&self) -> Track {
+        let bounds = self.bounds();
+        Track {
+            id: self.id,
+            x: bounds.x.round() as i32,
+            y: bounds.y.round() as i32,
+            w: bounds.w.round() as i32,
+            h: bounds.h.round() as i32,
+            score: self.score,
+        }
+    }
+}
+
+pub fn iou(a: &Detection, b: &Detection) -> f64 {
+    let left = a.x.max(b.x);
+    let top = a.y.max(b.y);
+    let right = (a.x + a.w).min(b.x + b.w);
+    let bottom = (a.y + a.h).min(b.y + b.h);
+    let overlap = (right - left).max(0.0) * (bottom - top).max(0.0);
+    let union = a.w * a.h + b.w * b.h - overlap;
+    if union <= 0.0 { 0.0 } else { overlap / union }
+}
+
+/// What changed with one set of detections
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Update {
+    // every confirmed track, smoothed
+    pub tracks: Vec<Track>,
+    // tracks confirmed by this update
+    pub entered: Vec<Track>,
+    // confirmed tracks dropped by this update, where they were last
+    pub left: Vec<Track>,
+}
+
+pub struct Tracker {
+    settings: Settings,
+    tracks: Vec<Tracked>,
+    next: u64,
+    last: Option<u64>,
+}
+
+impl Tracker {
+
+    pub fn new(settings: Settings) -> Tracker {
+        Tracker { settings: settings, tracks: Vec::new(), next: 1, last: None }
+    }
+
+    /// Fold in every detection from one frame, taken at timestamp (ms)
+    pub fn update(&mut self, timestamp: u64, detections: &[Detection]) -> Update {
+        let settings = &self.settings;
+        let dt = match self.last {
+            Some(last) => timestamp.saturating_sub(last) as f64 / 1000.0,
+            None => 0.0,
+        };
+        self.last = Some(timestamp);
+
+        for track in self.tracks.iter_mut() {
+            track.predict(dt, settings);
+        }
+
+        // the best overlaps get first pick
+        let mut pairs = Vec::new();
+        for (t, track) in self.tracks.iter().enumerate() {
+            let bounds = track.bounds();
+            for (d, detection) in detections.iter().enumerate() {
+                let overlap = iou(&bounds, detection);
+                if overlap >= settings.iou {
+                    pairs.push((overlap, t, d));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut matched_tracks = vec![false; self.tracks.len()];
+        let mut matched_detections = vec![false; detections.len()];
+        let mut update = Update::default();
+        for (_, t, d) in pairs {
+            if matched_tracks[t] || matched_detections[d] {
+                continue;
+            }
+            matched_tracks[t] = true;
+            matched_detections[d] = true;
+            let track = &mut self.tracks[t];
+            track.correct(&detections[d], settings);
+            if !track.confirmed && track.hits >= settings.confirm {
+                track.confirmed = true;
+                update.entered.push(track.track());
+            }
+        }
+
+        // whatever was not seen this time is closer to being gone
+        let mut kept = Vec::new();
+        for (t, mut track) in self.tracks.drain(..).enumerate() {
+            if !matched_tracks[t] {
+                track.misses += 1;
+                if track.misses > settings.misses || !track.confirmed {
+                    if track.confirmed {
+                        update.left.push(track.track());
+                    }
+                    continue;
+                }
+            }
+            kept.push(track);
+        }
+        self.tracks = kept;
+
+        // and whatever was seen for the first time might be something
+        for (d, detection) in detections.iter().enumerate() {
+            if !matched_detections[d] {
+                let track = Tracked::new(self.next, detection, settings);
+                self.next += 1;
+                if track.confirmed {
+                    update.entered.push(track.track());
+                }
+                self.tracks.push(track);
+            }
+        }
+
+        update.tracks = self.tracks.iter().filter(|track| track.confirmed).map(|track| track.track()).collect();
+        update
+    }
+}
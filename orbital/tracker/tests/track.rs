@@ -0,0 +1,97 @@
+use tracker::track::*;
+
+// a 40x40 box at x,y
+fn at(x: i32, y: i32) -> Detection {
+    Detection::new(x, y, 40, 40, 0.9)
+}
+
+// frames arrive every 100ms
+fn frame(tracker: &mut Tracker, n: u64, detections: &[Detection]) -> Update {
+    tracker.update(n * 100, detections)
+}
+
+#[test]
+fn a_track_is_announced_once_it_is_seen_twice() {
+    let mut tracker = Tracker::new(Settings::default());
+    let first = frame(&mut tracker, 0, &[at(100, 100)]);
+    assert!(first.entered.is_empty() && first.tracks.is_empty());
+    let second = frame(&mut tracker, 1, &[at(104, 100)]);
+    assert_eq!(second.entered.len(), 1);
+    assert_eq!(second.tracks, second.entered);
+
+    // one stray detection never becomes anything
+    let mut tracker = Tracker::new(Settings::default());
+    frame(&mut tracker, 0, &[at(100, 100)]);
+    let update = frame(&mut tracker, 1, &[]);
+    assert_eq!(update, Update::default());
+}
+
+#[test]
+fn a_track_survives_a_missed_frame() {
+    let mut tracker = Tracker::new(Settings::default());
+    frame(&mut tracker, 0, &[at(100, 100)]);
+    let id = frame(&mut tracker, 1, &[at(105, 100)]).entered[0].id;
+    let mut seen = 0;
+    for n in 2..6 {
+        seen = frame(&mut tracker, n, &[at(100 + n as i32 * 5, 100)]).tracks[0].x;
+    }
+    // the detector misses it for a frame, and it is still there, carrying on the way it was heading
+    let missed = frame(&mut tracker, 6, &[]);
+    assert!(missed.left.is_empty());
+    assert_eq!(missed.tracks.len(), 1);
+    assert_eq!(missed.tracks[0].id, id);
+    assert!(missed.tracks[0].x > seen);
+    let back = frame(&mut tracker, 7, &[at(135, 100)]);
+    assert!(back.entered.is_empty() && back.left.is_empty());
+    assert_eq!(back.tracks.iter().map(|track| track.id).collect::<Vec<u64>>(), vec![id]);
+}
+
+#[test]
+fn crossing_objects_keep_their_ids() {
+    let mut tracker = Tracker::new(Settings::default());
+    // one walks right and one walks left, a little lower, and they pass each other around frame 10
+    let walk = |n: i32| vec![at(100 + n * 10, 100), at(300 - n * 10, 110)];
+    frame(&mut tracker, 0, &walk(0));
+    let entered = frame(&mut tracker, 1, &walk(1)).entered;
+    assert_eq!(entered.len(), 2);
+    let (right, left) = (entered[0].id, entered[1].id);
+    assert_ne!(right, left);
+    for n in 2..20 {
+        let update = frame(&mut tracker, n as u64, &walk(n));
+        assert!(update.entered.is_empty() && update.left.is_empty(), "frame {}: {:?}", n, update);
+        assert_eq!(update.tracks.len(), 2);
+        let going_right = update.tracks.iter().find(|track| track.id == right).unwrap();
+        let going_left = update.tracks.iter().find(|track| track.id == left).unwrap();
+        assert!((going_right.x - (100 + n * 10)).abs() <= 20, "frame {}: {:?}", n, update);
+        assert!((going_left.x - (300 - n * 10)).abs() <= 20, "frame {}: {:?}", n, update);
+    }
+}
+
+#[test]
+fn a_track_leaves_after_too_many_misses() {
+    let settings = Settings { misses: 3, ..Settings::default() };
+    let mut tracker = Tracker::new(settings);
+    frame(&mut tracker, 0, &[at(100, 100)]);
+    let id = frame(&mut tracker, 1, &[at(100, 100)]).entered[0].id;
+    for n in 2..5 {
+        let update = frame(&mut tracker, n, &[]);
+        assert!(update.left.is_empty(), "left after {} misses", n - 1);
+        assert_eq!(update.tracks.len(), 1);
+    }
+    let update = frame(&mut tracker, 5, &[]);
+    assert_eq!(update.left.len(), 1);
+    assert_eq!(update.left[0].id, id);
+    assert_eq!((update.left[0].x, update.left[0].y), (100, 100));
+    assert!(update.tracks.is_empty());
+    // and something turning up there later is someone new
+    frame(&mut tracker, 6, &[at(100, 100)]);
+    assert_ne!(frame(&mut tracker, 7, &[at(100, 100)]).entered[0].id, id);
+}
+
+#[test]
+fn overlap() {
+    assert_eq!(iou(&at(0, 0), &at(0, 0)), 1.0);
+    assert_eq!(iou(&at(0, 0), &at(40, 0)), 0.0);
+    assert!((iou(&at(0, 0), &at(20, 0)) - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(iou(&Detection::new(0, 0, 0, 0, 1.0), &Detection::new(0, 0, 0, 0, 1.0)), 0.0);
+}
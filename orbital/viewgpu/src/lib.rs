@@ -941,6 +941,8 @@ struct Renderable {
 	box_y: i16,
 	velocity_x: i16,
 	velocity_y: i16,
+	// who asked for it, so that it goes when they clear
+	owner: String,
 }
 
 impl Renderable {
@@ -1002,6 +1004,7 @@ impl View {
 			box_y: 16,
 			velocity_x: 1,
 			velocity_y: 1,
+			owner: String::new(),
 		});
 
 		//////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -1254,7 +1257,14 @@ impl Serviceable for View {
 				match message {
 					Message::Event(topic,data) => {
 						println!("View: Received: {} {}",topic, data);
-						match data.as_str() {
+						// requests may be tagged with their owner as "cube @owner"
+						let (data, owner) = service::owner(&data);
+						let owner = owner.unwrap_or("").to_string();
+						match data {
+							"clear" => {
+								// the background is no ones to clear
+								objects.retain(|r| r.kind == 0 || r.owner != owner);
+							},
 							"cube" => {
 								println!("Display: got a cube");
 								let r = Renderable {
@@ -1263,6 +1273,7 @@ impl Serviceable for View {
 									box_y: 16,
 									velocity_x: 1,
 									velocity_y: 1,
+									owner: owner,
 								};
 								objects.push(r);
 							},
@@ -1270,7 +1281,7 @@ impl Serviceable for View {
 							}
 						}
 					},
-					//Message::Share(sharedmemory) => {
+					//Message::Share(_,sharedmemory) => {
 					//},
 					_ => { },
 				}
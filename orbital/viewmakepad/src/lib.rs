@@ -61,6 +61,18 @@ impl Serviceable for ViewMakepad {
         // listen to display messages
 		send.send(Message::Subscribe(sid,"/view".to_string())).expect("ViewMakepad: failed to subscribe");
 
+        // listen to frames of video to show
+		send.send(Message::Subscribe(sid,vision::FRAMES.to_string())).expect("ViewMakepad: failed to subscribe");
+
+        // listen to errors and other log traffic so that the desktop can show them
+		send.send(Message::Subscribe(sid,"/log".to_string())).expect("ViewMakepad: failed to subscribe");
+
+        // listen to face detections so that they can be boxed over the video
+		send.send(Message::Subscribe(sid,vision::FACES.to_string())).expect("ViewMakepad: failed to subscribe");
+
+        // listen to scanned codes so that a web address held up to the camera can be offered in the url bar
+		send.send(Message::Subscribe(sid,vision::CODES.to_string())).expect("ViewMakepad: failed to subscribe");
+
         // open a display -> this never returns for now!!!
         let mut cx = Cx::default();
         cx.style();
@@ -85,6 +97,12 @@ impl Serviceable for ViewMakepad {
 // here we jump over to makepad to do real work - this is all just scratch test code right now
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// how many lines of /log traffic the desktop keeps on screen
+const LOG_LINES: usize = 12;
+
+// where the video is drawn on the desktop
+const VIDEO: Rect = Rect{pos:Vec2{x:100.0,y:100.0},size:Vec2{x:356.0,y:200.0}};
+
 // extern crate rustface;
 // use rustface::{Detector, ImageData};
 //const BUFSIZE : usize = 1280*720/4;
@@ -97,6 +115,11 @@ pub struct OrbitalBrowserDesktopUX {
     world_view: WorldView,
     textinput:TextInput,
     button:NormalButton,
+    log_text:DrawText,
+    log:Vec<String>,
+    face_box:DrawColor,
+    faces:Option<vision::Faces>,
+    offered:Option<String>,
     send:Sender<Message>,
     recv:Receiver<Message>,
     //detector:Box<dyn Detector>,
@@ -141,6 +164,11 @@ impl OrbitalBrowserDesktopUX {
             image_texture: texture,
             textinput: TextInput::new(cx,TextInputOptions { multiline:false, read_only: false, empty_message: "Enter URL here".to_string() }),
             button: NormalButton::new(cx),
+            log_text: DrawText::new(cx, default_shader!()),
+            log: Vec::new(),
+            face_box: DrawColor::new(cx, default_shader!()),
+            faces: None,
+            offered: None,
             send:send,
             recv:recv,
             //detector:detector,
@@ -160,23 +188,62 @@ impl OrbitalBrowserDesktopUX {
         // draw primitives
         while let Ok(message) = self.recv.try_recv() {
             match message {
+                Message::Event(topic,data) if topic == "/log" => {
+                    // keep only the most recent lines - a script error with a stack is several lines
+                    for line in data.lines() {
+                        self.log.push(line.to_string());
+                    }
+                    if self.log.len() > LOG_LINES {
+                        self.log.drain(0..self.log.len()-LOG_LINES);
+                    }
+                    self.desktop_window.main_view.redraw_view(cx);
+                },
+                Message::Event(topic,data) if topic == vision::CODES => {
+                    // a code stays in view for many frames - only offer each address once so the user can still type
+                    match vision::Codes::parse(&data) {
+                        Ok(codes) => {
+                            if let Some(url) = codes.codes.iter().filter_map(|code| code.url()).next() {
+                                if self.offered.as_deref() != Some(url) {
+                                    self.textinput.set_value(cx, url);
+                                    self.offered = Some(url.to_string());
+                                    self.desktop_window.main_view.redraw_view(cx);
+                                }
+                            }
+                        },
+                        Err(err) => println!("Display: bad codes message: {}",err),
+                    }
+                },
+                Message::Event(topic,data) if topic == vision::FACES => {
+                    // only the latest frame's faces are kept - an empty list clears the boxes
+                    match vision::Faces::parse(&data) {
+                        Ok(faces) => self.faces = Some(faces),
+                        Err(err) => println!("Display: bad faces message: {}",err),
+                    }
+                    self.desktop_window.main_view.redraw_view(cx);
+                },
                 Message::Event(topic,data) => {
                     println!("Display: Received: {} {}",topic, data);
-                    match data.as_str() {
+                    // requests may be tagged with their owner as "cube @owner"
+                    let (data, owner) = service::owner(&data);
+                    let owner = owner.unwrap_or("").to_string();
+                    match data {
+                        "clear" => {
+                            self.world_view.scene.retain(|thing| thing.owner != owner);
+                        },
                         "cube" => {
-                            let thing = SceneThing { x:0.0, y:0.0, s:0.0, kind:1};
+                            let thing = SceneThing { x:0.0, y:0.0, s:0.0, kind:1, owner:owner };
                             self.world_view.add( thing );
                         },
                         _ => {
 
-                            let thing = SceneThing { x:0.0, y:0.0, s:0.0, kind:2};
+                            let thing = SceneThing { x:0.0, y:0.0, s:0.0, kind:2, owner:owner };
                             self.world_view.add( thing );
 
                         }
                     }
  
                 },
-                Message::Share(sharedmemory) => {
+                Message::Share(_,sharedmemory) => {
 
                     // paint to texture
                     let texture = self.image_texture;
@@ -281,7 +348,34 @@ impl OrbitalBrowserDesktopUX {
 
         if true {
             self.draw_image.texture = self.image_texture.into();
-            self.draw_image.draw_quad_abs(cx, Rect{pos:Vec2{x:100.0,y:100.0},size:Vec2{x:356.0,y:200.0}});
+            self.draw_image.draw_quad_abs(cx, VIDEO);
+        }
+
+        // box each detected face over the video, scaled from frame pixels down to the drawn size
+        if let Some(faces) = &self.faces {
+            let sx = VIDEO.size.x / faces.width as f32;
+            let sy = VIDEO.size.y / faces.height as f32;
+            self.face_box.color = Vec4{x:0.0, y:1.0, z:0.0, w:1.0};
+            for face in &faces.faces {
+                let x = VIDEO.pos.x + face.x as f32 * sx;
+                let y = VIDEO.pos.y + face.y as f32 * sy;
+                let w = face.w as f32 * sx;
+                let h = face.h as f32 * sy;
+                for edge in &[
+                    Rect{pos:Vec2{x:x, y:y}, size:Vec2{x:w, y:2.0}},
+                    Rect{pos:Vec2{x:x, y:y+h-2.0}, size:Vec2{x:w, y:2.0}},
+                    Rect{pos:Vec2{x:x, y:y}, size:Vec2{x:2.0, y:h}},
+                    Rect{pos:Vec2{x:x+w-2.0, y:y}, size:Vec2{x:2.0, y:h}},
+                ] {
+                    self.face_box.draw_quad_abs(cx, *edge);
+                }
+            }
+        }
+
+        // recent errors and log output along the left edge under the video
+        self.log_text.color = Vec4{x:1.0, y:0.4, z:0.4, w:1.0};
+        for (i,line) in self.log.iter().enumerate() {
+            self.log_text.draw_text_abs(cx, Vec2{x:10.0, y:320.0 + 16.0 * i as f32}, line);
         }
 
         self.desktop_window.end_desktop_window(cx);
@@ -306,6 +400,8 @@ pub struct SceneThing {
     pub y: f64,
     pub s: f64,
     pub kind: u32,
+    // who asked for it, so that it goes when they clear
+    pub owner: String,
 }
 
 #[derive(Clone)]
@@ -0,0 +1,276 @@
+
+// a picture being drawn in 2d, a pixel at a time in the same layout as camera frames - red in the low byte, then green,
+// blue and alpha - which is also the byte order the window wants. everything drawn is cut to a clip rectangle, and only
+// the pixels inside it are ever visited, so redrawing a small part of the window costs only that part. edges of
+// lines, circles and letters are smoothed by how much of each pixel they cover.
+
+use ab_glyph::{point, Font, FontRef, GlyphId, ScaleFont};
+
+/// A rectangle of whole pixels, from x, y going w across and h down - edges past the largest i32 stop there rather than
+/// wrapping, so a rectangle from anywhere is safe to cut down to the canvas
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+	pub x: i32,
+	pub y: i32,
+	pub w: i32,
+	pub h: i32,
+}
+
+impl Rect {
+
+	pub fn new(x: i32, y: i32, w: i32, h: i32) -> Rect {
+		Rect { x: x, y: y, w: w.max(0), h: h.max(0) }
+	}
+
+	/// The smallest rectangle of whole pixels that holds some span of the plane, as far as half the range of an i32
+	/// either way, so that it is never too wide to say how wide it is
+	pub fn around(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+		let whole = |value: f32| (value as i64).clamp(i32::MIN as i64 / 2, i32::MAX as i64 / 2) as i32;
+		let (left, top) = (whole(x0.min(x1).floor()), whole(y0.min(y1).floor()));
+		Rect::new(left, top, whole(x0.max(x1).ceil()) - left, whole(y0.max(y1).ceil()) - top)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.w <= 0 || self.h <= 0
+	}
+
+	pub fn right(&self) -> i32 { self.x.saturating_add(self.w) }
+
+	pub fn bottom(&self) -> i32 { self.y.saturating_add(self.h) }
+
+	/// Where both are, or nothing at all if they do not meet
+	pub fn intersect(&self, other: &Rect) -> Rect {
+		let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+		let rect = Rect::new(x, y, self.right().min(other.right()).saturating_sub(x), self.bottom().min(other.bottom()).saturating_sub(y));
+		if rect.is_empty() { Rect::default() } else { rect }
+	}
+
+	pub fn overlaps(&self, other: &Rect) -> bool {
+		!self.intersect(other).is_empty()
+	}
+
+	/// The smallest rectangle holding both, where an empty one holds nothing
+	pub fn union(&self, other: &Rect) -> Rect {
+		if self.is_empty() {
+			return *other;
+		}
+		if other.is_empty() {
+			return *self;
+		}
+		let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+		Rect::new(x, y, self.right().max(other.right()).saturating_sub(x), self.bottom().max(other.bottom()).saturating_sub(y))
+	}
+
+	pub fn area(&self) -> i64 {
+		if self.is_empty() { 0 } else { self.w as i64 * self.h as i64 }
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct Canvas {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<u32>,
+	clip: Rect,
+}
+
+impl Canvas {
+
+	pub fn new(width: usize, height: usize) -> Canvas {
+		Canvas { width: width, height: height, pixels: vec![0; width * height], clip: Rect::new(0, 0, width as i32, height as i32) }
+	}
+
+	pub fn bounds(&self) -> Rect {
+		Rect::new(0, 0, self.width as i32, self.height as i32)
+	}
+
+	/// Only draw inside this part of the canvas from now on
+	pub fn set_clip(&mut self, clip: Rect) {
+		self.clip = clip.intersect(&self.bounds());
+	}
+
+	pub fn clip(&self) -> Rect {
+		self.clip
+	}
+
+	/// Set every pixel being drawn to a color, as it is
+	pub fn clear(&mut self, color: u32) {
+		let clip = self.clip;
+		for y in clip.y..clip.bottom() {
+			let row = y as usize * self.width;
+			self.pixels[row + clip.x as usize..row + clip.right() as usize].iter_mut().for_each(|pixel| *pixel = color);
+		}
+	}
+
+	/// Fill a rectangle
+	pub fn rect(&mut self, rect: Rect, color: u32) {
+		let area = rect.intersect(&self.clip);
+		for y in area.y..area.bottom() {
+			let row = y as usize * self.width;
+			for pixel in &mut self.pixels[row + area.x as usize..row + area.right() as usize] {
+				*pixel = mix(*pixel, color, 1.0);
+			}
+		}
+	}
+
+	/// Draw the edge of a rectangle, this many pixels thick on the inside of it
+	pub fn frame(&mut self, rect: Rect, width: i32, color: u32) {
+		let width = width.max(1);
+		// four sides that do not overlap, so a see through color is not laid on twice at the corners
+		let (top, left) = (width.min(rect.h), width.min(rect.w));
+		let (bottom, right) = (width.min(rect.h - top), width.min(rect.w - left));
+		self.rect(Rect::new(rect.x, rect.y, rect.w, top), color);
+		self.rect(Rect::new(rect.x, rect.bottom() - bottom, rect.w, bottom), color);
+		self.rect(Rect::new(rect.x, rect.y.saturating_add(top), left, rect.h - top - bottom), color);
+		self.rect(Rect::new(rect.right() - right, rect.y.saturating_add(top), right, rect.h - top - bottom), color);
+	}
+
+	/// Draw a line this many pixels wide with round ends
+	pub fn line(&mut self, (x0, y0): (f32, f32), (x1, y1): (f32, f32), width: f32, color: u32) {
+		let half = width.max(1.0) / 2.0;
+		let (dx, dy) = (x1 - x0, y1 - y0);
+		let length = dx * dx + dy * dy;
+		self.cover(Rect::around(x0 - half, y0 - half, x1 + half, y1 + half), color, |px, py| {
+			// how far the middle of the pixel is from the nearest point on the line
+			let t = if length > 0.0 { (((px - x0) * dx + (py - y0) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+			let (nx, ny) = (x0 + dx * t - px, y0 + dy * t - py);
+			half + 0.5 - (nx * nx + ny * ny).sqrt()
+		});
+	}
+
+	/// Fill a circle
+	pub fn circle(&mut self, (x, y): (f32, f32), radius: f32, color: u32) {
+		self.cover(Rect::around(x - radius, y - radius, x + radius, y + radius), color, |px, py| {
+			radius + 0.5 - ((px - x) * (px - x) + (py - y) * (py - y)).sqrt()
+		});
+	}
+
+	/// Draw the edge of a circle, this many pixels thick and centred on its radius
+	pub fn ring(&mut self, (x, y): (f32, f32), radius: f32, width: f32, color: u32) {
+		let half = width.max(1.0) / 2.0;
+		let outer = radius + half;
+		self.cover(Rect::around(x - outer, y - outer, x + outer, y + outer), color, |px, py| {
+			half + 0.5 - (((px - x) * (px - x) + (py - y) * (py - y)).sqrt() - radius).abs()
+		});
+	}
+
+	/// Copy a picture of some size into a rectangle, stretched or squashed to fit
+	pub fn image(&mut self, source: &[u32], width: usize, height: usize, rect: Rect) {
+		if rect.is_empty() || width == 0 || height == 0 || source.len() < width * height {
+			return;
+		}
+		let area = rect.intersect(&self.clip);
+		// which column of the source each column of the area comes from, worked out once rather than per pixel
+		let columns: Vec<usize> = (area.x..area.right()).map(|x| ((x - rect.x) as usize * width / rect.w as usize).min(width - 1)).collect();
+		for y in area.y..area.bottom() {
+			let from = ((y - rect.y) as usize * height / rect.h as usize).min(height - 1) * width;
+			let row = y as usize * self.width + area.x as usize;
+			for (pixel, column) in self.pixels[row..row + columns.len()].iter_mut().zip(columns.iter()) {
+				*pixel = source[from + column] | 0xff000000;
+			}
+		}
+	}
+
+	/// Lay a color over one pixel as much as the coverage says, if it is being drawn
+	pub fn blend(&mut self, x: i32, y: i32, color: u32, coverage: f32) {
+		if coverage > 0.0 && x >= self.clip.x && x < self.clip.right() && y >= self.clip.y && y < self.clip.bottom() {
+			let at = y as usize * self.width + x as usize;
+			self.pixels[at] = mix(self.pixels[at], color, coverage.min(1.0));
+		}
+	}
+
+	// lay a color over the pixels in an area, as much as a shape covers each one - given the middle of a pixel it says
+	// how far inside the shape that is, and a pixel half in is half covered
+	fn cover(&mut self, area: Rect, color: u32, inside: impl Fn(f32, f32) -> f32) {
+		let area = area.intersect(&self.clip);
+		for y in area.y..area.bottom() {
+			let row = y as usize * self.width;
+			for x in area.x..area.right() {
+				let coverage = inside(x as f32 + 0.5, y as f32 + 0.5).clamp(0.0, 1.0);
+				if coverage > 0.0 {
+					self.pixels[row + x as usize] = mix(self.pixels[row + x as usize], color, coverage);
+				}
+			}
+		}
+	}
+}
+
+/// A color as red, green and blue from 0 to 255 and how solid it is from 0 to 1
+pub fn rgba(r: u8, g: u8, b: u8, a: f32) -> u32 {
+	r as u32 | (g as u32) << 8 | (b as u32) << 16 | ((a.clamp(0.0, 1.0) * 255.0 + 0.5) as u32) << 24
+}
+
+// a color laid over a pixel by how solid it is and how much of the pixel it covers - what is drawn on is always solid
+fn mix(under: u32, over: u32, coverage: f32) -> u32 {
+	let amount = (over >> 24) as f32 / 255.0 * coverage;
+	if amount >= 1.0 {
+		return over | 0xff000000;
+	}
+	let channel = |shift: u32| {
+		let (a, b) = ((under >> shift & 0xff) as f32, (over >> shift & 0xff) as f32);
+		((a + (b - a) * amount + 0.5) as u32).min(255) << shift
+	};
+	channel(0) | channel(8) | channel(16) | 0xff000000
+}
+
+/// Words in the font that ships in orbital/resources
+pub struct Lettering {
+	font: FontRef<'static>,
+}
+
+impl Lettering {
+
+	pub fn new() -> Lettering {
+		let font = FontRef::try_from_slice(include_bytes!("../../resources/Ubuntu-R.ttf")).expect("the bundled font is broken");
+		Lettering { font: font }
+	}
+
+	/// How wide and tall a line is with letters this many pixels tall
+	pub fn measure(&self, text: &str, size: f32) -> (f32, f32) {
+		let scaled = self.font.as_scaled(size);
+		let width = self.layout(text, size, |_, _| {});
+		(width, scaled.ascent() - scaled.descent())
+	}
+
+	/// Write a line with the top of its tallest letters at x, y
+	pub fn write(&self, canvas: &mut Canvas, text: &str, (x, y): (f32, f32), size: f32, color: u32) {
+		let baseline = y + self.font.as_scaled(size).ascent();
+		let font = &self.font;
+		self.layout(text, size, |id, caret| {
+			let glyph = id.with_scale_and_position(size, point(x + caret, baseline));
+			// letters wholly outside what is being drawn are not even outlined
+			if let Some(outline) = font.outline_glyph(glyph) {
+				let bounds = outline.px_bounds();
+				let area = Rect::around(bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y);
+				if area.overlaps(&canvas.clip()) {
+					outline.draw(|gx, gy, coverage| {
+						canvas.blend(bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32, color, coverage);
+					});
+				}
+			}
+		});
+	}
+
+	// go along a line letter by letter, saying where each one starts, and give back how far it went
+	fn layout(&self, text: &str, size: f32, mut each: impl FnMut(GlyphId, f32)) -> f32 {
+		let scaled = self.font.as_scaled(size);
+		let mut caret = 0.0;
+		let mut previous = None;
+		for letter in text.chars() {
+			let id = scaled.glyph_id(letter);
+			if let Some(previous) = previous {
+				caret += scaled.kern(previous, id);
+			}
+			each(id, caret);
+			caret += scaled.h_advance(id);
+			previous = Some(id);
+		}
+		caret
+	}
+}
+
+impl Default for Lettering {
+	fn default() -> Lettering {
+		Lettering::new()
+	}
+}
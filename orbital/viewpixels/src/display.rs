@@ -0,0 +1,334 @@
+
+// what apps have drawn in 2d, kept so that the window can be drawn again at any time. apps send json on /pixels, one
+// request or an array of them, each shape with an id of its own so it can be changed or taken away later:
+//
+//     {"op":"rect","id":"panel","x":10,"y":10,"w":100,"h":60,"fill":"#203040","stroke":"#ffffff","width":2}
+//     {"op":"line","id":"rule","from":[10,80],"to":[110,80],"color":[1,0,0],"width":3}
+//     {"op":"circle","id":"dot","x":60,"y":40,"radius":12,"fill":"#ffcc00"}
+//     {"op":"text","id":"title","x":14,"y":14,"text":"hello","size":16,"color":"#ffffff"}
+//     {"op":"image","id":"video","x":0,"y":120,"w":160,"h":90}      the latest shared frame, stretched to fit
+//     {"op":"delete","id":"dot"}
+//     {"op":"clear"}                                                  take away everything this app has drawn
+//     {"op":"background","color":"#000000"}
+//
+// colors are "#rrggbb" or "#rrggbbaa", [r,g,b] or [r,g,b,a] from 0 to 1, or a number 0xRRGGBB. a rect or circle may
+// be filled or stroked or both, and a rect's stroke is inside its edge. sending a shape with an id that is already
+// there replaces it where it is in the stack, and later shapes are drawn over earlier ones. requests are tagged with
+// who sent them as "{..} @owner" like on /scene, and ids belong to whoever sent them, so apps cannot touch each other's.
+//
+// only what has changed is drawn again - every change marks the area it covered before and covers now as dirty, and
+// drawing clears just those areas and draws just the shapes that reach into them.
+
+use service::interface::Json;
+
+use crate::canvas::{Canvas, Lettering, Rect};
+
+pub const FRAME_WIDTH: usize = 1280;
+pub const FRAME_HEIGHT: usize = 720;
+
+// past this many separate dirty areas they are drawn as one
+const DIRTY: usize = 16;
+
+// the furthest any number in a request may go either way
+const LIMIT: f32 = 1_000_000.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+	Rect { rect: Rect, fill: Option<u32>, stroke: Option<u32>, width: f32 },
+	Line { from: (f32, f32), to: (f32, f32), color: u32, width: f32 },
+	Circle { at: (f32, f32), radius: f32, fill: Option<u32>, stroke: Option<u32>, width: f32 },
+	Text { at: (f32, f32), text: String, size: f32, color: u32 },
+	Image { rect: Rect },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+	pub id: String,
+	pub owner: String,
+	pub shape: Shape,
+	// the pixels it may touch
+	pub bounds: Rect,
+}
+
+pub struct Display {
+	pub width: usize,
+	pub height: usize,
+	pub background: u32,
+	items: Vec<Item>,
+	dirty: Vec<Rect>,
+	// the latest shared frame, for images
+	frame: Option<Vec<u32>>,
+	lettering: Lettering,
+}
+
+impl Display {
+
+	pub fn new(width: usize, height: usize) -> Display {
+		Display {
+			width: width,
+			height: height,
+			background: 0xffe8b248,
+			items: Vec::new(),
+			dirty: vec![Rect::new(0, 0, width as i32, height as i32)],
+			frame: None,
+			lettering: Lettering::new(),
+		}
+	}
+
+	pub fn items(&self) -> &[Item] {
+		&self.items
+	}
+
+	pub fn get(&self, owner: &str, id: &str) -> Option<&Item> {
+		self.items.iter().find(|item| item.owner == owner && item.id == id)
+	}
+
+	/// Whether anything needs drawing again
+	pub fn is_dirty(&self) -> bool {
+		!self.dirty.is_empty()
+	}
+
+	/// Carry out a request or an array of them from someone - the first that goes wrong stops the rest
+	pub fn handle(&mut self, owner: &str, request: &str) -> Result<(), String> {
+		let json = Json::parse(request).map_err(|err| format!("bad request: {}", err))?;
+		match &json {
+			Json::Array(items) => items.iter().try_for_each(|item| self.request(owner, item)),
+			_ => self.request(owner, &json),
+		}
+	}
+
+	pub fn request(&mut self, owner: &str, json: &Json) -> Result<(), String> {
+		let op = match json.get("op") {
+			Some(Json::String(op)) => op.as_str(),
+			_ => return Err("a request needs an op".to_string()),
+		};
+		match op {
+			"delete" => {
+				let id = string(json, "id")?;
+				let index = self.items.iter().position(|item| item.owner == owner && item.id == id).ok_or(format!("no {} to delete", id))?;
+				let item = self.items.remove(index);
+				self.mark(item.bounds);
+			},
+			"clear" => {
+				let (gone, kept): (Vec<Item>, Vec<Item>) = std::mem::take(&mut self.items).into_iter().partition(|item| item.owner == owner);
+				self.items = kept;
+				gone.iter().for_each(|item| self.mark(item.bounds));
+			},
+			"background" => {
+				self.background = color(json.get("color").ok_or("a background needs a color")?, "color")? | 0xff000000;
+				self.mark(Rect::new(0, 0, self.width as i32, self.height as i32));
+			},
+			_ => {
+				let id = string(json, "id")?;
+				let shape = Shape::parse(op, json)?;
+				let bounds = self.bounds(&shape);
+				let item = Item { id: id.clone(), owner: owner.to_string(), shape: shape, bounds: bounds };
+				self.mark(bounds);
+				match self.items.iter().position(|item| item.owner == owner && item.id == id) {
+					Some(index) => {
+						let old = std::mem::replace(&mut self.items[index], item);
+						self.mark(old.bounds);
+					},
+					None => self.items.push(item),
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// Keep a copy of a shared frame, and draw the images of it again
+	pub fn frame(&mut self, frame: &[u32]) {
+		match &mut self.frame {
+			Some(kept) => kept.copy_from_slice(frame),
+			None => self.frame = Some(frame.to_vec()),
+		}
+		let images: Vec<Rect> = self.items.iter().filter(|item| matches!(item.shape, Shape::Image { .. })).map(|item| item.bounds).collect();
+		images.into_iter().for_each(|bounds| self.mark(bounds));
+	}
+
+	/// Draw what has changed since last time, giving back the areas of the canvas that were drawn
+	pub fn draw(&mut self, canvas: &mut Canvas) -> Vec<Rect> {
+		let areas = std::mem::take(&mut self.dirty);
+		for area in &areas {
+			canvas.set_clip(*area);
+			canvas.clear(self.background);
+			for item in self.items.iter().filter(|item| item.bounds.overlaps(area)) {
+				self.paint(canvas, &item.shape);
+			}
+		}
+		canvas.set_clip(canvas.bounds());
+		areas
+	}
+
+	fn paint(&self, canvas: &mut Canvas, shape: &Shape) {
+		match shape {
+			Shape::Rect { rect, fill, stroke, width } => {
+				if let Some(fill) = fill {
+					canvas.rect(*rect, *fill);
+				}
+				if let Some(stroke) = stroke {
+					canvas.frame(*rect, width.round() as i32, *stroke);
+				}
+			},
+			Shape::Line { from, to, color, width } => canvas.line(*from, *to, *width, *color),
+			Shape::Circle { at, radius, fill, stroke, width } => {
+				if let Some(fill) = fill {
+					canvas.circle(*at, *radius, *fill);
+				}
+				if let Some(stroke) = stroke {
+					canvas.ring(*at, *radius, *width, *stroke);
+				}
+			},
+			Shape::Text { at, text, size, color } => self.lettering.write(canvas, text, *at, *size, *color),
+			Shape::Image { rect } => {
+				if let Some(frame) = &self.frame {
+					canvas.image(frame, FRAME_WIDTH, FRAME_HEIGHT, *rect);
+				}
+			},
+		}
+	}
+
+	// the pixels a shape may touch, with a pixel to spare around smoothed edges
+	fn bounds(&self, shape: &Shape) -> Rect {
+		match shape {
+			Shape::Rect { rect, .. } | Shape::Image { rect } => *rect,
+			Shape::Line { from, to, width, .. } => {
+				let half = width.max(1.0) / 2.0 + 1.0;
+				Rect::around(from.0 - half, from.1 - half, to.0 + half, to.1 + half)
+			},
+			Shape::Circle { at, radius, stroke, width, .. } => {
+				let outer = radius + if stroke.is_some() { width.max(1.0) / 2.0 } else { 0.0 } + 1.0;
+				Rect::around(at.0 - outer, at.1 - outer, at.0 + outer, at.1 + outer)
+			},
+			Shape::Text { at, text, size, .. } => {
+				// letters can lean a little past where the line says it ends
+				let (width, height) = self.lettering.measure(text, *size);
+				Rect::around(at.0 - size / 4.0, at.1 - 1.0, at.0 + width + size / 4.0, at.1 + height + 1.0)
+			},
+		}
+	}
+
+	// something has changed in an area - it joins any dirty area it touches, and too many areas become one
+	fn mark(&mut self, area: Rect) {
+		let mut area = area.intersect(&Rect::new(0, 0, self.width as i32, self.height as i32));
+		if area.is_empty() {
+			return;
+		}
+		while let Some(index) = self.dirty.iter().position(|dirty| dirty.overlaps(&area)) {
+			area = area.union(&self.dirty.swap_remove(index));
+		}
+		self.dirty.push(area);
+		if self.dirty.len() > DIRTY {
+			let all = self.dirty.iter().fold(Rect::default(), |all, dirty| all.union(dirty));
+			self.dirty = vec![all];
+		}
+	}
+}
+
+impl Shape {
+
+	pub fn parse(op: &str, json: &Json) -> Result<Shape, String> {
+		match op {
+			"rect" | "image" => {
+				let rect = Rect::new(number(json, "x")? as i32, number(json, "y")? as i32, number(json, "w")? as i32, number(json, "h")? as i32);
+				if op == "image" {
+					return Ok(Shape::Image { rect: rect });
+				}
+				let (fill, stroke) = paints(json)?;
+				Ok(Shape::Rect { rect: rect, fill: fill, stroke: stroke, width: optional(json, "width")?.unwrap_or(1.0) })
+			},
+			"line" => Ok(Shape::Line {
+				from: point(json, "from")?,
+				to: point(json, "to")?,
+				color: json.get("color").map(|value| color(value, "color")).transpose()?.unwrap_or(0xffffffff),
+				width: optional(json, "width")?.unwrap_or(1.0),
+			}),
+			"circle" => {
+				let (fill, stroke) = paints(json)?;
+				Ok(Shape::Circle {
+					at: (number(json, "x")?, number(json, "y")?),
+					radius: number(json, "radius")?.max(0.0),
+					fill: fill,
+					stroke: stroke,
+					width: optional(json, "width")?.unwrap_or(1.0),
+				})
+			},
+			"text" => Ok(Shape::Text {
+				at: (number(json, "x")?, number(json, "y")?),
+				text: string(json, "text")?,
+				size: optional(json, "size")?.unwrap_or(16.0).clamp(1.0, 512.0),
+				color: json.get("color").map(|value| color(value, "color")).transpose()?.unwrap_or(0xffffffff),
+			}),
+			_ => Err(format!("unknown op {}", op)),
+		}
+	}
+}
+
+// a fill and a stroke, where a shape with neither is filled white
+fn paints(json: &Json) -> Result<(Option<u32>, Option<u32>), String> {
+	let fill = json.get("fill").map(|value| color(value, "fill")).transpose()?;
+	let stroke = json.get("stroke").map(|value| color(value, "stroke")).transpose()?;
+	Ok(if fill.is_none() && stroke.is_none() { (Some(0xffffffff), None) } else { (fill, stroke) })
+}
+
+fn string(json: &Json, key: &str) -> Result<String, String> {
+	match json.get(key) {
+		Some(Json::String(text)) => Ok(text.clone()),
+		_ => Err(format!("{} is missing or not a string", key)),
+	}
+}
+
+// every number is kept to a span far larger than any window, so that nothing worked out from it can overflow
+fn optional(json: &Json, key: &str) -> Result<Option<f32>, String> {
+	match json.get(key) {
+		None => Ok(None),
+		Some(Json::Number(n)) => finite(n).map(Some).ok_or(format!("{} is not a number: {}", key, n)),
+		Some(_) => Err(format!("{} is not a number", key)),
+	}
+}
+
+fn finite(n: &str) -> Option<f32> {
+	n.parse::<f64>().ok().filter(|n| n.is_finite()).map(|n| n.clamp(-LIMIT as f64, LIMIT as f64) as f32)
+}
+
+fn number(json: &Json, key: &str) -> Result<f32, String> {
+	optional(json, key)?.ok_or(format!("{} is missing", key))
+}
+
+fn point(json: &Json, key: &str) -> Result<(f32, f32), String> {
+	match json.get(key) {
+		Some(Json::Array(xy)) => match xy.as_slice() {
+			[Json::Number(x), Json::Number(y)] => match (finite(x), finite(y)) {
+				(Some(x), Some(y)) => Ok((x, y)),
+				_ => Err(format!("{} is [x,y]", key)),
+			},
+			_ => Err(format!("{} is [x,y]", key)),
+		},
+		_ => Err(format!("{} is missing or not [x,y]", key)),
+	}
+}
+
+fn color(json: &Json, what: &str) -> Result<u32, String> {
+	let bad = || format!("{} is not a color", what);
+	let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+	match json {
+		Json::Number(n) => n.parse::<u32>().map(|rgb| (rgb >> 16 & 0xff) | (rgb & 0xff00) | (rgb & 0xff) << 16 | 0xff000000).map_err(|_| bad()),
+		Json::String(text) if text.starts_with('#') && (text.len() == 7 || text.len() == 9) => {
+			let hex = u32::from_str_radix(&text[1..], 16).map_err(|_| bad())?;
+			let rgba = if text.len() == 7 { hex << 8 | 0xff } else { hex };
+			Ok((rgba >> 24 & 0xff) | (rgba >> 8 & 0xff00) | (rgba << 8 & 0xff0000) | (rgba & 0xff) << 24)
+		},
+		Json::Array(values) => {
+			let values = values.iter().map(|value| match value {
+				Json::Number(n) => n.parse::<f32>().map_err(|_| bad()),
+				_ => Err(bad()),
+			}).collect::<Result<Vec<f32>, String>>()?;
+			match values.as_slice() {
+				[r, g, b] => Ok(byte(*r) | byte(*g) << 8 | byte(*b) << 16 | 0xff000000),
+				[r, g, b, a] => Ok(byte(*r) | byte(*g) << 8 | byte(*b) << 16 | byte(*a) << 24),
+				_ => Err(format!("{} is [r,g,b] or [r,g,b,a]", what)),
+			}
+		},
+		_ => Err(bad()),
+	}
+}
@@ -11,21 +11,17 @@ use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
 
+pub mod canvas;
+pub mod display;
+
+use canvas::Canvas;
+use display::Display;
+
 const WIDTH: u32 = 320;
 const HEIGHT: u32 = 240;
-const BOX_SIZE: i16 = 64;
-
-//
-// A renderable
-//
-struct Renderable {
-	kind: i16,
-	box_x: i16,
-	box_y: i16,
-	velocity_x: i16,
-	velocity_y: i16,
-}
 
+// apps draw by sending json here - see display.rs
+pub const PIXELS: &str = "/pixels";
 
 #[derive(Clone)]
 pub struct ViewPixels {}
@@ -44,18 +40,10 @@ impl Serviceable for ViewPixels {
 		let _name = self.name();
 
 	    //////////////////////////////////////////////////////////////////////////////////////////////////////////////
-		// This is an array of things to render
-
-		let mut objects: Vec<Renderable> = vec![];
+		// This is what apps have drawn, and the picture of it that is copied to the window
 
-		// clear backgrounder
-		objects.push( Renderable {
-        	kind: 0,
-            box_x: 24,
-            box_y: 16,
-            velocity_x: 1,
-            velocity_y: 1,
-        });
+		let mut display = Display::new(WIDTH as usize, HEIGHT as usize);
+		let mut canvas = Canvas::new(WIDTH as usize, HEIGHT as usize);
 
 	    //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 		// Build 
@@ -79,12 +67,16 @@ impl Serviceable for ViewPixels {
 		let mut pixels = Pixels::new(WIDTH, HEIGHT, surface_texture).unwrap();
 
 	    //////////////////////////////////////////////////////////////////////////////////////////////////////////////
-		// tell the system message broker that I want to listen for messages to '/view'
+		// tell the system message broker that I want to listen for frames of video on '/frames'
 		// TODO - i wonder if the broker cannot be smarter? think about wiring a bit more
 		// TODO - also, I could just tell the message system what my receive port is at this time; not earlier
 		// TOOD - or... send to this based on absolute path /system/context/view or something?
 
-		let message = Message::Subscribe(_sid,"/view".to_string());
+		let message = Message::Subscribe(_sid,vision::FRAMES.to_string());
+	    send.send(message).expect("error");
+
+		// and for things to draw - see display.rs
+		let message = Message::Subscribe(_sid,PIXELS.to_string());
 	    send.send(message).expect("error");
 
 	    //////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -93,52 +85,39 @@ impl Serviceable for ViewPixels {
 		event_loop.run(move |event, _, control_flow| {
 
 
-			// TODO
-			//		- catch messages here and then add new objects to some kind of list
-			//		- i could test with 2d boxes
-			//		- how hard is it to tell wgpu to make and cache say a box, or text, or a 3d object?
-			//		- how hard is it to dynamically extend or add new things to the list of wgpu objects?
-			//		- it is a big hassle to do camera projection, shader lighting transforms and so on?
-			//		- is it a big hassle to load fonts
-			//		- is it a big hassle to load gltf?
-			//		- see https://nyxtom.dev/2020/10/08/framebuffers/
-
 	        while let Ok(message) = recv.try_recv() {
 	            match message {
-	                Message::Event(topic,data) => {
-	                    println!("ViewPixels: Received: {} {}",topic, data);
-	                    match data.as_str() {
-	                        "cube" => {
-			                    println!("ViewPixels: got a cube");
-								let r = Renderable {
-						        	kind: 1,
-						            box_x: 24,
-						            box_y: 16,
-						            velocity_x: 1,
-						            velocity_y: 1,
-						        };
-								objects.push(r);
-	                        },
-	                        _ => {
-	                        }
+	                Message::Event(topic,data) if topic == PIXELS => {
+	                    let (request,owner) = owner(&data);
+	                    if let Err(err) = display.handle(owner.unwrap_or("app"),request) {
+	                        let text = format!("ViewPixels: {}",err);
+	                        println!("{}",text);
+	                        let _ = send.send(Message::Event("/log".to_string(),text));
 	                    }
 	                },
-	                //Message::Share(sharedmemory) => {
-	                //},
+	                Message::Share(_,sharedmemory) => {
+	                    let ptr = sharedmemory.lock().unwrap();
+	                    display.frame(&ptr[..]);
+	                },
 					_ => { },
 				}
 			}
+			if display.is_dirty() {
+				window.request_redraw();
+			}
 
-			// Draw the current frame
+			// Draw whatever has changed, and copy just that to the window
 			if let Event::RedrawRequested(_) = event {
 
-				for i in 0..objects.len() {
-
-					let r = &mut objects[i];
-
-					r.draw(pixels.get_frame());
-
-					r.update();
+				let frame = pixels.get_frame();
+				for area in display.draw(&mut canvas) {
+					let area = area.intersect(&canvas.bounds());
+					for y in area.y..area.bottom() {
+						let row = y as usize * canvas.width;
+						for x in area.x as usize..area.right() as usize {
+							frame[(row + x) * 4..(row + x) * 4 + 4].copy_from_slice(&canvas.pixels[row + x].to_le_bytes());
+						}
+					}
 				}
 
 				if pixels.render().is_err() {
@@ -168,53 +147,3 @@ impl Serviceable for ViewPixels {
 	}
 }
 
-
-
-impl Renderable {
-
-    /// Update the internal state; bounce the box around the screen.
-    fn update(&mut self) {
-        if self.box_x <= 0 || self.box_x + BOX_SIZE > WIDTH as i16 {
-            self.velocity_x *= -1;
-        }
-        if self.box_y <= 0 || self.box_y + BOX_SIZE > HEIGHT as i16 {
-            self.velocity_y *= -1;
-        }
-
-        self.box_x += self.velocity_x;
-        self.box_y += self.velocity_y;
-    }
-
-    /// Draw the state to the frame buffer.
-    ///
-    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
-    fn draw(&self, frame: &mut [u8]) {
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let x = (i % WIDTH as usize) as i16;
-            let y = (i / WIDTH as usize) as i16;
-
-            if self.kind == 1 {
-
-	            let inside_the_box = x >= self.box_x
-	                && x < self.box_x + BOX_SIZE
-	                && y >= self.box_y
-	                && y < self.box_y + BOX_SIZE;
-
-	            let rgba = if inside_the_box {
-	                [0x5e, 0x48, 0xe8, 0xff]
-	            } else {
-	            	continue
-	            };
-
-	            pixel.copy_from_slice(&rgba);
-	        }
-            if self.kind == 0 {
-
-	            let rgba = [0x48, 0xb2, 0xe8, 0xff];
-
-	            pixel.copy_from_slice(&rgba);
-	        }
-
-        }
-    }
-}
@@ -0,0 +1,187 @@
+
+// drawing in 2d without a window - the canvas on its own, and the display keeping what apps drew and redrawing only
+// what changed
+
+use viewpixels::canvas::*;
+use viewpixels::display::*;
+
+const BLACK: u32 = 0xff000000;
+const WHITE: u32 = 0xffffffff;
+
+fn changed(canvas: &Canvas, from: u32) -> Vec<(i32, i32)> {
+    (0..canvas.pixels.len()).filter(|at| canvas.pixels[*at] != from)
+        .map(|at| ((at % canvas.width) as i32, (at / canvas.width) as i32)).collect()
+}
+
+fn inside(rect: Rect, (x, y): (i32, i32)) -> bool {
+    x >= rect.x && x < rect.right() && y >= rect.y && y < rect.bottom()
+}
+
+#[test]
+fn everything_keeps_to_the_clip() {
+    let mut canvas = Canvas::new(32, 24);
+    canvas.clear(BLACK);
+    let clip = Rect::new(8, 6, 10, 8);
+    canvas.set_clip(clip);
+    canvas.rect(Rect::new(-100, -100, 500, 500), WHITE);
+    canvas.line((-50.0, -50.0), (80.0, 60.0), 5.0, WHITE);
+    canvas.circle((16.0, 12.0), 40.0, WHITE);
+    canvas.ring((16.0, 12.0), 9.0, 3.0, WHITE);
+    canvas.image(&[WHITE; 4], 2, 2, Rect::new(-10, -10, 60, 60));
+    canvas.frame(Rect::new(0, 0, 32, 24), 4, WHITE);
+    canvas.blend(0, 0, WHITE, 1.0);
+    let changed = changed(&canvas, BLACK);
+    assert_eq!(changed.len(), 80);
+    assert!(changed.iter().all(|at| inside(clip, *at)));
+
+    // and to the canvas, whatever clip it is given
+    canvas.set_clip(Rect::new(-5, 20, 100, 100));
+    assert_eq!(canvas.clip(), Rect::new(0, 20, 32, 4));
+    canvas.circle((0.0, 24.0), 3.0, WHITE);
+}
+
+#[test]
+fn rectangles_far_out_do_not_overflow() {
+    let far = Rect::new(1_000_000_000, 0, 2_000_000_000, 10);
+    assert_eq!(far.right(), i32::MAX);
+    assert!(far.intersect(&Rect::new(0, 0, 32, 24)).is_empty());
+    assert_eq!(Rect::around(-3e9, 0.0, 3e9, 1.0), Rect::new(i32::MIN / 2, 0, i32::MAX, 1));
+    assert_eq!(Rect::new(i32::MIN, 0, 1, 1).union(&far), Rect::new(i32::MIN, 0, i32::MAX, 10));
+
+    let mut canvas = Canvas::new(32, 24);
+    canvas.clear(BLACK);
+    canvas.rect(far, WHITE);
+    canvas.frame(Rect::new(-2_000_000_000, -2_000_000_000, i32::MAX, i32::MAX), 3, WHITE);
+    assert!(changed(&canvas, BLACK).is_empty());
+    canvas.circle((16.0, 12.0), 3e9, WHITE);
+    assert_eq!(changed(&canvas, BLACK).len(), 32 * 24);
+
+    // and a request that far out is kept in bounds rather than taking the display down
+    let mut display = Display::new(64, 48);
+    display.handle("a.js", r#"{"op":"rect","id":"a","x":1e9,"y":0,"w":2e9,"h":1}"#).unwrap();
+    display.handle("a.js", r#"[{"op":"circle","id":"b","x":-1e30,"y":1e30,"radius":1e30},{"op":"line","id":"c","from":[-1e40,0],"to":[1e40,10]}]"#).unwrap();
+    display.draw(&mut Canvas::new(64, 48));
+}
+
+#[test]
+fn edges_are_smoothed() {
+    let mut canvas = Canvas::new(32, 32);
+    canvas.clear(BLACK);
+    canvas.circle((16.0, 16.0), 8.0, WHITE);
+    let at = |canvas: &Canvas, x: usize, y: usize| canvas.pixels[y * 32 + x] & 0xff;
+    assert_eq!(at(&canvas, 16, 16), 255);
+    assert_eq!(at(&canvas, 16, 27), 0);
+    // a pixel on the edge is only partly covered
+    let edge = (0..32).map(|x| at(&canvas, x, 10)).filter(|value| *value > 0 && *value < 255).count();
+    assert!(edge >= 2);
+
+    // a ring leaves its middle alone, and a line is as wide as it says across its middle
+    canvas.clear(BLACK);
+    canvas.ring((16.0, 16.0), 8.0, 2.0, WHITE);
+    assert_eq!(at(&canvas, 16, 16), 0);
+    assert_eq!(at(&canvas, 23, 16), 255);
+    canvas.clear(BLACK);
+    canvas.line((4.0, 16.0), (28.0, 16.0), 4.0, WHITE);
+    assert_eq!((13..19).map(|y| at(&canvas, 16, y)).collect::<Vec<u32>>(), vec![0, 255, 255, 255, 255, 0]);
+
+    // see through colors are mixed with what is under them
+    canvas.clear(BLACK);
+    canvas.rect(Rect::new(0, 0, 4, 4), rgba(255, 0, 0, 0.5));
+    assert_eq!(canvas.pixels[0], 0xff000080);
+    // and a see through stroke is laid on once, even at the corners
+    canvas.clear(BLACK);
+    canvas.frame(Rect::new(2, 2, 10, 10), 3, rgba(255, 255, 255, 0.5));
+    assert!([2, 3, 4, 11].iter().all(|x| canvas.pixels[2 * 32 + x] == 0xff808080));
+    assert_eq!(canvas.pixels[6 * 32 + 6], BLACK);
+}
+
+#[test]
+fn pictures_are_stretched_into_place() {
+    let mut canvas = Canvas::new(8, 4);
+    let source = [1, 2, 3, 4, 5, 6, 7, 8];
+    canvas.image(&source, 4, 2, Rect::new(0, 0, 8, 4));
+    for y in 0..4 {
+        for x in 0..8 {
+            assert_eq!(canvas.pixels[y * 8 + x], source[y / 2 * 4 + x / 2] | 0xff000000);
+        }
+    }
+    // half of it hanging off the side
+    canvas.image(&[9; 8], 4, 2, Rect::new(-4, 0, 8, 4));
+    assert_eq!(canvas.pixels[3], 0xff000009);
+    assert_eq!(canvas.pixels[4], 0xff000003);
+}
+
+#[test]
+fn only_what_changes_is_drawn_again() {
+    let mut display = Display::new(64, 48);
+    let mut canvas = Canvas::new(64, 48);
+    assert_eq!(display.draw(&mut canvas), vec![Rect::new(0, 0, 64, 48)]);
+    assert!(!display.is_dirty());
+    let background = canvas.pixels[0];
+
+    display.handle("a.js", r##"{"op":"rect","id":"box","x":4,"y":4,"w":8,"h":8,"fill":"#ffffff"}"##).unwrap();
+    assert_eq!(display.draw(&mut canvas), vec![Rect::new(4, 4, 8, 8)]);
+    assert_eq!(canvas.pixels[4 * 64 + 4], WHITE);
+
+    // a pixel outside what changed is left as it is, so scribble on one to see
+    canvas.pixels[47 * 64 + 63] = 1;
+    display.handle("a.js", r##"{"op":"rect","id":"box","x":8,"y":4,"w":8,"h":8,"fill":"#ff0000"}"##).unwrap();
+    assert_eq!(display.draw(&mut canvas), vec![Rect::new(4, 4, 12, 8)]);
+    assert_eq!(canvas.pixels[4 * 64 + 4], background);
+    assert_eq!(canvas.pixels[4 * 64 + 8], 0xff0000ff);
+    assert_eq!(canvas.pixels[47 * 64 + 63], 1);
+    assert_eq!(display.items().len(), 1);
+
+    // shapes apart from each other are drawn apart, and what is under a change is drawn again with it
+    display.handle("a.js", r#"[
+        {"op":"circle","id":"dot","x":40,"y":30,"radius":4,"stroke":[0,1,0],"width":2},
+        {"op":"line","id":"rule","from":[0,46],"to":[20,46]}
+    ]"#).unwrap();
+    let areas = display.draw(&mut canvas);
+    assert_eq!(areas.len(), 2);
+    assert!(areas.iter().all(|area| !area.overlaps(&Rect::new(8, 4, 8, 8))));
+    display.handle("a.js", r#"{"op":"delete","id":"dot"}"#).unwrap();
+    display.draw(&mut canvas);
+    assert_eq!(canvas.pixels[30 * 64 + 44], background);
+
+    // ids belong to whoever drew them
+    assert!(display.handle("b.js", r#"{"op":"delete","id":"box"}"#).is_err());
+    display.handle("b.js", r#"{"op":"rect","id":"box","x":30,"y":0,"w":2,"h":2}"#).unwrap();
+    assert_eq!(display.items().len(), 3);
+    display.handle("b.js", r#"{"op":"clear"}"#).unwrap();
+    assert_eq!(display.items().len(), 2);
+    assert!(display.get("a.js", "box").is_some());
+
+    // a bad request in an array stops the rest, but what came before it stands
+    assert!(display.handle("a.js", r#"[{"op":"delete","id":"rule"},{"op":"spiral","id":"s"},{"op":"delete","id":"box"}]"#).is_err());
+    assert_eq!(display.items().len(), 1);
+    assert!(display.handle("a.js", r#"{"op":"rect","id":"x","x":0,"y":0,"w":1,"h":1,"fill":"red"}"#).is_err());
+    assert!(display.handle("a.js", r#"{"op":"line","id":"x","from":[0,0]}"#).is_err());
+
+    // a new background draws everything again
+    display.handle("a.js", r##"{"op":"background","color":"#000000"}"##).unwrap();
+    assert_eq!(display.draw(&mut canvas), vec![Rect::new(0, 0, 64, 48)]);
+    assert_eq!(canvas.pixels[0], BLACK);
+}
+
+#[test]
+fn images_follow_the_frames_and_text_is_written() {
+    let mut display = Display::new(64, 48);
+    let mut canvas = Canvas::new(64, 48);
+    display.handle("a.js", r##"[{"op":"background","color":"#000000"},{"op":"image","id":"video","x":0,"y":0,"w":32,"h":18}]"##).unwrap();
+    display.draw(&mut canvas);
+    // nothing to show until a frame comes
+    assert_eq!(canvas.pixels[0], BLACK);
+    display.frame(&vec![0xff00ff00; FRAME_WIDTH * FRAME_HEIGHT]);
+    assert_eq!(display.draw(&mut canvas), vec![Rect::new(0, 0, 32, 18)]);
+    assert_eq!(canvas.pixels[0], 0xff00ff00);
+    assert_eq!(canvas.pixels[18 * 64], BLACK);
+
+    let (request, owner) = service::owner(r#"{"op":"text","id":"title","x":2,"y":24,"text":"Orbital @home","size":14} @a.js"#);
+    assert_eq!(owner, Some("a.js"));
+    display.handle(owner.unwrap(), request).unwrap();
+    let areas = display.draw(&mut canvas);
+    let written: Vec<(i32, i32)> = changed(&canvas, BLACK).into_iter().filter(|(_, y)| *y >= 18).collect();
+    assert!(written.len() > 40);
+    assert!(written.iter().all(|at| areas.iter().any(|area| inside(*area, *at))));
+}
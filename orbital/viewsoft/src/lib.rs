@@ -0,0 +1,270 @@
+
+//
+// ViewSoft: draws the shared scene on the cpu into a picture in memory, with no window or gpu needed
+//
+// it mirrors the scene from /scene/changes like any other renderer, and takes commands as text on /viewsoft:
+//
+//     snapshot [name]     draw the scene now and write it to name.png in the snapshots folder
+//     frames on           draw the scene whenever it changes and share it like camera frames, on /viewsoft/frames
+//     frames off          stop sharing frames
+//     status              say how things stand
+//
+// and says how things stand on /viewsoft/status after each one, as json, with the last snapshot written. the size of
+// snapshots, the background and which camera to look through are in public/viewsoft.manifest - frames are always
+// the size of camera frames.
+//
+// meshes are drawn flat or lambert shaded or unlit as their material says, and text is drawn with the font in
+// orbital/resources - see render.rs for what is and is not drawn. since it needs nothing but the cpu the same scene
+// always comes out the same, which is what lets tests compare it against pictures they expect.
+//
+
+use crossbeam::channel::*;
+use service::*;
+use service::interface::quote;
+use service::vision::timestamp;
+
+use std::convert::TryInto;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use scene::graph::Graph;
+
+pub mod picture;
+pub mod raster;
+pub mod render;
+pub mod shapes;
+pub mod text;
+
+use raster::Canvas;
+use render::{Renderer, View};
+
+pub const FRAME_WIDTH: usize = 1280;
+pub const FRAME_HEIGHT: usize = 720;
+
+pub const VIEWSOFT: &str = "/viewsoft";
+pub const STATUS: &str = "/viewsoft/status";
+pub const FRAMES: &str = "/viewsoft/frames";
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    // where snapshots go
+    pub folder: String,
+    pub width: usize,
+    pub height: usize,
+    pub background: u32,
+    // the node to look through, or the first camera in the scene
+    pub camera: Option<String>,
+    // the most frames a second that are shared
+    pub fps: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            folder: "../public/snapshots".to_string(),
+            width: FRAME_WIDTH,
+            height: FRAME_HEIGHT,
+            background: render::BACKGROUND,
+            camera: None,
+            fps: 30.0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_manifest(manifest: &Manifest) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        if let Some(folder) = manifest.get("folder") {
+            settings.folder = folder.to_string();
+        }
+        settings.width = manifest.get_or("width", settings.width);
+        settings.height = manifest.get_or("height", settings.height);
+        if settings.width == 0 || settings.height == 0 || settings.width > 8192 || settings.height > 8192 {
+            return Err(format!("snapshots cannot be {} by {}", settings.width, settings.height));
+        }
+        if let Some(background) = manifest.get("background") {
+            let bytes: Vec<u8> = background.split_whitespace().map(|byte| byte.parse::<u8>()).collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| format!("background is red green blue from 0 to 255, not {}", background))?;
+            settings.background = match bytes.as_slice() {
+                [r, g, b] => *r as u32 | (*g as u32) << 8 | (*b as u32) << 16 | 0xff << 24,
+                _ => return Err(format!("background is red green blue from 0 to 255, not {}", background)),
+            };
+        }
+        settings.camera = manifest.get("camera").map(|camera| camera.to_string()).filter(|camera| !camera.is_empty());
+        settings.fps = manifest.get_or("fps", settings.fps).max(1.0);
+        Ok(settings)
+    }
+}
+
+#[derive(Clone)]
+pub struct ViewSoft {
+    manifest: String,
+}
+
+impl ViewSoft {
+    pub fn new() -> Box<dyn Serviceable> {
+        Self::with_manifest("../public/viewsoft.manifest")
+    }
+    pub fn with_manifest(manifest: &str) -> Box<dyn Serviceable> {
+        Box::new(Self { manifest: manifest.to_string() })
+    }
+}
+
+impl Serviceable for ViewSoft {
+    fn name(&self) -> &str { "ViewSoft" }
+    fn stop(&self) {}
+    fn start(&self, _name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message>) {
+        let name = self.name().to_string();
+        let manifest = self.manifest.clone();
+        let _thread = std::thread::Builder::new().name(name).spawn(move || {
+
+            let manifest = Manifest::load(&manifest).unwrap_or_else(|err| {
+                println!("ViewSoft: no manifest at {} ({}), using defaults", manifest, err);
+                Manifest::default()
+            });
+            let settings = Settings::from_manifest(&manifest).unwrap_or_else(|err| {
+                log(&send, format!("ViewSoft: bad manifest, using defaults: {}", err));
+                Settings::default()
+            });
+
+            send.send(Message::Subscribe(sid, VIEWSOFT.to_string())).expect("error");
+            send.send(Message::Subscribe(sid, scene::CHANGES.to_string())).expect("error");
+
+            let period = Duration::from_secs_f32(1.0 / settings.fps);
+            let mut renderer = Renderer::new();
+            renderer.background = settings.background;
+            let mut view = Viewer {
+                send: send,
+                settings: settings,
+                renderer: renderer,
+                graph: Graph::new(),
+                frames: None,
+                dirty: false,
+                shared: 0,
+                last: None,
+            };
+            let mut next = Instant::now() + period;
+            loop {
+                match recv.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                    Ok(Message::Event(topic, data)) if topic == scene::CHANGES => {
+                        match view.graph.apply(&data) {
+                            Ok(()) => view.dirty = true,
+                            Err(err) => log(&view.send, format!("ViewSoft: cannot follow the scene: {}", err)),
+                        }
+                    },
+                    Ok(Message::Event(topic, data)) if topic == VIEWSOFT => {
+                        match view.command(data.trim()) {
+                            Ok(()) => view.publish(STATUS, view.status()),
+                            Err(err) => log(&view.send, format!("ViewSoft: {}: {}", data.trim(), err)),
+                        }
+                    },
+                    Ok(_) => { },
+                    Err(RecvTimeoutError::Timeout) => {
+                        next += period;
+                        if next < Instant::now() {
+                            next = Instant::now() + period;
+                        }
+                        if view.dirty && view.frames.is_some() && !view.share() {
+                            return;
+                        }
+                    },
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+    }
+}
+
+// a frame as it is shared
+type Frame = Arc<Mutex<Box<[u32;921600]>>>;
+
+struct Viewer {
+    send: Sender<Message>,
+    settings: Settings,
+    renderer: Renderer,
+    graph: Graph,
+    // where frames are drawn and shared from, while they are being shared
+    frames: Option<(Canvas, Frame)>,
+    // whether the scene has changed since the last frame
+    dirty: bool,
+    shared: usize,
+    // the last snapshot written
+    last: Option<PathBuf>,
+}
+
+impl Viewer {
+
+    fn command(&mut self, command: &str) -> Result<(), String> {
+        let mut words = command.split_whitespace();
+        match words.next().unwrap_or("") {
+            "snapshot" => {
+                let name = match words.next() {
+                    Some(name) => name.to_string(),
+                    None => format!("snapshot-{}", timestamp()),
+                };
+                if name.contains('/') || name.contains('\\') || name.starts_with('.') {
+                    return Err(format!("{} cannot be used as a name", name));
+                }
+                let path = PathBuf::from(&self.settings.folder).join(format!("{}.png", name));
+                let canvas = self.draw(self.settings.width, self.settings.height);
+                picture::save(&path, canvas.width, canvas.height, &canvas.pixels)?;
+                self.last = Some(path);
+            },
+            "frames" => match words.next() {
+                Some("on") => {
+                    if self.frames.is_none() {
+                        self.frames = Some((Canvas::new(FRAME_WIDTH, FRAME_HEIGHT), Arc::new(Mutex::new(frame()))));
+                        // whatever is there now is the first frame
+                        self.dirty = true;
+                    }
+                },
+                Some("off") => self.frames = None,
+                _ => return Err("frames is on or off".to_string()),
+            },
+            "status" => { },
+            _ => return Err("unknown command".to_string()),
+        }
+        Ok(())
+    }
+
+    fn draw(&self, width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        let view = View::find(&self.graph, self.settings.camera.as_deref());
+        self.renderer.render(&self.graph, &view, &mut canvas);
+        canvas
+    }
+
+    // draw a frame and share it, saying whether anyone is still there to share it with
+    fn share(&mut self) -> bool {
+        let view = View::find(&self.graph, self.settings.camera.as_deref());
+        let (canvas, sharedmemory) = self.frames.as_mut().expect("sharing frames");
+        self.renderer.render(&self.graph, &view, canvas);
+        sharedmemory.lock().unwrap().copy_from_slice(&canvas.pixels);
+        self.dirty = false;
+        self.shared += 1;
+        self.send.send(Message::Share(FRAMES.to_string(), sharedmemory.clone())).is_ok()
+    }
+
+    fn status(&self) -> String {
+        format!("{{\"frames\":{},\"shared\":{},\"nodes\":{},\"snapshot\":{}}}",
+            self.frames.is_some(),
+            self.shared,
+            self.graph.len(),
+            self.last.as_ref().map(|path| quote(&path.display().to_string())).unwrap_or_else(|| "null".to_string()))
+    }
+
+    fn publish(&self, topic: &str, data: String) {
+        let _ = self.send.send(Message::Event(topic.to_string(), data));
+    }
+}
+
+// built on the heap, since a frame is too big for a threads stack
+fn frame() -> Box<[u32;921600]> {
+    vec![0u32; FRAME_WIDTH * FRAME_HEIGHT].into_boxed_slice().try_into().unwrap()
+}
+
+fn log(send: &Sender<Message>, text: String) {
+    println!("{}", text);
+    let _ = send.send(Message::Event("/log".to_string(), text));
+}
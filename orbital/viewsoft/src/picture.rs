@@ -0,0 +1,44 @@
+
+// pictures on disk as png, in the same pixel layout as the canvas - used for snapshots and for the pictures tests
+// compare against
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Write pixels out as an rgb png
+pub fn save(path: &Path, width: usize, height: usize, pixels: &[u32]) -> Result<(), String> {
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(|err| format!("cannot make {}: {}", folder.display(), err))?;
+    }
+    let file = File::create(path).map_err(|err| format!("cannot write {}: {}", path.display(), err))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let bytes: Vec<u8> = pixels.iter().flat_map(|pixel| [*pixel as u8, (*pixel >> 8) as u8, (*pixel >> 16) as u8]).collect();
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(&bytes).map_err(|err| err.to_string())
+}
+
+/// Read a png back as its width, height and pixels
+pub fn load(path: &Path) -> Result<(usize, usize, Vec<u32>), String> {
+    let file = File::open(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| format!("cannot decode {}: {}", path.display(), err))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|err| format!("cannot decode {}: {}", path.display(), err))?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("palette was not expanded".to_string()),
+    };
+    let pixels = buffer[..width * height * channels].chunks(channels).map(|pixel| {
+        let (r, g, b) = if channels < 3 { (pixel[0], pixel[0], pixel[0]) } else { (pixel[0], pixel[1], pixel[2]) };
+        r as u32 | (g as u32) << 8 | (b as u32) << 16 | 0xff << 24
+    }).collect();
+    Ok((width, height, pixels))
+}
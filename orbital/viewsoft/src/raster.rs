@@ -0,0 +1,104 @@
+
+// a picture being drawn - colors a pixel at a time in the same layout as camera frames, red in the low byte, and how
+// far away whatever is drawn at each pixel is so that nearer things cover farther ones whatever order they come in
+
+/// A corner of a triangle on the screen - x and y in pixels, z from -1 near to 1 far, and its color from 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Corner {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub color: [f32; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+    depth: Vec<f32>,
+}
+
+impl Canvas {
+
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas { width: width, height: height, pixels: vec![0; width * height], depth: vec![f32::INFINITY; width * height] }
+    }
+
+    pub fn clear(&mut self, color: u32) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = color);
+        self.depth.iter_mut().for_each(|depth| *depth = f32::INFINITY);
+    }
+
+    /// Fill a triangle, blending the colors of its corners across it - only the pixels whose middles are inside are
+    /// drawn, so triangles that share an edge neither overlap nor leave a gap
+    pub fn triangle(&mut self, a: Corner, b: Corner, c: Corner) {
+        let area = edge(&a, &b, c.x, c.y);
+        if area.abs() < 1e-12 {
+            return;
+        }
+        // go round the same way whichever way the triangle winds
+        let (b, c, area) = if area < 0.0 { (c, b, -area) } else { (b, c, area) };
+        let left = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let top = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let right = (a.x.max(b.x).max(c.x).ceil().max(0.0) as usize).min(self.width);
+        let bottom = (a.y.max(b.y).max(c.y).ceil().max(0.0) as usize).min(self.height);
+        for y in top..bottom {
+            let py = y as f32 + 0.5;
+            for x in left..right {
+                let px = x as f32 + 0.5;
+                let wa = edge(&b, &c, px, py);
+                let wb = edge(&c, &a, px, py);
+                let wc = edge(&a, &b, px, py);
+                if !(inside(wa, &b, &c) && inside(wb, &c, &a) && inside(wc, &a, &b)) {
+                    continue;
+                }
+                let (wa, wb, wc) = (wa / area, wb / area, wc / area);
+                let z = a.z * wa + b.z * wb + c.z * wc;
+                let at = y * self.width + x;
+                if !(-1.0..=1.0).contains(&z) || z >= self.depth[at] {
+                    continue;
+                }
+                self.depth[at] = z;
+                let channel = |k: usize| a.color[k] * wa + b.color[k] * wb + c.color[k] * wc;
+                self.pixels[at] = pack([channel(0), channel(1), channel(2)], 1.0);
+            }
+        }
+    }
+
+    /// Lay a color over a pixel as much as the coverage says, if nothing nearer is there
+    pub fn blend(&mut self, x: i32, y: i32, z: f32, color: [f32; 3], coverage: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height || coverage <= 0.0 {
+            return;
+        }
+        let at = y as usize * self.width + x as usize;
+        if z >= self.depth[at] {
+            return;
+        }
+        let under = unpack(self.pixels[at]);
+        let coverage = coverage.min(1.0);
+        let mixed = [0, 1, 2].map(|k| under[k] + (color[k] - under[k]) * coverage);
+        self.pixels[at] = pack(mixed, 1.0);
+    }
+}
+
+// twice the area of a, b, p - positive when p is to the left going from a to b
+fn edge(a: &Corner, b: &Corner, px: f32, py: f32) -> f32 {
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+// a pixel exactly on an edge belongs to the triangle on one side of it only - the top and left edges keep theirs
+fn inside(weight: f32, a: &Corner, b: &Corner) -> bool {
+    weight > 0.0 || (weight == 0.0 && ((a.y == b.y && b.x < a.x) || b.y < a.y))
+}
+
+/// A color from 0 to 1 as a pixel
+pub fn pack(color: [f32; 3], alpha: f32) -> u32 {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+    byte(color[0]) | byte(color[1]) << 8 | byte(color[2]) << 16 | byte(alpha) << 24
+}
+
+/// A pixel as a color from 0 to 1
+pub fn unpack(pixel: u32) -> [f32; 3] {
+    [(pixel & 0xff) as f32 / 255.0, (pixel >> 8 & 0xff) as f32 / 255.0, (pixel >> 16 & 0xff) as f32 / 255.0]
+}
@@ -0,0 +1,251 @@
+
+// drawing the scene - every mesh is cut into triangles, lit, seen through the camera and filled in, and then text is
+// laid over where its node is. the camera is the first camera node being drawn, or one a little way back from the
+// middle of the scene looking down -z if there are none. lights are the scene's own, or a dim ambient one and a
+// light from above and in front if it has none. point lights do not fade with distance.
+//
+// a few things are kept simple: meshes loaded from files are drawn as cubes, everything is opaque, and text always
+// faces the camera at the size its node would be where it is.
+
+use scene::graph::Graph;
+use scene::math::*;
+use scene::node::*;
+
+use crate::raster::{Canvas, Corner};
+use crate::shapes::{self, Triangle, Vertex};
+use crate::text::Lettering;
+
+/// What is behind everything, a very dark blue
+pub const BACKGROUND: u32 = 0xff181010;
+
+pub struct Renderer {
+    pub background: u32,
+    cube: Vec<Triangle>,
+    plane: Vec<Triangle>,
+    sphere: Vec<Triangle>,
+    lettering: Lettering,
+}
+
+/// Where the scene is seen from
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    pub world: Mat4,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for View {
+    fn default() -> View {
+        View { world: compose([0.0, 2.0, 8.0], [0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0]), fov: 60.0, near: 0.1, far: 100.0 }
+    }
+}
+
+impl View {
+
+    /// The camera to see a scene through - a named node, or the first camera being drawn, or the default
+    pub fn find(graph: &Graph, camera: Option<&str>) -> View {
+        let node = match camera {
+            Some(id) => graph.get(id),
+            None => graph.walk().into_iter().find(|node| node.camera.is_some() && graph.shown(&node.id)),
+        };
+        match node.and_then(|node| node.camera.clone().map(|camera| (node.id.clone(), camera))) {
+            Some((id, camera)) => {
+                // a camera sees along its -z, not squashed or stretched by anything it hangs from
+                let world = graph.world(&id).unwrap_or(IDENTITY);
+                let x = normalize(transform_vector(&world, [1.0, 0.0, 0.0]));
+                let y = normalize(transform_vector(&world, [0.0, 1.0, 0.0]));
+                let z = normalize(cross(x, y));
+                let y = cross(z, x);
+                let at = translation(&world);
+                let world = [x[0], x[1], x[2], 0.0, y[0], y[1], y[2], 0.0, z[0], z[1], z[2], 0.0, at[0], at[1], at[2], 1.0];
+                View { world: world, fov: camera.fov, near: camera.near, far: camera.far }
+            },
+            None => View::default(),
+        }
+    }
+}
+
+enum Lamp {
+    Ambient([f32; 3]),
+    Point(Vec3, [f32; 3]),
+    // the way the light goes
+    Directional(Vec3, [f32; 3]),
+}
+
+impl Renderer {
+
+    pub fn new() -> Renderer {
+        Renderer {
+            background: BACKGROUND,
+            cube: shapes::cube(),
+            plane: shapes::plane(),
+            sphere: shapes::sphere(24, 12),
+            lettering: Lettering::new(),
+        }
+    }
+
+    /// Draw the scene as seen from a view
+    pub fn render(&self, graph: &Graph, view: &View, canvas: &mut Canvas) {
+        canvas.clear(self.background);
+        let eye = translation(&view.world);
+        let look = match invert(&view.world) { Some(look) => look, None => return };
+        let (width, height) = (canvas.width as f32, canvas.height as f32);
+        let aspect = width / height.max(1.0);
+        let focal = 1.0 / (view.fov.to_radians() / 2.0).tan();
+        let lamps = lamps(graph);
+
+        // from the camera to the screen, or nothing if it is behind the camera - what is clipped to the near plane
+        // may be a hair in front of it or behind it
+        let screen = |seen: Vec3| -> Option<(f32, f32, f32)> {
+            let depth = -seen[2];
+            if depth <= 0.0 {
+                return None;
+            }
+            let x = focal / aspect * seen[0] / depth;
+            let y = focal * seen[1] / depth;
+            // -1 at the near plane to 1 at the far one
+            let z = (view.far + view.near - 2.0 * view.far * view.near / depth) / (view.far - view.near);
+            Some(((x + 1.0) / 2.0 * width, (1.0 - y) / 2.0 * height, z))
+        };
+
+        for node in graph.walk() {
+            let (mesh, closed) = match &node.mesh {
+                Some(Mesh::Plane) => (&self.plane, false),
+                Some(Mesh::Sphere) => (&self.sphere, true),
+                Some(_) => (&self.cube, true),
+                None => continue,
+            };
+            if !graph.shown(&node.id) {
+                continue;
+            }
+            let world = match graph.world(&node.id) { Some(world) => world, None => continue };
+            // normals are turned by the inverse transpose so that stretching a mesh does not skew them
+            let normals = match invert(&world) { Some(inverse) => transpose(&inverse), None => continue };
+            let material = node.material.clone().unwrap_or(Material { color: WHITE, shading: Shading::Lambert });
+            let base = [material.color[0], material.color[1], material.color[2]];
+
+            for triangle in mesh {
+                let corners: Vec<Vertex> = triangle.iter().map(|vertex| Vertex {
+                    position: transform_point(&world, vertex.position),
+                    normal: normalize(transform_vector(&normals, vertex.normal)),
+                }).collect();
+                let face = normalize(cross(sub(corners[1].position, corners[0].position), sub(corners[2].position, corners[0].position)));
+                let middle = scale(add(add(corners[0].position, corners[1].position), corners[2].position), 1.0 / 3.0);
+                // the back of a closed mesh is hidden by its front, the back of a plane is seen the other way round
+                let facing = dot(face, sub(eye, middle)) > 0.0;
+                if !facing && closed {
+                    continue;
+                }
+                let flip = if facing { 1.0 } else { -1.0 };
+                let colors: Vec<[f32; 3]> = match material.shading {
+                    Shading::Unlit => vec![base; 3],
+                    Shading::Flat => vec![light(&lamps, middle, scale(face, flip), base); 3],
+                    Shading::Lambert => corners.iter().map(|corner| light(&lamps, corner.position, scale(corner.normal, flip), base)).collect(),
+                };
+
+                // cut off whatever is behind the near plane before it goes on the screen
+                let seen: Vec<(Vec3, [f32; 3])> = corners.iter().zip(colors).map(|(corner, color)| (transform_point(&look, corner.position), color)).collect();
+                let kept = clip(&seen, view.near);
+                let projected: Vec<Corner> = kept.iter().filter_map(|(at, color)| {
+                    screen(*at).map(|(x, y, z)| Corner { x: x, y: y, z: z, color: *color })
+                }).collect();
+                for index in 1..projected.len().saturating_sub(1) {
+                    canvas.triangle(projected[0], projected[index], projected[index + 1]);
+                }
+            }
+        }
+
+        // words go on last so that they can be laid over whatever they are in front of
+        for node in graph.walk() {
+            let text = match &node.text { Some(text) => text, None => continue };
+            if !graph.shown(&node.id) || text.text.is_empty() {
+                continue;
+            }
+            let world = match graph.world(&node.id) { Some(world) => world, None => continue };
+            let foot = transform_point(&look, translation(&world));
+            if -foot[2] < view.near {
+                continue;
+            }
+            // as tall as the node's y axis scaled by the size of the text, seen from however far away it is
+            let tall = length(transform_vector(&world, [0.0, text.size, 0.0]));
+            let tall = tall * focal * height / 2.0 / -foot[2];
+            if let Some(at) = screen(foot) {
+                if tall >= 2.0 {
+                    self.lettering.write(canvas, &text.text, at, tall, [text.color[0], text.color[1], text.color[2]]);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer::new()
+    }
+}
+
+fn lamps(graph: &Graph) -> Vec<Lamp> {
+    let mut lamps = Vec::new();
+    for node in graph.walk() {
+        let light = match &node.light { Some(light) => light, None => continue };
+        if !graph.shown(&node.id) {
+            continue;
+        }
+        let world = graph.world(&node.id).unwrap_or(IDENTITY);
+        let color = [light.color[0] * light.intensity, light.color[1] * light.intensity, light.color[2] * light.intensity];
+        lamps.push(match light.kind {
+            LightKind::Ambient => Lamp::Ambient(color),
+            LightKind::Point => Lamp::Point(translation(&world), color),
+            LightKind::Directional => Lamp::Directional(normalize(transform_vector(&world, [0.0, 0.0, -1.0])), color),
+        });
+    }
+    if lamps.is_empty() {
+        lamps.push(Lamp::Ambient([0.25, 0.25, 0.25]));
+        lamps.push(Lamp::Directional(normalize([-0.3, -1.0, -0.6]), [0.8, 0.8, 0.8]));
+    }
+    lamps
+}
+
+// how a surface looks lit by the lamps, at a point facing some way
+fn light(lamps: &[Lamp], at: Vec3, normal: Vec3, base: [f32; 3]) -> [f32; 3] {
+    let mut total = [0.0f32; 3];
+    for lamp in lamps {
+        let (color, amount) = match lamp {
+            Lamp::Ambient(color) => (color, 1.0),
+            Lamp::Point(position, color) => (color, dot(normal, normalize(sub(*position, at))).max(0.0)),
+            Lamp::Directional(direction, color) => (color, dot(normal, scale(*direction, -1.0)).max(0.0)),
+        };
+        for k in 0..3 {
+            total[k] += color[k] * amount;
+        }
+    }
+    [0, 1, 2].map(|k| (base[k] * total[k]).min(1.0))
+}
+
+// the part of a polygon seen from a camera that is in front of the near plane
+fn clip(corners: &[(Vec3, [f32; 3])], near: f32) -> Vec<(Vec3, [f32; 3])> {
+    let ahead = |at: &Vec3| -at[2] >= near;
+    let mut kept = Vec::with_capacity(corners.len() + 1);
+    for index in 0..corners.len() {
+        let (a, b) = (&corners[index], &corners[(index + 1) % corners.len()]);
+        if ahead(&a.0) {
+            kept.push(*a);
+        }
+        if ahead(&a.0) != ahead(&b.0) {
+            let t = (-near - a.0[2]) / (b.0[2] - a.0[2]);
+            kept.push((lerp(a.0, b.0, t), [0, 1, 2].map(|k| a.1[k] + (b.1[k] - a.1[k]) * t)));
+        }
+    }
+    kept
+}
+
+fn transpose(m: &Mat4) -> Mat4 {
+    let mut out = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[column * 4 + row] = m[row * 4 + column];
+        }
+    }
+    out
+}
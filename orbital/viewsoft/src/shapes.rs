@@ -0,0 +1,67 @@
+
+// the built in meshes as triangles, each a unit across and centred on the origin like scene::node says - every corner
+// has where it is and which way the surface faces there
+
+use scene::math::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+}
+
+pub type Triangle = [Vertex; 3];
+
+pub fn cube() -> Vec<Triangle> {
+    let mut triangles = Vec::with_capacity(12);
+    // each face by which way it faces and two directions across it, chosen so the corners go round anticlockwise
+    // seen from outside
+    let faces: [(Vec3, Vec3, Vec3); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+    for (normal, u, v) in faces.iter() {
+        let corner = |su: f32, sv: f32| Vertex {
+            position: add(scale(*normal, 0.5), add(scale(*u, su * 0.5), scale(*v, sv * 0.5))),
+            normal: *normal,
+        };
+        let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+        triangles.push([a, b, c]);
+        triangles.push([a, c, d]);
+    }
+    triangles
+}
+
+/// Flat on the ground facing up
+pub fn plane() -> Vec<Triangle> {
+    let corner = |x: f32, z: f32| Vertex { position: [x, 0.0, z], normal: [0.0, 1.0, 0.0] };
+    let (a, b, c, d) = (corner(-0.5, 0.5), corner(0.5, 0.5), corner(0.5, -0.5), corner(-0.5, -0.5));
+    vec![[a, b, c], [a, c, d]]
+}
+
+/// Bands of latitude from pole to pole, each cut into segments around
+pub fn sphere(segments: usize, bands: usize) -> Vec<Triangle> {
+    let point = |segment: usize, band: usize| {
+        let (theta, phi) = (segment as f32 / segments as f32 * std::f32::consts::TAU, band as f32 / bands as f32 * std::f32::consts::PI);
+        let normal = [phi.sin() * theta.cos(), phi.cos(), -phi.sin() * theta.sin()];
+        Vertex { position: scale(normal, 0.5), normal: normal }
+    };
+    let mut triangles = Vec::with_capacity(segments * bands * 2);
+    for band in 0..bands {
+        for segment in 0..segments {
+            let (a, b) = (point(segment, band), point(segment + 1, band));
+            let (c, d) = (point(segment + 1, band + 1), point(segment, band + 1));
+            if band != 0 {
+                triangles.push([a, d, b]);
+            }
+            if band != bands - 1 {
+                triangles.push([b, d, c]);
+            }
+        }
+    }
+    triangles
+}
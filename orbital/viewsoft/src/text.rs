@@ -0,0 +1,46 @@
+
+// words drawn with the font that ships in orbital/resources, shaded by how much of each pixel a letter covers
+
+use ab_glyph::{point, Font, FontRef, ScaleFont};
+
+use crate::raster::Canvas;
+
+pub struct Lettering {
+    font: FontRef<'static>,
+}
+
+impl Lettering {
+
+    pub fn new() -> Lettering {
+        let font = FontRef::try_from_slice(include_bytes!("../../resources/Ubuntu-R.ttf")).expect("the bundled font is broken");
+        Lettering { font: font }
+    }
+
+    /// Write a line with its baseline starting at x, y, with letters this many pixels tall, behind anything nearer than z
+    pub fn write(&self, canvas: &mut Canvas, text: &str, (x, y, z): (f32, f32, f32), height: f32, color: [f32; 3]) {
+        let scaled = self.font.as_scaled(height);
+        let mut caret = x;
+        let mut previous = None;
+        for letter in text.chars() {
+            let id = scaled.glyph_id(letter);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(height, point(caret, y));
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+            if let Some(outline) = self.font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    canvas.blend(bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32, z, color, coverage);
+                });
+            }
+        }
+    }
+}
+
+impl Default for Lettering {
+    fn default() -> Lettering {
+        Lettering::new()
+    }
+}
@@ -0,0 +1,197 @@
+
+// the scene drawn on the cpu, compared against pictures in tests/golden - if a change to drawing is meant to change
+// them, run with ORBITAL_BLESS=1 to write them again and look at what changed before committing. a picture that does
+// not match is written next to the test build as <name>.png to look at
+
+use crossbeam::channel::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use broker::*;
+use scene::Scene;
+use scene::graph::Graph;
+use scene::owners::Owners;
+use service::*;
+use viewsoft::*;
+use viewsoft::raster::*;
+use viewsoft::render::*;
+
+const PROBE: SID = 1;
+const SCENE_SID: SID = 2;
+const VIEWSOFT_SID: SID = 3;
+
+fn build(text: &str) -> Graph {
+    let mut graph = Graph::new();
+    scene::handle(&mut graph, &mut Owners::default(), "desktop", text).1.unwrap();
+    graph
+}
+
+fn draw(graph: &Graph, width: usize, height: usize) -> Canvas {
+    let mut canvas = Canvas::new(width, height);
+    Renderer::new().render(graph, &View::find(graph, None), &mut canvas);
+    canvas
+}
+
+fn pixel(canvas: &Canvas, x: usize, y: usize) -> u32 {
+    canvas.pixels[y * canvas.width + x]
+}
+
+// the same picture, give or take a little on a few pixels at the edges of things
+fn golden(name: &str, canvas: &Canvas) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+    if std::env::var("ORBITAL_BLESS").is_ok() {
+        picture::save(&path, canvas.width, canvas.height, &canvas.pixels).unwrap();
+        return;
+    }
+    let (width, height, pixels) = picture::load(&path).unwrap();
+    let actual = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+    assert_eq!((width, height), (canvas.width, canvas.height), "{} is a different size", name);
+    let off = pixels.iter().zip(canvas.pixels.iter()).filter(|(a, b)| {
+        [0, 8, 16].iter().any(|shift| (((*a >> shift) & 0xff) as i32 - ((*b >> shift) & 0xff) as i32).abs() > 8)
+    }).count();
+    if off * 200 > width * height {
+        picture::save(&actual, canvas.width, canvas.height, &canvas.pixels).unwrap();
+        panic!("{} has {} pixels that are not as expected, see {}", name, off, actual.display());
+    }
+}
+
+#[test]
+fn meshes_are_lit_and_shaded() {
+    let graph = build(r##"[
+        {"op":"create","id":"eye","position":[0,2,6],"rotation":[-0.1305262,0,0,0.9914449],"camera":{"fov":50}},
+        {"op":"create","id":"sun","rotation":[-0.3826834,0.3,0,0.9238795],"light":{"kind":"directional","intensity":0.8}},
+        {"op":"create","id":"sky","light":{"kind":"ambient","intensity":0.2}},
+        {"op":"create","id":"ground","scale":[8,1,8],"mesh":"plane","material":{"color":"#808080","shading":"flat"}},
+        {"op":"create","id":"ball","position":[-1.5,0.75,0],"scale":1.5,"mesh":"sphere","material":{"color":"#ff4020"}},
+        {"op":"create","id":"box","position":[0.5,0.5,-1],"rotation":[0,0.3826834,0,0.9238795],"mesh":"cube","material":{"color":"#20c040","shading":"flat"}},
+        {"op":"create","id":"lamp","position":[2,0.5,1],"mesh":"cube","scale":0.5,"material":{"color":"#3060ff","shading":"unlit"}}
+    ]"##);
+    let canvas = draw(&graph, 160, 120);
+    golden("meshes", &canvas);
+
+    // unlit is just its color, and lit things are darker on the side away from the light
+    assert!(canvas.pixels.iter().filter(|pixel| **pixel == pack([48.0 / 255.0, 96.0 / 255.0, 1.0], 1.0)).count() > 20);
+    let ball = |x: usize, y: usize| unpack(pixel(&canvas, x, y))[0];
+    assert!(ball(36, 45) > ball(50, 62));
+}
+
+#[test]
+fn text_faces_the_camera() {
+    let graph = build(r##"[
+        {"op":"create","id":"board","position":[0,0,-1],"scale":[6,1,3],"rotation":[0.7071068,0,0,0.7071068],"mesh":"plane","material":{"color":"#203040","shading":"unlit"}},
+        {"op":"create","id":"title","position":[-2.4,1.2,0],"text":{"text":"Orbital","size":1.5,"color":"#ffffff"}},
+        {"op":"create","id":"behind","position":[-2.4,0,-3],"text":"hidden"}
+    ]"##);
+    let canvas = draw(&graph, 200, 100);
+    golden("text", &canvas);
+    // the title is there, and the text behind the board is covered by it
+    let lettered = |x0: usize, x1: usize, y0: usize, y1: usize| (y0..y1).flat_map(|y| (x0..x1).map(move |x| (x, y)))
+        .filter(|(x, y)| pixel(&canvas, *x, *y) & 0xff > 0x80).count();
+    assert!(lettered(0, 200, 0, 100) > 50);
+    assert_eq!(lettered(0, 200, 60, 100), 0);
+}
+
+#[test]
+fn nearer_things_cover_farther_ones() {
+    // whichever order they are made in
+    for text in [
+        r#"[{"op":"create","id":"near","position":[0,2,4],"mesh":"cube","material":{"color":[1,0,0],"shading":"unlit"}},{"op":"create","id":"far","position":[0,2,0],"scale":3,"mesh":"cube","material":{"color":[0,0,1],"shading":"unlit"}}]"#,
+        r#"[{"op":"create","id":"far","position":[0,2,0],"scale":3,"mesh":"cube","material":{"color":[0,0,1],"shading":"unlit"}},{"op":"create","id":"near","position":[0,2,4],"mesh":"cube","material":{"color":[1,0,0],"shading":"unlit"}}]"#,
+    ] {
+        let canvas = draw(&build(text), 64, 48);
+        assert_eq!(pixel(&canvas, 32, 24), 0xff0000ff);
+        assert_eq!(pixel(&canvas, 23, 24), 0xffff0000);
+        assert_eq!(pixel(&canvas, 0, 0), BACKGROUND);
+    }
+
+    // hidden things are not drawn, and nor is what is behind the camera or cut off by the near plane
+    let canvas = draw(&build(r#"[
+        {"op":"create","id":"gone","position":[0,2,0],"mesh":"cube","visible":false},
+        {"op":"create","id":"behind","position":[0,2,10],"mesh":"sphere"}
+    ]"#), 64, 48);
+    assert!(canvas.pixels.iter().all(|pixel| *pixel == BACKGROUND));
+
+    // a camera inside a plane's edge sees the part in front of it
+    let canvas = draw(&build(r#"{"op":"create","id":"floor","position":[0,1,0],"scale":40,"mesh":"plane","material":{"shading":"unlit"}}"#), 64, 48);
+    assert_eq!(pixel(&canvas, 32, 47), 0xffffffff);
+    assert_eq!(pixel(&canvas, 32, 0), BACKGROUND);
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orbital-viewsoft-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn snapshots_and_frames_follow_the_scene() {
+    let dir = scratch("service");
+    let manifest = dir.join("viewsoft.manifest");
+    std::fs::write(&manifest,format!("folder = {}\nwidth = 64\nheight = 48\nbackground = 0 0 0\n",dir.display())).unwrap();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,SUBSCRIBERS.to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,STATUS.to_string())).unwrap();
+
+    for (sid,instance) in [(SCENE_SID,Scene::new()),(VIEWSOFT_SID,ViewSoft::with_manifest(manifest.to_str().unwrap()))] {
+        let (localsend,localrecv) = unbounded::<Message>();
+        brokersend.send(Message::Channel(sid,instance.name().to_string(),localsend)).unwrap();
+        instance.start(instance.name().to_string(),sid,brokersend.clone(),localrecv);
+    }
+    // both are listening once the view has subscribed to commands and to the scene's changes
+    let mut waiting = vec![format!("{} 1",VIEWSOFT),format!("{} 1",scene::CHANGES),format!("{} 1",scene::SCENE)];
+    while !waiting.is_empty() {
+        if let Message::Event(_,data) = proberecv.recv_timeout(Duration::from_secs(10)).expect("no services") {
+            waiting.retain(|wait| *wait != data);
+        }
+    }
+    let status = || loop {
+        match proberecv.recv_timeout(Duration::from_secs(10)).expect("no status") {
+            Message::Event(topic,data) if topic == STATUS => break data,
+            _ => { },
+        }
+    };
+
+    brokersend.send(Message::Event(scene::SCENE.to_string(),r#"{"op":"create","id":"box","position":[0,2,0],"scale":2,"mesh":"cube","material":{"shading":"unlit"}} @desktop"#.to_string())).unwrap();
+    // the view follows the scene as it hears of it, so ask until it has the box
+    let path = dir.join("box.png");
+    loop {
+        brokersend.send(Message::Event(VIEWSOFT.to_string(),"snapshot box".to_string())).unwrap();
+        let status = status();
+        assert!(status.contains(&format!("\"snapshot\":{}",service::interface::quote(&path.display().to_string()))),"{}",status);
+        if status.contains("\"nodes\":1") {
+            break;
+        }
+    }
+    let (width,height,pixels) = picture::load(&path).unwrap();
+    assert_eq!((width,height),(64,48));
+    assert_eq!(pixels[24 * 64 + 32],0xffffffff);
+    assert_eq!(pixels[0],0xff000000);
+
+    // names cannot go outside the folder
+    brokersend.send(Message::Event(VIEWSOFT.to_string(),"snapshot ../escape".to_string())).unwrap();
+    brokersend.send(Message::Event(VIEWSOFT.to_string(),"status".to_string())).unwrap();
+    assert!(status().contains("\"frames\":false"));
+    assert!(!dir.join("../escape.png").exists());
+
+    // frames are shared on a topic of their own whenever the scene changes, and are always the size of camera frames
+    brokersend.send(Message::Subscribe(PROBE,FRAMES.to_string())).unwrap();
+    brokersend.send(Message::Event(VIEWSOFT.to_string(),"frames on".to_string())).unwrap();
+    assert!(status().contains("\"frames\":true"));
+    let frame = || loop {
+        if let Message::Share(topic,sharedmemory) = proberecv.recv_timeout(Duration::from_secs(10)).expect("no frame") {
+            assert_eq!(topic,FRAMES);
+            break sharedmemory.lock().unwrap().to_vec();
+        }
+    };
+    let first = frame();
+    assert_eq!(first[360 * FRAME_WIDTH + 640],0xffffffff);
+    brokersend.send(Message::Event(scene::SCENE.to_string(),r#"{"op":"update","id":"box","material":{"color":[1,0,0],"shading":"unlit"}} @desktop"#.to_string())).unwrap();
+    let second = frame();
+    assert_eq!(second[360 * FRAME_WIDTH + 640],0xff0000ff);
+    brokersend.send(Message::Event(VIEWSOFT.to_string(),"frames off".to_string())).unwrap();
+}
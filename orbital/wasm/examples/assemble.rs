@@ -0,0 +1,12 @@
+// writes public/cubes.wasm from public/cubes.wat, which tests/apps.rs checks they agree on
+//
+//     cd orbital/wasm && cargo run --example assemble
+
+use std::path::Path;
+
+fn main() {
+    let public = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../public");
+    let built = wat::parse_file(public.join("cubes.wat")).unwrap();
+    std::fs::write(public.join("cubes.wasm"), &built).unwrap();
+    println!("wrote {} bytes to {}", built.len(), public.join("cubes.wasm").display());
+}
@@ -0,0 +1,198 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// A disk cache of compiled wasm modules so apps start without recompiling
+//
+// an entry is named for the app - its file name and a short hash of where the file is, so that two cubes.wasm in
+// different folders keep apart - and a sha256 of the wasmtime version, how the engine is set up and the module bytes, ie
+// cubes-<16 hex digits>-<64 hex digits>.cwasm. changing any of those gives a new name, and an entry that does not load
+// anyway (say it was cut short) is refused by wasmtime. either way the module is compiled again and the new entry
+// replaces any older ones for that app. a cache that cannot be read or written only costs time, never a failure.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use wasmtime::*;
+
+pub const CACHE: &str = "../public/cache/wasm";
+
+/// Load a module, text or binary, from the cache if a good entry is there and otherwise by compiling it and caching that
+/// - config has to be the one the engine was built from so that entries made under some other config are not picked up
+pub fn load(engine: &Engine, config: &Config, path: &str) -> Result<Module> {
+    load_in(Path::new(CACHE), engine, config, path)
+}
+
+fn load_in(cache: &Path, engine: &Engine, config: &Config, path: &str) -> Result<Module> {
+    let bytes = fs::read(path)?;
+    let app = app(path);
+    let entry = cache.join(entry(&app, &bytes, &format!("{:?}", config)));
+
+    if let Ok(compiled) = fs::read(&entry) {
+        // safe as long as the cache directory is only ever written by us; wasmtime checks the entry matches this engine
+        match unsafe { Module::deserialize(engine, &compiled) } {
+            Ok(module) => {
+                println!("Wasm: {} loaded from cache",path);
+                return Ok(module);
+            },
+            Err(err) => println!("Wasm: cache entry {} refused, recompiling: {}",entry.display(),err),
+        }
+    }
+
+    let module = Module::new(engine, &bytes)?;
+    if let Err(err) = store(cache, &app, &entry, &module) {
+        println!("Wasm: could not cache {}: {}",path,err);
+    }
+    Ok(module)
+}
+
+// what the entries for the app at path start with - its name, and a hash of the whole path so only it has those entries
+fn app(path: &str) -> String {
+    let path = Path::new(path);
+    let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("app");
+    let whole = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let hash: String = Sha256::digest(whole.to_string_lossy().as_bytes()).iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}", name, hash)
+}
+
+// the name of the entry for a module, which is all that says whether an entry is for this module on this engine
+fn entry(app: &str, bytes: &[u8], engine_key: &str) -> PathBuf {
+    let mut digest = Sha256::new();
+    for part in [wasmtime_jit::VERSION.as_bytes(), engine_key.as_bytes(), bytes].iter() {
+        // each part goes in with its length so that no two different sets of parts run together the same
+        digest.update((part.len() as u64).to_le_bytes());
+        digest.update(part);
+    }
+    let hex: String = digest.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    PathBuf::from(format!("{}-{}.cwasm", app, hex))
+}
+
+// write the new entry by way of a temporary file so a half written entry is never picked up, then drop stale ones
+fn store(cache: &Path, app: &str, entry: &Path, module: &Module) -> Result<()> {
+    fs::create_dir_all(cache)?;
+    let temporary = entry.with_extension("tmp");
+    fs::write(&temporary, module.serialize()?)?;
+    fs::rename(&temporary, entry)?;
+    for stale in fs::read_dir(cache)? {
+        let stale = stale?.path();
+        let name = stale.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        let ours = name.starts_with(&format!("{}-", app)) && name.ends_with(".cwasm") && name.len() == app.len() + 1 + 64 + 6;
+        if stale != entry && ours {
+            let _ = fs::remove_file(&stale);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP: &str = "(module (func (export \"answer\") (result i32) i32.const 42))";
+
+    // a cache of our own with one app in it
+    fn setup(name: &str) -> (PathBuf, String, Engine, Config) {
+        let dir = std::env::temp_dir().join(format!("orbital-wasm-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("answer.wat");
+        fs::write(&path, APP).unwrap();
+        let config = Config::new();
+        let engine = Engine::new(&config).unwrap();
+        (dir.join("cache"), path.to_string_lossy().to_string(), engine, config)
+    }
+
+    fn entries(cache: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(cache).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    fn answers(module: &Module) -> bool {
+        module.exports().any(|export| export.name() == "answer")
+    }
+
+    #[test]
+    fn entries_are_named_for_everything_that_matters() {
+        let name = entry("app", b"module", "config");
+        let name = name.to_string_lossy();
+        assert!(name.starts_with("app-") && name.ends_with(".cwasm") && name.len() == 4 + 64 + 6, "{}", name);
+        assert_eq!(entry("app", b"module", "config").to_string_lossy(), name);
+        assert_ne!(entry("app", b"module!", "config").to_string_lossy(), name);
+        assert_ne!(entry("app", b"module", "other config").to_string_lossy(), name);
+        // the parts cannot run into each other
+        assert_ne!(entry("app", b"gmodule", "confi"), entry("app", b"module", "config"));
+    }
+
+    #[test]
+    fn a_corrupt_entry_is_rebuilt() {
+        let (cache, path, engine, config) = setup("corrupt");
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        let names = entries(&cache);
+        assert_eq!(names.len(), 1);
+        let entry = cache.join(&names[0]);
+        let good = fs::read(&entry).unwrap();
+        // an entry that is good again is one wasmtime takes
+        let rebuilt = |entry: &Path| unsafe { Module::deserialize(&engine, &fs::read(entry).unwrap()) }.is_ok();
+
+        // cut short
+        fs::write(&entry, &good[..good.len() / 2]).unwrap();
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        assert!(rebuilt(&entry));
+
+        // or just rubbish
+        fs::write(&entry, b"not a module").unwrap();
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        assert!(rebuilt(&entry));
+        assert_eq!(entries(&cache), names);
+        let _ = fs::remove_dir_all(cache.parent().unwrap());
+    }
+
+    #[test]
+    fn stale_entries_are_replaced() {
+        let (cache, path, engine, config) = setup("stale");
+        // left by an older module or another wasmtime, beside another app's entry and something that is not an entry at all
+        fs::create_dir_all(&cache).unwrap();
+        let stale = format!("{}-{}.cwasm", app(&path), "0".repeat(64));
+        let other = format!("other-{}.cwasm", "0".repeat(64));
+        for name in [&stale, &other, &"answer-notes.txt".to_string()].iter() {
+            fs::write(cache.join(name), b"old").unwrap();
+        }
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        let names = entries(&cache);
+        assert!(!names.contains(&stale));
+        assert!(names.contains(&other));
+        assert!(names.contains(&"answer-notes.txt".to_string()));
+        assert_eq!(names.len(), 3);
+
+        // and a changed module gets a new entry in place of the old one
+        fs::write(&path, APP.replace("42", "43")).unwrap();
+        let before = entries(&cache);
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        let after = entries(&cache);
+        assert_eq!(after.len(), 3);
+        assert_ne!(after, before);
+        let _ = fs::remove_dir_all(cache.parent().unwrap());
+    }
+
+    #[test]
+    fn apps_of_the_same_name_keep_their_own_entries() {
+        let (cache, path, engine, config) = setup("same-name");
+        let elsewhere = Path::new(&path).parent().unwrap().join("elsewhere");
+        fs::create_dir_all(&elsewhere).unwrap();
+        let other = elsewhere.join("answer.wat").to_string_lossy().to_string();
+        fs::write(&other, APP.replace("42", "43")).unwrap();
+        assert_ne!(app(&path), app(&other));
+
+        // loading one after the other leaves both cached, so neither is compiled again
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        assert!(answers(&load_in(&cache, &engine, &config, &other).unwrap()));
+        assert!(answers(&load_in(&cache, &engine, &config, &path).unwrap()));
+        let names = entries(&cache);
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().any(|name| name.starts_with(&app(&path))) && names.iter().any(|name| name.starts_with(&app(&other))));
+        let _ = fs::remove_dir_all(cache.parent().unwrap());
+    }
+}
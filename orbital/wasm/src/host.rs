@@ -0,0 +1,242 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// The host abi that wasm guests are linked against
+//
+// guests import these from the "orbital" module:
+//
+//     publish(topic_ptr: i32, topic_len: i32, payload_ptr: i32, payload_len: i32)
+//     subscribe(topic_ptr: i32, topic_len: i32)
+//     call(target_ptr: i32, target_len: i32, args_ptr: i32, args_len: i32) -> id  - see interface.rs
+//     result(ptr: i32, len: i32)                                                  - see interface.rs
+//
+// guests export these:
+//
+//     memory                                   - their linear memory
+//     alloc(len: i32) -> i32                   - space for the host to write an inbound message into
+//     dealloc(ptr: i32, len: i32)              - optional; handed back once the host is done with it
+//     on_message(topic_ptr: i32, topic_len: i32, payload_ptr: i32, payload_len: i32)
+//     run()                                    - optional; called once after instantiation
+//     tick(elapsed_ms: i32)                    - optional; called every so often for time based work
+//     on_reply(id: i32, ok: i32, ptr: i32, len: i32)   - optional; answers to calls, see interface.rs
+//     <function>(args_ptr: i32, args_len: i32) - one for each function the guest serves, see interface.rs
+//
+// publishing on a topic that services speak on, such as /scene/changes or /log, traps the guest - see service::RESERVED.
+// so does subscribing to /scene/changes, which has all of the scene; apps ask the scene what they may see instead.
+//
+// strings are utf8 and never nul terminated. the host never trusts a guest pointer; every read and write is bounds checked
+// against the guests memory and a bad one traps the guest rather than touching anything else.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use crossbeam::channel::*;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use wasmtime::*;
+
+use service::*;
+
+use crate::interface::Calls;
+
+// the largest topic or payload a guest may hand across in one go
+pub const MAX_MESSAGE: usize = 1024 * 1024;
+
+pub const MODULE: &str = "orbital";
+
+// everything the host provides, as name, params, results
+const FUNCTIONS: &[(&str, &[ValType], &[ValType])] = &[
+    ("publish", &[ValType::I32, ValType::I32, ValType::I32, ValType::I32], &[]),
+    ("subscribe", &[ValType::I32, ValType::I32], &[]),
+    ("call", &[ValType::I32, ValType::I32, ValType::I32, ValType::I32], &[ValType::I32]),
+    ("result", &[ValType::I32, ValType::I32], &[]),
+];
+
+// services that act for apps by who asked - what a guest publishes on these is always tagged with the app by the host,
+// whatever the guest wrote, so that one app cannot pass itself off as another or as the desktop
+const TAGGED: &[&str] = &["/display", "/audio", "/scene", "/scene/query", "/pixels"];
+
+// what only the desktop and renderers may hear - the whole scene, whatever an app may see of it
+const PRIVATE: &[&str] = &["/scene/changes"];
+
+// wasi lives under its own module names; it is checked by wasmtime-wasi itself when it is linked
+const WASI_MODULES: &[&str] = &["wasi_snapshot_preview1", "wasi_unstable"];
+
+///
+/// ImportError: every import a module asked for that the host cannot give it
+///
+
+#[derive(Debug)]
+pub struct ImportError(pub Vec<String>);
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "module has {} bad import(s):", self.0.len())?;
+        for problem in &self.0 {
+            write!(f, "\n    {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ImportError {}
+
+/// Check what a module imports against the host abi before trying to instantiate it - wasi imports only pass if wasi is on
+pub fn check(module: &Module, wasi: bool) -> Result<(), ImportError> {
+    let mut problems = Vec::new();
+    for import in module.imports() {
+        let name = import.name().unwrap_or("");
+        let what = format!("{}.{}", import.module(), name);
+        if WASI_MODULES.contains(&import.module()) {
+            if !wasi {
+                problems.push(format!("{}: wasi is not enabled for this app (set wasi = true in its manifest)", what));
+            }
+            continue;
+        }
+        if import.module() != MODULE {
+            problems.push(format!("{}: unknown module '{}', host functions are imported from '{}'", what, import.module(), MODULE));
+            continue;
+        }
+        let (params, results) = match FUNCTIONS.iter().find(|(function,_,_)| *function == name) {
+            Some((_,params,results)) => (params, results),
+            None => {
+                let names: Vec<&str> = FUNCTIONS.iter().map(|(function,_,_)| *function).collect();
+                problems.push(format!("{}: not provided by the host, which has {}", what, names.join(", ")));
+                continue;
+            }
+        };
+        match import.ty() {
+            ExternType::Func(ty) => {
+                if !ty.params().eq(params.iter().cloned()) || !ty.results().eq(results.iter().cloned()) {
+                    problems.push(format!("{}: host has {} but module wants {}",
+                        what, signature(params.iter().cloned(), results.iter().cloned()), signature(ty.params(), ty.results())));
+                }
+            },
+            _ => problems.push(format!("{}: host provides a function here", what)),
+        }
+    }
+    if problems.is_empty() { Ok(()) } else { Err(ImportError(problems)) }
+}
+
+/// What a guest publishes as it goes to the broker - on the topics of services that act for apps any tag the guest put
+/// on the end is dropped for the host's own
+pub fn tag(topic: &str, payload: &str, owner: &str) -> String {
+    if TAGGED.contains(&topic) {
+        format!("{} @{}", service::owner(payload).0, owner)
+    } else {
+        payload.to_string()
+    }
+}
+
+fn signature(params: impl Iterator<Item=ValType>, results: impl Iterator<Item=ValType>) -> String {
+    let params: Vec<String> = params.map(|ty| ty.to_string()).collect();
+    let results: Vec<String> = results.map(|ty| ty.to_string()).collect();
+    format!("({}) -> ({})", params.join(", "), results.join(", "))
+}
+
+/// Define the host side of the abi in a linker; the guest is identified to the broker as sid, and to services that act
+/// for apps as owner
+pub fn link(linker: &mut Linker, sid: SID, owner: &str, send: Sender<Message>, calls: Rc<RefCell<Calls>>) -> Result<(), Trap> {
+
+    let send2 = send.clone();
+    let owner = owner.to_string();
+    linker.func(MODULE, "publish", move |caller: Caller<'_>, topic_ptr: i32, topic_len: i32, payload_ptr: i32, payload_len: i32| {
+        let memory = guest_memory(&caller)?;
+        let topic = read_string(&memory, topic_ptr, topic_len)?;
+        let payload = read_string(&memory, payload_ptr, payload_len)?;
+        if reserved(&topic) {
+            return Err(Trap::new(format!("guests may not publish on {}", topic)));
+        }
+        let _ = send2.send(Message::Event(topic.clone(),tag(&topic,&payload,&owner)));
+        Ok(())
+    }).map_err(|err| Trap::new(err.to_string()))?;
+
+    let send2 = send.clone();
+    linker.func(MODULE, "subscribe", move |caller: Caller<'_>, topic_ptr: i32, topic_len: i32| {
+        let memory = guest_memory(&caller)?;
+        let topic = read_string(&memory, topic_ptr, topic_len)?;
+        if PRIVATE.contains(&topic.as_str()) {
+            return Err(Trap::new(format!("guests may not subscribe to {}", topic)));
+        }
+        let _ = send2.send(Message::Subscribe(sid,topic));
+        Ok(())
+    }).map_err(|err| Trap::new(err.to_string()))?;
+
+    let send2 = send.clone();
+    let calls2 = calls.clone();
+    linker.func(MODULE, "call", move |caller: Caller<'_>, target_ptr: i32, target_len: i32, args_ptr: i32, args_len: i32| {
+        let memory = guest_memory(&caller)?;
+        let target = read_string(&memory, target_ptr, target_len)?;
+        let args = read_bytes(&memory, args_ptr, args_len)?;
+        calls2.borrow_mut().call(&send2, &target, &args).map_err(Trap::new)
+    }).map_err(|err| Trap::new(err.to_string()))?;
+
+    linker.func(MODULE, "result", move |caller: Caller<'_>, ptr: i32, len: i32| {
+        let memory = guest_memory(&caller)?;
+        calls.borrow_mut().result = Some(read_bytes(&memory, ptr, len)?);
+        Ok(())
+    }).map_err(|err| Trap::new(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Hand an inbound message to the guests on_message export, if it has one
+pub fn deliver(instance: &Instance, topic: &str, payload: &str) -> Result<(), Trap> {
+    let on_message = match instance.get_typed_func::<(i32,i32,i32,i32), ()>("on_message") {
+        Ok(on_message) => on_message,
+        Err(_) => return Ok(()),
+    };
+
+    // topic and payload go into one guest allocation, back to back
+    let bytes = [topic.as_bytes(), payload.as_bytes()].concat();
+    let ptr = give(instance, &bytes)?;
+    on_message.call((ptr, topic.len() as i32, ptr + topic.len() as i32, payload.len() as i32))?;
+    release(instance, ptr, bytes.len() as i32)
+}
+
+/// Copy bytes into space the guest allocates for them, returning where they went
+pub fn give(instance: &Instance, bytes: &[u8]) -> Result<i32, Trap> {
+    let memory = instance.get_memory("memory").ok_or_else(|| Trap::new("guest does not export its memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>("alloc").map_err(|_| Trap::new("guest does not export alloc(len) -> ptr"))?;
+    if bytes.len() > 2 * MAX_MESSAGE {
+        return Err(Trap::new("message too large for guest"));
+    }
+    let ptr = alloc.call(bytes.len() as i32)?;
+    write_bytes(&memory, ptr, bytes)?;
+    Ok(ptr)
+}
+
+/// Hand back space from give() once the guest is done with it
+pub fn release(instance: &Instance, ptr: i32, len: i32) -> Result<(), Trap> {
+    if let Ok(dealloc) = instance.get_typed_func::<(i32,i32), ()>("dealloc") {
+        dealloc.call((ptr, len))?;
+    }
+    Ok(())
+}
+
+fn guest_memory(caller: &Caller<'_>) -> Result<Memory, Trap> {
+    caller.get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| Trap::new("guest does not export its memory"))
+}
+
+fn read_string(memory: &Memory, ptr: i32, len: i32) -> Result<String, Trap> {
+    String::from_utf8(read_bytes(memory, ptr, len)?).map_err(|_| Trap::new("guest string is not utf8"))
+}
+
+fn read_bytes(memory: &Memory, ptr: i32, len: i32) -> Result<Vec<u8>, Trap> {
+    if ptr < 0 || len < 0 || len as usize > MAX_MESSAGE {
+        return Err(Trap::new(format!("bad guest buffer at {} length {}", ptr, len)));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    memory.read(ptr as usize, &mut bytes).map_err(|_| Trap::new(format!("guest buffer at {} length {} is out of bounds", ptr, len)))?;
+    Ok(bytes)
+}
+
+fn write_bytes(memory: &Memory, ptr: i32, bytes: &[u8]) -> Result<(), Trap> {
+    if ptr < 0 {
+        return Err(Trap::new(format!("bad guest pointer {}", ptr)));
+    }
+    memory.write(ptr as usize, bytes).map_err(|_| Trap::new(format!("guest buffer at {} length {} is out of bounds", ptr, bytes.len())))
+}
@@ -0,0 +1,337 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// Typed calls into and out of wasm guests - see service::interface for how they are declared and how they travel
+//
+// inside the guest a value is laid out packed and little endian, with no alignment:
+//
+//     bool u8                  1 byte
+//     s32 f32                  4 bytes
+//     s64 f64                  8 bytes
+//     string                   u32 byte count then the utf8 bytes
+//     list<T>                  u32 element count then the elements
+//     record                   its fields in declared order
+//
+// arguments are laid out one after another in parameter order.
+//
+// serving: for each export.<function> in its manifest a guest exports <function>(args_ptr, args_len); before returning it
+// hands back its result, if the function has one, with the host import result(ptr, len).
+//
+// calling: a guest calls a declared import.<app>.<function> with the host import call(target_ptr, target_len, args_ptr,
+// args_len) -> id where target is "<app>.<function>". the answer comes later through the guest export
+// on_reply(id, ok, ptr, len): the laid out result if ok is 1, or a utf8 error if ok is 0. ids are a u32 that counts up
+// from 1 and wraps around past the largest, skipping any still waiting; the abi carries them as the same 32 bits in an
+// i32, so past 2^31 a guest sees them go negative.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use crossbeam::channel::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use wasmtime::*;
+
+use service::*;
+use service::interface::*;
+
+use crate::host;
+
+///
+/// Calls: the interface of one guest along with its calls that are still waiting on an answer
+///
+
+pub struct Calls {
+    pub app: String,
+    pub interface: Interface,
+    reply: String,
+    next: u32,
+    pending: HashMap<u32,Function>,
+    // set by the guest through result() while it is serving a call
+    pub result: Option<Vec<u8>>,
+}
+
+impl Calls {
+
+    pub fn new(app: &str, interface: Interface, sid: SID) -> Calls {
+        Calls {
+            app: app.to_string(),
+            interface: interface,
+            reply: format!("/reply/{}",sid),
+            next: 1,
+            pending: HashMap::new(),
+            result: None,
+        }
+    }
+
+    /// Topics to listen on - one for each function served, and one for answers to calls made
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.interface.exports.iter().map(|function| function.topic(&self.app)).collect();
+        if !self.interface.imports.is_empty() {
+            topics.push(self.reply.clone());
+        }
+        topics
+    }
+
+    /// Publish a call on behalf of the guest; target is "<app>.<function>" and args are laid out as above
+    pub fn call(&mut self, send: &Sender<Message>, target: &str, args: &[u8]) -> Result<i32, String> {
+        let (app,name) = target.split_once('.').ok_or_else(|| format!("call target '{}' should be <app>.<function>", target))?;
+        let function = self.interface.import(app, name).ok_or_else(|| format!("'{}' is not declared as an import in the manifest", target))?.clone();
+        let mut at = 0;
+        let mut values = Vec::new();
+        for (_,ty) in &function.params {
+            values.push(decode(args, &mut at, ty)?);
+        }
+        if at != args.len() {
+            return Err(format!("{} arguments have {} bytes left over", target, args.len() - at));
+        }
+        let id = self.id();
+        let call = Call { id: id as u64, reply: self.reply.clone(), args: values };
+        let _ = send.send(Message::Event(function.topic(app), call.to_json()));
+        self.pending.insert(id, function);
+        Ok(abi(id))
+    }
+
+    // the next id that is not 0 and is not waiting on an answer
+    fn id(&mut self) -> u32 {
+        loop {
+            let id = self.next;
+            self.next = self.next.wrapping_add(1);
+            if id != 0 && !self.pending.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+}
+
+// a call id as the guest sees it - the same 32 bits
+fn abi(id: u32) -> i32 {
+    i32::from_le_bytes(id.to_le_bytes())
+}
+
+/// If a message is a call to something this guest serves, run it and answer; returns false for ordinary traffic
+pub fn serve(instance: &Instance, calls: &RefCell<Calls>, send: &Sender<Message>, topic: &str, data: &str) -> Result<bool, Trap> {
+    let function = {
+        let calls = calls.borrow();
+        match calls.interface.exports.iter().find(|function| function.topic(&calls.app) == topic) {
+            Some(function) => function.clone(),
+            None => return Ok(false),
+        }
+    };
+    let call = match Call::parse(data, &function) {
+        Ok(call) => call,
+        Err((Some((id,reply)),err)) => {
+            let _ = send.send(Message::Event(reply, Reply { id: id, result: Err(err) }.to_json()));
+            return Ok(true);
+        },
+        Err((None,err)) => {
+            println!("Wasm: unreadable call to {}: {}", function.name, err);
+            return Ok(true);
+        },
+    };
+
+    // the guest has to be answered for no matter what happens so a trap becomes an error reply as well as a trap
+    let outcome = run(instance, calls, &function, &call.args);
+    let result = match &outcome {
+        Ok(result) => Ok(result.clone()),
+        Err(trap) => Err(format!("{} failed: {}", function.name, trap)),
+    };
+    let _ = send.send(Message::Event(call.reply.clone(), Reply { id: call.id, result: result }.to_json()));
+    outcome.map(|_| true)
+}
+
+fn run(instance: &Instance, calls: &RefCell<Calls>, function: &Function, args: &[Value]) -> Result<Option<Value>, Trap> {
+    let export = instance.get_typed_func::<(i32,i32), ()>(&function.name)
+        .map_err(|_| Trap::new(format!("guest declares {} but does not export {}(ptr, len)", function, function.name)))?;
+    let mut bytes = Vec::new();
+    for arg in args {
+        encode(arg, &mut bytes);
+    }
+    calls.borrow_mut().result = None;
+    let ptr = host::give(instance, &bytes)?;
+    export.call((ptr, bytes.len() as i32))?;
+    host::release(instance, ptr, bytes.len() as i32)?;
+    let result = calls.borrow_mut().result.take();
+    match (&function.result, result) {
+        (None, _) => Ok(None),
+        (Some(ty), Some(result)) => {
+            let mut at = 0;
+            let value = decode(&result, &mut at, ty).map_err(|err| Trap::new(format!("{} result: {}", function.name, err)))?;
+            Ok(Some(value))
+        },
+        (Some(_), None) => Err(Trap::new(format!("{} returned without calling result()", function.name))),
+    }
+}
+
+/// If a message answers a call this guest made, hand it to on_reply; returns false for ordinary traffic
+pub fn receive(instance: &Instance, calls: &RefCell<Calls>, topic: &str, data: &str) -> Result<bool, Trap> {
+    let (id, function) = {
+        let mut calls = calls.borrow_mut();
+        if topic != calls.reply {
+            return Ok(false);
+        }
+        match Reply::id(data).and_then(|id| u32::try_from(id).ok()).and_then(|id| calls.pending.remove(&id).map(|function| (id, function))) {
+            Some(found) => found,
+            None => return Ok(true),
+        }
+    };
+    let (ok, bytes) = match Reply::parse(data, &function) {
+        Ok(Reply { result: Ok(value), .. }) => {
+            let mut bytes = Vec::new();
+            if let Some(value) = value {
+                encode(&value, &mut bytes);
+            }
+            (1, bytes)
+        },
+        Ok(Reply { result: Err(err), .. }) => (0, err.into_bytes()),
+        Err(err) => (0, err.into_bytes()),
+    };
+    let on_reply = match instance.get_typed_func::<(i32,i32,i32,i32), ()>("on_reply") {
+        Ok(on_reply) => on_reply,
+        Err(_) => return Ok(true),
+    };
+    let ptr = host::give(instance, &bytes)?;
+    on_reply.call((abi(id), ok, ptr, bytes.len() as i32))?;
+    host::release(instance, ptr, bytes.len() as i32)?;
+    Ok(true)
+}
+
+//////////////////////////////////////////////////////////////////////////
+// the guest side layout
+//////////////////////////////////////////////////////////////////////////
+
+pub fn encode(value: &Value, bytes: &mut Vec<u8>) {
+    match value {
+        Value::Bool(v) => bytes.push(*v as u8),
+        Value::U8(v) => bytes.push(*v),
+        Value::S32(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+        Value::S64(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+        Value::F32(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+        Value::F64(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+        Value::String(v) => {
+            bytes.extend_from_slice(&(v.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(v.as_bytes());
+        },
+        Value::List(values) => {
+            bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            for value in values {
+                encode(value, bytes);
+            }
+        },
+        Value::Record(fields) => {
+            for (_,value) in fields {
+                encode(value, bytes);
+            }
+        },
+    }
+}
+
+fn take<'a>(bytes: &'a [u8], at: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    let end = at.checked_add(len).filter(|end| *end <= bytes.len()).ok_or_else(|| format!("ran out of bytes at {}", at))?;
+    let taken = &bytes[*at..end];
+    *at = end;
+    Ok(taken)
+}
+
+pub fn decode(bytes: &[u8], at: &mut usize, ty: &Type) -> Result<Value, String> {
+    Ok(match ty {
+        Type::Bool => Value::Bool(take(bytes, at, 1)?[0] != 0),
+        Type::U8 => Value::U8(take(bytes, at, 1)?[0]),
+        Type::S32 => Value::S32(i32::from_le_bytes(take(bytes, at, 4)?.try_into().unwrap())),
+        Type::S64 => Value::S64(i64::from_le_bytes(take(bytes, at, 8)?.try_into().unwrap())),
+        Type::F32 => Value::F32(f32::from_le_bytes(take(bytes, at, 4)?.try_into().unwrap())),
+        Type::F64 => Value::F64(f64::from_le_bytes(take(bytes, at, 8)?.try_into().unwrap())),
+        Type::String => {
+            let len = u32::from_le_bytes(take(bytes, at, 4)?.try_into().unwrap()) as usize;
+            Value::String(String::from_utf8(take(bytes, at, len)?.to_vec()).map_err(|_| "string is not utf8".to_string())?)
+        },
+        Type::List(of) => {
+            let count = u32::from_le_bytes(take(bytes, at, 4)?.try_into().unwrap()) as usize;
+            // every element takes at least a byte, so a count larger than what is left is a lie
+            if count > bytes.len() - *at {
+                return Err(format!("list of {} elements cannot fit in {} bytes", count, bytes.len() - *at));
+            }
+            let mut values = Vec::with_capacity(count);
+            for _ in 0..count {
+                values.push(decode(bytes, at, of)?);
+            }
+            Value::List(values)
+        },
+        Type::Record(_,fields) => {
+            let mut values = Vec::new();
+            for (name,ty) in fields {
+                values.push((name.clone(), decode(bytes, at, ty)?));
+            }
+            Value::Record(values)
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> Type {
+        Type::Record("rect".to_string(), vec![("x".to_string(), Type::S32), ("name".to_string(), Type::String)])
+    }
+
+    fn round_trip(value: &Value, ty: &Type) {
+        let mut bytes = Vec::new();
+        encode(value, &mut bytes);
+        let mut at = 0;
+        assert_eq!(&decode(&bytes, &mut at, ty).unwrap(), value);
+        assert_eq!(at, bytes.len());
+    }
+
+    #[test]
+    fn every_type_comes_back_as_it_went() {
+        round_trip(&Value::Bool(true), &Type::Bool);
+        round_trip(&Value::U8(200), &Type::U8);
+        round_trip(&Value::S32(-5), &Type::S32);
+        round_trip(&Value::S64(i64::MIN), &Type::S64);
+        round_trip(&Value::F32(1.5), &Type::F32);
+        round_trip(&Value::F64(-0.25), &Type::F64);
+        round_trip(&Value::String("héllo".to_string()), &Type::String);
+        round_trip(&Value::List(vec![]), &Type::List(Box::new(Type::U8)));
+        let rect = Value::Record(vec![("x".to_string(), Value::S32(7)), ("name".to_string(), Value::String("a".to_string()))]);
+        round_trip(&Value::List(vec![rect.clone(), rect]), &Type::List(Box::new(record())));
+
+        // packed and little endian, with counts in front
+        let mut bytes = Vec::new();
+        encode(&Value::List(vec![Value::String("ab".to_string())]), &mut bytes);
+        assert_eq!(bytes, vec![1, 0, 0, 0, 2, 0, 0, 0, b'a', b'b']);
+    }
+
+    #[test]
+    fn short_or_lying_input_is_refused() {
+        let decoded = |bytes: &[u8], ty: &Type| decode(bytes, &mut 0, ty);
+        assert_eq!(decoded(&[1, 2, 3], &Type::S32), Err("ran out of bytes at 0".to_string()));
+        assert!(decoded(&[], &Type::Bool).is_err());
+        assert!(decoded(&[1, 2, 3, 4, 5, 6, 7], &Type::F64).is_err());
+        // a string longer than what is left, and one that is not utf8
+        assert!(decoded(&[5, 0, 0, 0, b'a'], &Type::String).is_err());
+        assert!(decoded(&[0xff, 0xff, 0xff, 0xff, b'a'], &Type::String).is_err());
+        assert_eq!(decoded(&[1, 0, 0, 0, 0xff], &Type::String), Err("string is not utf8".to_string()));
+        // a list that says it has more than could fit is refused before anything is made for it
+        assert_eq!(decoded(&[0xff, 0xff, 0xff, 0xff, 1, 2], &Type::List(Box::new(Type::U8))),
+            Err("list of 4294967295 elements cannot fit in 2 bytes".to_string()));
+        // and one that could fit but runs short part way through
+        assert!(decoded(&[3, 0, 0, 0, 1, 0, 0, 0, 2], &Type::List(Box::new(Type::S32))).is_err());
+        // a record missing its last field
+        assert!(decoded(&[7, 0, 0, 0], &record()).is_err());
+    }
+
+    #[test]
+    fn call_ids_wrap_around_and_skip_those_waiting() {
+        let manifest = Manifest::parse("import.other.f = () -> s32").unwrap();
+        let mut calls = Calls::new("app", Interface::from_manifest(&manifest).unwrap(), 1);
+        let (send, recv) = unbounded::<Message>();
+        assert_eq!(calls.call(&send, "other.f", &[]), Ok(1));
+        calls.next = u32::MAX;
+        assert_eq!(calls.call(&send, "other.f", &[]), Ok(-1));
+        // 0 is never an id and 1 is still waiting
+        assert_eq!(calls.call(&send, "other.f", &[]), Ok(2));
+        assert_eq!(recv.try_iter().count(), 3);
+        assert!(calls.call(&send, "other.f", &[0]).is_err());
+        assert!(calls.call(&send, "nobody.f", &[]).is_err());
+    }
+}
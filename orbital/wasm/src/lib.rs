@@ -1,38 +1,59 @@
 
 use crossbeam::channel::*;
+use std::cell::RefCell;
 use std::error::Error;
-use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use service::*;
 
-//////////////////////////////////////////////////////////////////////////
-
-#[derive(Debug)]
-struct MyError(String);
+mod cache;
+mod host;
+mod interface;
+mod limits;
+#[cfg(feature = "wasi")]
+mod wasi;
 
-impl fmt::Display for MyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "There is an error: {}", self.0)
-    }
-}
-
-impl Error for MyError {}
+pub use host::{check, ImportError};
+pub use limits::Limits;
 
 //////////////////////////////////////////////////////////////////////////
 
+// how often a guest that exports tick(elapsed_ms) is woken up when there is no traffic for it
+const TICK: Duration = Duration::from_millis(33);
+
 #[derive(Clone)]
-pub struct Wasm {}
+pub struct Wasm {
+    module: String,
+    tick: Duration,
+    limits: Limits,
+}
 impl Wasm {
     pub fn new() -> Box<dyn Serviceable> {
-        Box::new(Self{})
+        Self::with_module("../public/cubes.wasm")
+    }
+    pub fn with_module(module: &str) -> Box<dyn Serviceable> {
+        Box::new(Self{ module: module.to_string(), tick: TICK, limits: Limits::default() })
+    }
+    pub fn with_tick(module: &str, tick: Duration) -> Box<dyn Serviceable> {
+        Box::new(Self{ module: module.to_string(), tick: tick, limits: Limits::default() })
+    }
+    pub fn with_limits(module: &str, limits: Limits) -> Box<dyn Serviceable> {
+        Box::new(Self{ module: module.to_string(), tick: TICK, limits: limits })
     }
 }
 impl Serviceable for Wasm {
     fn name(&self) -> &str { "Wasm" }
     fn stop(&self) {}
-    fn start(&self, name: String, _sid: SID, send: Sender<Message>, recv: Receiver<Message> ) {
+    fn start(&self, name: String, sid: SID, send: Sender<Message>, recv: Receiver<Message> ) {
+        let module = self.module.clone();
+        let tick = self.tick;
+        let limits = self.limits;
         let _thread = std::thread::Builder::new().name(name.to_string()).spawn(move || {
-           let _ = wasm2(name,send,recv);
+            if let Err(err) = wasm2(&module,tick,limits,sid,send.clone(),recv) {
+                log(&send,format!("Wasm: {} failed: {}",module,err));
+            }
         });
     }
 }
@@ -41,61 +62,149 @@ impl Serviceable for Wasm {
 
 use wasmtime::*;
 use anyhow::Result;
-//use wasmtime_wasi::{sync::WasiCtxBuilder, Wasi};
 
 
-fn wasm2(name: String, _send: Sender<Message>,_recv:Receiver<Message>) -> Result<(), Box<dyn Error>> {
+fn wasm2(path: &str, tick: Duration, limits: Limits, sid: SID, send: Sender<Message>, recv: Receiver<Message>) -> Result<(), Box<dyn Error>> {
 
-    let send2 = _send.clone();
+    // the manifest sits beside the module, ie cubes.wasm and cubes.manifest; an app without one gets the defaults
+    let manifest = Manifest::load(&Path::new(path).with_extension("manifest").to_string_lossy()).unwrap_or_default();
+    let limits = limits.with_manifest(&manifest);
 
     // start engine once
     println!("Initializing...");
-    let engine = Engine::default();
-    let store = Store::new(&engine);
+    let mut config = Config::new();
+    limits::configure(&mut config);
+    let engine = Engine::new(&config)?;
+    let (store, budget) = limits.store(&engine);
 
-    // compile code once
+    // compile code once - either the binary or the text format will do - or better yet pick it up already compiled
     println!("Compiling module...");
-    let module = Module::from_file(&engine,name)?;
-
-    // attach callbacks
-
-    let orbital_dowork_func = move |_a:i32, _b:i32 | {
-        println!("wasm::orbital::dowork called");
-        //let _ = send2.send(Message::Event("/camera".to_string(),"WASM->Camera: Give me a Frame".to_string()));
-        //let _ = send2.send(Message::Event("/display".to_string(),"WASM->Display: Show Frame".to_string()));
-        let _ = send2.send(Message::Event("/display".to_string(),"manycubes".to_string()));
-    };
+    let module = cache::load(&engine,&config,path)?;
 
-    let orbital_dowork_func = Func::wrap(&store,orbital_dowork_func);
-
-    /*
-    let drawcube_type = FuncType::new([ValType::I32, ValType::I32].iter().cloned(),[].iter().cloned());
-    let drawcube_func = |args1: i32, args2 :i32 , _results :i32| {
-        println!("Calling back...");
-        println!("... {} {}", args[0].unwrap_i32(), args[1].unwrap_i32());
-        //let _ = send2.send(Message::Event("/display".to_string(),"cubes".to_string()));
-        Ok(())
-    };
-    */
-    //let add = Func::wrap(&store, |a: i32, b: i32| -> i32 { a + b });
-    //let double = Func::new(&store, double_type, |_, params, results| {
-    //let drawcube_func = Func::new(&store, drawcube_type, drawcube_func);
+    // catch a module asking for things the host does not have before instantiating it, with a line for each
+    let wasi = manifest.get_or("wasi",false);
+    if wasi && !cfg!(feature = "wasi") {
+        return Err("the app asks for wasi, which this build does not have (build the wasm crate with --features wasi)".into());
+    }
+    host::check(&module,wasi)?;
+
+    // the functions the app offers to others and wants from others - see interface.rs
+    let app = manifest.get("name").map(|name| name.to_string())
+        .unwrap_or_else(|| Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("app").to_string());
+    let interface = service::interface::Interface::from_manifest(&manifest).map_err(|err| format!("bad interface in manifest: {}",err))?;
+    let calls = Rc::new(RefCell::new(interface::Calls::new(&app,interface,sid)));
+
+    // attach the host abi - see host.rs - and wasi if the app asks for it - see wasi.rs
+    // the app is known to the scene, display and audio by its module path, as scripts are by theirs - not by anything
+    // the app says about itself, so a manifest cannot name it the desktop
+    let mut linker = Linker::new(&store);
+    host::link(&mut linker,sid,path,send.clone(),calls.clone())?;
+    #[cfg(feature = "wasi")]
+    if wasi {
+        wasi::link(&mut linker,&store,path,&manifest,&send)?;
+    }
 
-    // Instantiate.
+    // Instantiate - this one instance lives as long as the service does, so guest state carries over from call to call
+    // start functions run here too so they get a budget like any other call
     println!("Instantiating module...");
-    let instance = Instance::new(&store, &module, &[orbital_dowork_func.into()])?;
+    budget.refuel();
+    let instance = match linker.instantiate(&module) {
+        Ok(instance) => instance,
+        Err(err) => match budget.exceeded() {
+            Some(reason) => { terminated(&send,path,&reason); return Ok(()) },
+            None => return Err(err.into()),
+        }
+    };
 
-    // Extract exports.
-    println!("Extracting export...");
-    let run = instance.get_typed_func::<(), ()>("run")?;
+    for topic in calls.borrow().topics() {
+        let _ = send.send(Message::Subscribe(sid,topic));
+    }
 
-    // Call `$g`.
-    println!("Calling run");
-    run.call(())?;
+    // let the guest set itself up - typically this is where it subscribes to things
+    if let Ok(run) = instance.get_typed_func::<(), ()>("run") {
+        println!("Calling run");
+        budget.refuel();
+        if let Err(trap) = run.call(()) {
+            match budget.exceeded() {
+                Some(reason) => { terminated(&send,path,&reason); return Ok(()) },
+                None => return Err(trap.into()),
+            }
+        }
+    }
 
-    println!("Printing result...");
+    // an ordinary wasi program just runs main - exiting from it is not a failure, though a non zero status is worth a mention
+    if let Ok(start) = instance.get_typed_func::<(), ()>("_start") {
+        println!("Calling _start");
+        budget.refuel();
+        if let Err(trap) = start.call(()) {
+            match (trap.i32_exit_status(), budget.exceeded()) {
+                (Some(0), _) => { },
+                (Some(status), _) => log(&send,format!("Wasm: {} exited with status {}",path,status)),
+                (None, Some(reason)) => { terminated(&send,path,&reason); return Ok(()) },
+                (None, None) => return Err(trap.into()),
+            }
+        }
+    }
 
+    // hand the guest its traffic, and if it wants time based work then wake it up every so often as well
+    // a trap inside a handler is reported but the guest is kept - it is up to the guest to cope with what it dropped
+    // a guest that goes over one of its limits is stopped though; it has shown it cannot be trusted with the thread
+    let ticker = instance.get_typed_func::<i32, ()>("tick").ok();
+    let mut last = Instant::now();
+    loop {
+        let message = match ticker {
+            Some(_) => match recv.recv_timeout(tick.saturating_sub(last.elapsed())) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match recv.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        // calls and answers to calls are taken out of the traffic before the rest goes to on_message
+        if let Some(Message::Event(topic,data)) = message {
+            budget.refuel();
+            let delivered = interface::receive(&instance,&calls,&topic,&data)
+                .and_then(|taken| if taken { Ok(true) } else { interface::serve(&instance,&calls,&send,&topic,&data) })
+                .and_then(|taken| if taken { Ok(()) } else { host::deliver(&instance,&topic,&data) });
+            if let Err(trap) = delivered {
+                match budget.exceeded() {
+                    Some(reason) => { terminated(&send,path,&reason); return Ok(()) },
+                    None => log(&send,format!("Wasm: {} trapped in on_message: {}",path,trap)),
+                }
+            }
+        }
+
+        if let Some(ticker) = &ticker {
+            if last.elapsed() >= tick {
+                let elapsed = last.elapsed().as_millis() as i32;
+                last = Instant::now();
+                budget.refuel();
+                if let Err(trap) = ticker.call(elapsed) {
+                    match budget.exceeded() {
+                        Some(reason) => { terminated(&send,path,&reason); return Ok(()) },
+                        None => log(&send,format!("Wasm: {} trapped in tick: {}",path,trap)),
+                    }
+                }
+            }
+        }
+    }
 
     Ok(())
 }
 
+// say why a guest was stopped - the same topic scripting uses for runaway scripts
+fn terminated(send: &Sender<Message>, path: &str, reason: &str) {
+    let text = format!("Wasm: {} terminated: {}",path,reason);
+    println!("{}",text);
+    let _ = send.send(Message::Event("/diagnostics".to_string(),text));
+}
+
+// publish a guest failure for the desktop to show
+fn log(send: &Sender<Message>, text: String) {
+    println!("{}",text);
+    let _ = send.send(Message::Event("/log".to_string(),text));
+}
@@ -0,0 +1,123 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// Execution limits for wasm guests
+//
+// every call into a guest - run, _start, on_message, tick - gets a fresh allowance of fuel, which is roughly one unit per
+// instruction. a guest that spins forever runs dry and traps instead of pinning its thread. linear memory and tables are
+// capped for the life of the instance; a guest asking to grow past a cap is refused, which well behaved guests see as
+// memory.grow or table.grow returning -1 and most guests turn into a trap of their own.
+//
+// a trap that was caused by a limit is told apart from any other trap so the service can say why the guest was stopped.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasmtime::*;
+
+use service::*;
+
+const PAGE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    // fuel handed to each call into the guest
+    pub fuel: u64,
+    // largest linear memory in bytes
+    pub memory: usize,
+    // largest number of elements in any one table
+    pub tables: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { fuel: 1_000_000_000, memory: 64 * 1024 * 1024, tables: 10_000 }
+    }
+}
+
+impl Limits {
+    /// These limits with any "fuel", "memory" or "tables" settings from an app manifest on top
+    pub fn with_manifest(self, manifest: &Manifest) -> Limits {
+        Limits {
+            fuel: manifest.get_or("fuel", self.fuel),
+            memory: manifest.get_or("memory", self.memory),
+            tables: manifest.get_or("tables", self.tables),
+        }
+    }
+
+    /// A store that enforces these limits; fuel also has to be turned on in the engine config
+    pub fn store(&self, engine: &Engine) -> (Store, Budget) {
+        let tripped = Rc::new(RefCell::new(None));
+        let store = Store::new_with_limits(engine, Limiter { limits: *self, tripped: tripped.clone() });
+        let budget = Budget { store: store.clone(), fuel: self.fuel, added: Cell::new(0), tripped: tripped };
+        (store, budget)
+    }
+}
+
+/// Turn fuel metering on in an engine config
+pub fn configure(config: &mut Config) {
+    config.consume_fuel(true);
+}
+
+///
+/// Limiter: refuses growth past the caps and remembers that it did
+///
+
+struct Limiter {
+    limits: Limits,
+    tripped: Rc<RefCell<Option<String>>>,
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(&self, _current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        let ok = desired as usize * PAGE <= self.limits.memory;
+        if !ok {
+            *self.tripped.borrow_mut() = Some(format!("memory limit of {} bytes exceeded asking for {} bytes", self.limits.memory, desired as usize * PAGE));
+        }
+        ok
+    }
+    fn table_growing(&self, _current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        let ok = desired <= self.limits.tables;
+        if !ok {
+            *self.tripped.borrow_mut() = Some(format!("table limit of {} elements exceeded asking for {}", self.limits.tables, desired));
+        }
+        ok
+    }
+}
+
+///
+/// Budget: tops up fuel before each call and works out whether a trap was the guest going over a limit
+///
+
+pub struct Budget {
+    store: Store,
+    fuel: u64,
+    added: Cell<u64>,
+    tripped: Rc<RefCell<Option<String>>>,
+}
+
+impl Budget {
+
+    /// Call before entering the guest
+    pub fn refuel(&self) {
+        let consumed = self.store.fuel_consumed().unwrap_or(0);
+        let remaining = self.added.get().saturating_sub(consumed);
+        if remaining < self.fuel {
+            let _ = self.store.add_fuel(self.fuel - remaining);
+            self.added.set(self.added.get() + self.fuel - remaining);
+        }
+        *self.tripped.borrow_mut() = None;
+    }
+
+    /// If the guest trapped because of a limit, say which one
+    pub fn exceeded(&self) -> Option<String> {
+        if let Some(reason) = self.tripped.borrow_mut().take() {
+            return Some(reason);
+        }
+        match self.store.fuel_consumed() {
+            Some(consumed) if consumed >= self.added.get() => Some(format!("ran out of fuel after {} units in one call", self.fuel)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+
+//////////////////////////////////////////////////////////////////////////
+//
+// An optional wasi environment for wasm apps
+//
+// an app gets wasi only if its manifest asks for it with "wasi = true". what it gets is deliberately small:
+//
+//     stdout and stderr        - each line is published on /log, tagged with the app
+//     args                     - "args = ..." split on whitespace, after the module path as argv[0]
+//     env                      - every "env.NAME = value"
+//     a private directory      - "dir = ..." or by default ../public/data/<app>, created if need be and preopened as "."
+//
+// the app has no other filesystem access and stdin is empty. this is only built with the wasi feature of this crate;
+// without it an app that asks for wasi is refused.
+//
+//////////////////////////////////////////////////////////////////////////
+
+use crossbeam::channel::*;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+use wasi_common::pipe::WritePipe;
+use wasmtime::*;
+use wasmtime_wasi::sync::{Dir, WasiCtxBuilder};
+use wasmtime_wasi::Wasi;
+
+use service::*;
+
+/// Where an app keeps its files unless its manifest says otherwise
+pub fn private_dir(path: &str, manifest: &Manifest) -> String {
+    match manifest.get("dir") {
+        Some(dir) => dir.to_string(),
+        None => {
+            let app = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("app");
+            format!("../public/data/{}",app)
+        }
+    }
+}
+
+/// Define wasi in a linker for the app at path, set up as its manifest describes
+pub fn link(linker: &mut Linker, store: &Store, path: &str, manifest: &Manifest, send: &Sender<Message>) -> Result<(), Box<dyn Error>> {
+
+    let mut args = vec![path.to_string()];
+    args.extend(manifest.get("args").unwrap_or("").split_whitespace().map(|arg| arg.to_string()));
+    let env = manifest.prefixed("env");
+
+    let dir = private_dir(path, manifest);
+    std::fs::create_dir_all(&dir)?;
+    // opening the directory is the one place ambient authority is used; everything the app reaches goes through this handle
+    let preopen = unsafe { Dir::open_ambient_dir(&dir)? };
+
+    let ctx = WasiCtxBuilder::new()
+        .args(&args)?
+        .envs(&env)?
+        .stdout(Box::new(WritePipe::new(LogWriter::new(path, "stdout", send))))
+        .stderr(Box::new(WritePipe::new(LogWriter::new(path, "stderr", send))))
+        .preopened_dir(preopen, ".")?
+        .build()?;
+
+    Wasi::new(store, ctx).add_to_linker(linker)?;
+    Ok(())
+}
+
+///
+/// LogWriter: turns what an app writes to a stream into one /log message per line
+///
+
+struct LogWriter {
+    prefix: String,
+    send: Sender<Message>,
+    line: Vec<u8>,
+}
+
+impl LogWriter {
+    fn new(path: &str, stream: &str, send: &Sender<Message>) -> LogWriter {
+        LogWriter { prefix: format!("Wasm: {} {}",path,stream), send: send.clone(), line: Vec::new() }
+    }
+    fn publish(&mut self) {
+        let text = String::from_utf8_lossy(&self.line).trim_end().to_string();
+        self.line.clear();
+        let _ = self.send.send(Message::Event("/log".to_string(),format!("{}: {}",self.prefix,text)));
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for byte in bytes {
+            if *byte == b'\n' {
+                self.publish();
+            } else {
+                self.line.push(*byte);
+            }
+        }
+        Ok(bytes.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.publish();
+        }
+        Ok(())
+    }
+}
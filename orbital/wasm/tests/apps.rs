@@ -0,0 +1,89 @@
+
+// the wasm apps that ship in public, which are kept as text beside what the desktop boots
+
+use std::path::Path;
+
+#[test]
+fn cubes_is_built_from_its_source() {
+    let public = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../public");
+    let built = wat::parse_file(public.join("cubes.wat")).unwrap();
+    assert!(std::fs::read(public.join("cubes.wasm")).unwrap() == built, "cubes.wasm is not cubes.wat - see the top of cubes.wat");
+}
+
+#[test]
+fn every_app_imports_only_what_the_host_has() {
+    let public = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../public");
+    let engine = wasmtime::Engine::default();
+    let mut checked = 0;
+    for entry in std::fs::read_dir(&public).unwrap() {
+        let path = entry.unwrap().path();
+        if !matches!(path.extension().and_then(|extension| extension.to_str()), Some("wat") | Some("wasm")) {
+            continue;
+        }
+        let module = wasmtime::Module::from_file(&engine, &path).unwrap();
+        if let Err(err) = wasm::check(&module, false) {
+            panic!("{}: {}", path.display(), err);
+        }
+        checked += 1;
+    }
+    assert!(checked >= 4);
+}
+
+#[test]
+fn the_small_apps_ask_for_cubes() {
+    use broker::*;
+    use crossbeam::channel::*;
+    use service::*;
+    use std::time::Duration;
+
+    let public = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../public");
+    for (sid,app) in [(2,"friendfinder.wat"),(3,"multi.wat")].iter() {
+        let (brokersend,brokerrecv) = unbounded::<Message>();
+        Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+        let (probesend,proberecv) = unbounded::<Message>();
+        brokersend.send(Message::Channel(1,"probe".to_string(),probesend)).unwrap();
+        brokersend.send(Message::Subscribe(1,"/display".to_string())).unwrap();
+
+        let path = public.join(app).to_string_lossy().to_string();
+        let (localsend,localrecv) = unbounded::<Message>();
+        brokersend.send(Message::Channel(*sid,app.to_string(),localsend)).unwrap();
+        wasm::Wasm::with_module(&path).start(app.to_string(),*sid,brokersend.clone(),localrecv);
+
+        match proberecv.recv_timeout(Duration::from_secs(30)) {
+            Ok(Message::Event(topic,data)) => assert_eq!((topic,data), ("/display".to_string(), format!("manycubes @{}",path))),
+            other => panic!("{} sent {:?}", app, other.map(|_| "something else")),
+        }
+    }
+}
+
+#[test]
+fn cubes_makes_room_for_big_messages() {
+    use broker::*;
+    use crossbeam::channel::*;
+    use service::*;
+    use std::time::Duration;
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(1,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(1,"/display".to_string())).unwrap();
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../public/cubes.wasm").to_string_lossy().to_string();
+    let (localsend,localrecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(2,"cubes".to_string(),localsend)).unwrap();
+    wasm::Wasm::with_module(&path).start("cubes".to_string(),2,brokersend.clone(),localrecv);
+    let next = || match proberecv.recv_timeout(Duration::from_secs(30)) {
+        Ok(Message::Event(topic,data)) => (topic,data),
+        other => panic!("cubes sent {:?}", other.map(|_| "something else")),
+    };
+    assert_eq!(next().1, format!("manycubes @{}",path));
+
+    // more than the memory cubes starts with has free, and sent a few times so the space has to be given back
+    let big = "x".repeat(512 * 1024);
+    for _ in 0..4 {
+        brokersend.send(Message::Event("/cubes".to_string(),big.clone())).unwrap();
+        let (topic,data) = next();
+        assert!(topic == "/display" && data == format!("{} @{}",big,path), "cubes sent {} bytes on {}", data.len(), topic);
+    }
+}
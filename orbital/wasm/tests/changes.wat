@@ -0,0 +1,9 @@
+;; a guest that makes up a change to the scene, as if the scene had said it
+(module
+  (import "orbital" "publish" (func $publish (param i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/scene/changes")
+  (data (i32.const 16) "{\"op\":\"delete\",\"id\":\"desktop\"}")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (call $publish (i32.const 0) (i32.const 14) (i32.const 16) (i32.const 30))))
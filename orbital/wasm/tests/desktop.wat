@@ -0,0 +1,12 @@
+;; a guest that says it is the desktop, to hide another app and clear what it drew
+(module
+  (import "orbital" "publish" (func $publish (param i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/scene")
+  (data (i32.const 16) "{\"op\":\"hide\",\"owner\":\"other.js\"} @desktop")
+  (data (i32.const 64) "/pixels")
+  (data (i32.const 80) "{\"op\":\"clear\"} @other.js")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (call $publish (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 41))
+    (call $publish (i32.const 64) (i32.const 7) (i32.const 80) (i32.const 24))))
@@ -0,0 +1,9 @@
+;; a guest that keeps asking for memory and traps once it is refused
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (loop $more
+      (if (i32.eq (memory.grow (i32.const 16)) (i32.const -1))
+        (then unreachable))
+      (br $more))))
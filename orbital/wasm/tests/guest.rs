@@ -0,0 +1,73 @@
+
+// the guest sdk example app, built for wasm32 and run under the Wasm service
+// needs the wasm32 target: rustup target add wasm32-unknown-unknown - without it the test says so and passes
+
+use crossbeam::channel::*;
+use std::process::Command;
+use std::time::Duration;
+
+use broker::*;
+use service::*;
+use wasm::*;
+
+const PROBE: SID = 1;
+const GUEST: SID = 2;
+
+// whether rustup says the wasm32 target is there to build for
+fn have_target() -> bool {
+    match Command::new("rustup").args(&["target", "list", "--installed"]).output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).lines().any(|target| target.trim() == "wasm32-unknown-unknown"),
+        Err(_) => false,
+    }
+}
+
+fn build_example() -> String {
+    let status = Command::new(env!("CARGO"))
+        .args(&["build", "--release", "--example", "hello", "--target", "wasm32-unknown-unknown", "--manifest-path", "../guest/Cargo.toml"])
+        .status()
+        .expect("could not run cargo");
+    assert!(status.success(), "could not build the guest example - is the wasm32-unknown-unknown target installed?");
+    "../guest/target/wasm32-unknown-unknown/release/examples/hello.wasm".to_string()
+}
+
+fn next_event(recv: &Receiver<Message>) -> (String,String) {
+    loop {
+        match recv.recv_timeout(Duration::from_secs(30)).expect("no message from guest") {
+            Message::Event(topic,data) => return (topic,data),
+            _ => { },
+        }
+    }
+}
+
+#[test]
+fn example_app_runs_under_the_wasm_service() {
+    if !have_target() {
+        println!("skipping the guest example: the wasm32-unknown-unknown target is not installed (rustup target add wasm32-unknown-unknown)");
+        return;
+    }
+    let module = build_example();
+
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/display".to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/hello/reply".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_module(&module);
+    brokersend.send(Message::Channel(GUEST,"hello".to_string(),localsend)).unwrap();
+    instance.start("hello".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    // run builds a scene
+    for expected in &["camera", "light", "cube"] {
+        assert_eq!(next_event(&proberecv), ("/display".to_string(), format!("{} @{}", expected, module)));
+    }
+
+    // and the app answers on /hello, keeping count across messages
+    brokersend.send(Message::Event("/hello".to_string(),"there".to_string())).unwrap();
+    assert_eq!(next_event(&proberecv), ("/hello/reply".to_string(), "hello there (1)".to_string()));
+    brokersend.send(Message::Event("/hello".to_string(),"again".to_string())).unwrap();
+    assert_eq!(next_event(&proberecv), ("/hello/reply".to_string(), "hello again (2)".to_string()));
+}
@@ -0,0 +1,42 @@
+// modules are checked against the host abi before they are instantiated, with a line for each import that is wrong
+
+use wasmtime::*;
+
+fn check(wat: &str, wasi: bool) -> Result<(), String> {
+    let module = Module::new(&Engine::default(), wat).unwrap();
+    wasm::check(&module, wasi).map_err(|err| err.to_string())
+}
+
+#[test]
+fn a_module_importing_what_the_host_has_passes() {
+    let good = r#"(module
+        (import "orbital" "publish" (func (param i32 i32 i32 i32)))
+        (import "orbital" "subscribe" (func (param i32 i32)))
+        (import "orbital" "call" (func (param i32 i32 i32 i32) (result i32)))
+        (import "orbital" "result" (func (param i32 i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32))))"#;
+    assert_eq!(check(good, true), Ok(()));
+    assert_eq!(check("(module)", false), Ok(()));
+}
+
+#[test]
+fn a_module_importing_the_wrong_things_is_told_what() {
+    let bad = r#"(module
+        (import "orbital" "drawcube" (func (param i32 i32)))
+        (import "orbital" "publish" (func (param i32 i32)))
+        (import "orbital" "subscribe" (memory 1))
+        (import "imports" "drawcube" (func (param i32 i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32))))"#;
+    assert_eq!(check(bad, false), Err([
+        "module has 5 bad import(s):",
+        "    orbital.drawcube: not provided by the host, which has publish, subscribe, call, result",
+        "    orbital.publish: host has (i32, i32, i32, i32) -> () but module wants (i32, i32) -> ()",
+        "    orbital.subscribe: host provides a function here",
+        "    imports.drawcube: unknown module 'imports', host functions are imported from 'orbital'",
+        "    wasi_snapshot_preview1.fd_write: wasi is not enabled for this app (set wasi = true in its manifest)",
+    ].join("\n")));
+
+    // a result the host does not give back is as wrong as a missing parameter
+    let bad = r#"(module (import "orbital" "call" (func (param i32 i32 i32 i32))))"#;
+    assert_eq!(check(bad, false), Err("module has 1 bad import(s):\n    orbital.call: host has (i32, i32, i32, i32) -> (i32) but module wants (i32, i32, i32, i32) -> ()".to_string()));
+}
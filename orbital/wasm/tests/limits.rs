@@ -0,0 +1,89 @@
+
+// guests that go over their limits are stopped, say why on /diagnostics, and leave the broker running
+
+use crossbeam::channel::*;
+use std::time::Duration;
+
+use broker::*;
+use service::*;
+use wasm::*;
+
+const PROBE: SID = 1;
+const GUEST: SID = 2;
+
+// start a broker with a probe listening to /diagnostics and the guest under test
+fn boot(module: &str, limits: Limits) -> (Sender<Message>, Receiver<Message>) {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/diagnostics".to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/echo".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_limits(module,limits);
+    brokersend.send(Message::Channel(GUEST,"guest".to_string(),localsend)).unwrap();
+    instance.start("guest".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    (brokersend,proberecv)
+}
+
+fn diagnostic(recv: &Receiver<Message>) -> String {
+    loop {
+        match recv.recv_timeout(Duration::from_secs(30)).expect("no diagnostic from guest") {
+            Message::Event(topic,data) if topic == "/diagnostics" => return data,
+            _ => { },
+        }
+    }
+}
+
+// the broker should still be passing traffic around after a guest has been stopped
+fn assert_broker_healthy(send: &Sender<Message>, recv: &Receiver<Message>) {
+    send.send(Message::Event("/echo".to_string(),"still here".to_string())).unwrap();
+    loop {
+        match recv.recv_timeout(Duration::from_secs(5)).expect("broker stopped routing") {
+            Message::Event(topic,data) if topic == "/echo" => { assert_eq!(data,"still here"); return },
+            _ => { },
+        }
+    }
+}
+
+#[test]
+fn looping_run_runs_out_of_fuel() {
+    let limits = Limits { fuel: 1_000_000, ..Limits::default() };
+    let (send,recv) = boot("tests/loop.wat",limits);
+    let reason = diagnostic(&recv);
+    assert!(reason.contains("terminated") && reason.contains("fuel"), "{}", reason);
+    assert_broker_healthy(&send,&recv);
+}
+
+#[test]
+fn looping_handler_runs_out_of_fuel() {
+    let limits = Limits { fuel: 1_000_000, ..Limits::default() };
+    let (send,recv) = boot("tests/spin.wat",limits);
+    // give run a moment to subscribe before poking it
+    std::thread::sleep(Duration::from_millis(500));
+    send.send(Message::Event("/spin".to_string(),"go".to_string())).unwrap();
+    let reason = diagnostic(&recv);
+    assert!(reason.contains("terminated") && reason.contains("fuel"), "{}", reason);
+    assert_broker_healthy(&send,&recv);
+}
+
+#[test]
+fn memory_is_capped() {
+    let limits = Limits { memory: 4 * 1024 * 1024, ..Limits::default() };
+    let (send,recv) = boot("tests/grow.wat",limits);
+    let reason = diagnostic(&recv);
+    assert!(reason.contains("terminated") && reason.contains("memory limit"), "{}", reason);
+    assert_broker_healthy(&send,&recv);
+}
+
+#[test]
+fn tables_are_capped() {
+    let limits = Limits { tables: 1000, ..Limits::default() };
+    let (send,recv) = boot("tests/table.wat",limits);
+    let reason = diagnostic(&recv);
+    assert!(reason.contains("terminated") && reason.contains("table limit"), "{}", reason);
+    assert_broker_healthy(&send,&recv);
+}
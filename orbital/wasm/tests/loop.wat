@@ -0,0 +1,6 @@
+;; a guest that never comes back from run
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (loop $forever (br $forever))))
@@ -0,0 +1,8 @@
+;; a guest that tries to listen in on the whole scene, as renderers do
+(module
+  (import "orbital" "subscribe" (func $subscribe (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/scene/changes")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (call $subscribe (i32.const 0) (i32.const 14))))
@@ -0,0 +1,94 @@
+
+// what a guest publishes to services that act for apps is tagged by the host with the app, not by the guest, and
+// the topics services keep to themselves are refused it
+
+use crossbeam::channel::*;
+use std::time::Duration;
+
+use broker::*;
+use scene::graph::Graph;
+use scene::owners::Owners;
+use service::*;
+use wasm::*;
+
+const PROBE: SID = 1;
+const GUEST: SID = 2;
+
+#[test]
+fn guests_cannot_say_they_are_the_desktop() {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/scene".to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/pixels".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_module("tests/desktop.wat");
+    brokersend.send(Message::Channel(GUEST,"guest".to_string(),localsend)).unwrap();
+    instance.start("guest".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    let mut heard = Vec::new();
+    while heard.len() < 2 {
+        if let Message::Event(topic,data) = proberecv.recv_timeout(Duration::from_secs(30)).expect("nothing from the guest") {
+            heard.push((topic,data));
+        }
+    }
+    // the tags the guest wrote are gone, and it is known by its module instead
+    assert_eq!(heard[0], ("/scene".to_string(), r#"{"op":"hide","owner":"other.js"} @tests/desktop.wat"#.to_string()));
+    assert_eq!(heard[1], ("/pixels".to_string(), r#"{"op":"clear"} @tests/desktop.wat"#.to_string()));
+
+    // so the scene refuses it what only the desktop may do
+    let (request,owner) = service::owner(&heard[0].1);
+    let (_,result) = scene::handle(&mut Graph::new(),&mut Owners::default(),owner.unwrap(),request);
+    assert_eq!(result, Err("only the desktop can hide an app".to_string()));
+}
+
+#[test]
+fn guests_cannot_speak_for_the_scene() {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/scene/changes".to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_module("tests/changes.wat");
+    brokersend.send(Message::Channel(GUEST,"guest".to_string(),localsend)).unwrap();
+    instance.start("guest".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    // the guest is stopped and nothing reaches those who trust the scene
+    match proberecv.recv_timeout(Duration::from_secs(30)).expect("nothing from the guest") {
+        Message::Event(topic,data) => {
+            assert_eq!(topic, "/log");
+            assert!(data.contains("guests may not publish on /scene/changes"), "{}", data);
+        },
+        _ => panic!("expected a log"),
+    }
+}
+
+#[test]
+fn guests_cannot_listen_in_on_the_scene() {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_module("tests/mirror.wat");
+    brokersend.send(Message::Channel(GUEST,"guest".to_string(),localsend)).unwrap();
+    instance.start("guest".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    match proberecv.recv_timeout(Duration::from_secs(30)).expect("nothing from the guest") {
+        Message::Event(topic,data) => {
+            assert_eq!(topic, "/log");
+            assert!(data.contains("guests may not subscribe to /scene/changes"), "{}", data);
+        },
+        _ => panic!("expected a log"),
+    }
+}
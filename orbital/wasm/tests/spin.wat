@@ -0,0 +1,10 @@
+;; a guest that starts fine and then never comes back from the first message it gets
+(module
+  (import "orbital" "subscribe" (func $subscribe (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/spin")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (call $subscribe (i32.const 0) (i32.const 5)))
+  (func (export "on_message") (param i32 i32 i32 i32)
+    (loop $forever (br $forever))))
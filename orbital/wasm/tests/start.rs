@@ -0,0 +1,62 @@
+
+// guests that are ordinary programs, run once through _start rather than driven by messages
+
+use crossbeam::channel::*;
+use std::time::Duration;
+
+use broker::*;
+use service::*;
+use wasm::*;
+
+const PROBE: SID = 1;
+const GUEST: SID = 2;
+
+// start a broker with a probe listening to /log and /echo, and the guest under test
+fn boot(module: &str) -> Receiver<Message> {
+    let (brokersend,brokerrecv) = unbounded::<Message>();
+    Broker::new().start("broker".to_string(),0,brokersend.clone(),brokerrecv);
+
+    let (probesend,proberecv) = unbounded::<Message>();
+    brokersend.send(Message::Channel(PROBE,"probe".to_string(),probesend)).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/log".to_string())).unwrap();
+    brokersend.send(Message::Subscribe(PROBE,"/echo".to_string())).unwrap();
+
+    let (localsend,localrecv) = unbounded::<Message>();
+    let instance = Wasm::with_module(module);
+    brokersend.send(Message::Channel(GUEST,"guest".to_string(),localsend)).unwrap();
+    instance.start("guest".to_string(),GUEST,brokersend.clone(),localrecv);
+
+    proberecv
+}
+
+fn next_event(recv: &Receiver<Message>) -> (String,String) {
+    loop {
+        match recv.recv_timeout(Duration::from_secs(30)).expect("no message from guest") {
+            Message::Event(topic,data) => return (topic,data),
+            _ => { },
+        }
+    }
+}
+
+#[test]
+fn start_runs_once() {
+    let recv = boot("tests/start.wat");
+    assert_eq!(next_event(&recv), ("/echo".to_string(), "main ran".to_string()));
+}
+
+#[cfg(feature = "wasi")]
+#[test]
+fn wasi_programs_write_to_the_log_and_exit() {
+    let recv = boot("tests/wasi.wat");
+    assert_eq!(next_event(&recv), ("/log".to_string(), "Wasm: tests/wasi.wat stdout: from wasi".to_string()));
+    assert_eq!(next_event(&recv), ("/log".to_string(), "Wasm: tests/wasi.wat exited with status 3".to_string()));
+}
+
+#[cfg(not(feature = "wasi"))]
+#[test]
+fn wasi_programs_are_refused_without_wasi() {
+    let recv = boot("tests/wasi.wat");
+    let (topic,data) = next_event(&recv);
+    assert_eq!(topic, "/log");
+    assert!(data.starts_with("Wasm: tests/wasi.wat failed: the app asks for wasi"), "{}", data);
+}
@@ -0,0 +1,9 @@
+;; a guest that is an ordinary program - it does its work in _start and never subscribes to anything
+(module
+  (import "orbital" "publish" (func $publish (param i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/echo")
+  (data (i32.const 16) "main ran")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "_start")
+    (call $publish (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 8))))
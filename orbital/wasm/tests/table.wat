@@ -0,0 +1,10 @@
+;; a guest that keeps growing a table and traps once it is refused
+(module
+  (memory (export "memory") 1)
+  (table 1 funcref)
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "run")
+    (loop $more
+      (if (i32.eq (table.grow (ref.null func) (i32.const 100)) (i32.const -1))
+        (then unreachable))
+      (br $more))))
@@ -0,0 +1,39 @@
+;; the source of cubes.wasm - subscribes to /cubes, asks the display for many cubes, and sends on to the display
+;; whatever it is sent. public/cubes is the same app written in Rust against orbital/guest, which is where to start a
+;; real one; this stays as text so the module the desktop boots is small and can be read. orbital/wasm/tests/apps.rs
+;; checks that cubes.wasm is this assembled - after changing it, write cubes.wasm again and commit both with
+;;
+;;     cd orbital/wasm && cargo run --example assemble
+(module
+  (import "orbital" "publish" (func $publish (param i32 i32 i32 i32)))
+  (import "orbital" "subscribe" (func $subscribe (param i32 i32)))
+  (memory (export "memory") 17)
+  (global $heap (mut i32) (i32.const 1048576))
+  (data (i32.const 1024) "/cubes")
+  (data (i32.const 1040) "/display")
+  (data (i32.const 1056) "manycubes")
+  ;; space for each message is taken off the end of the heap, growing memory when it runs out, and all given back
+  ;; once the message is handled
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local $end i32)
+    (local.set $ptr (global.get $heap))
+    (local.set $end (i32.add (local.get $ptr) (local.get $len)))
+    (if (i32.lt_u (local.get $end) (local.get $ptr))
+      (then (unreachable)))
+    (if (i32.gt_u (local.get $end) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (if (i32.eq
+              (memory.grow (i32.sub (i32.shr_u (i32.add (local.get $end) (i32.const 65535)) (i32.const 16)) (memory.size)))
+              (i32.const -1))
+          (then (unreachable)))))
+    (global.set $heap (local.get $end))
+    (local.get $ptr))
+  (func (export "dealloc") (param i32 i32)
+    (global.set $heap (i32.const 1048576)))
+  (func (export "run")
+    (call $subscribe (i32.const 1024) (i32.const 6))
+    (call $publish (i32.const 1040) (i32.const 8) (i32.const 1056) (i32.const 9)))
+  (func (export "on_message") (param i32 i32 i32 i32)
+    (call $publish (i32.const 1040) (i32.const 8) (local.get 2) (local.get 3)))
+)
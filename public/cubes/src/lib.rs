@@ -1,12 +1,16 @@
 
-#[link(wasm_import_module = "imports")]
-extern "C" {
-  fn orbital_dowork(a:i32, b:i32);
-}
+// a tiny orbital app - see orbital/guest for the sdk it is written against
+
+use guest::*;
 
 #[no_mangle]
-extern "C" fn run() {
-  unsafe {
-    orbital_dowork(1,2);
-  }
+pub extern "C" fn run() {
+  subscribe("/cubes");
+  publish("/display", "manycubes");
 }
+
+fn handle(_topic: &str, payload: &str) {
+  publish("/display", payload);
+}
+
+on_message!(handle);
@@ -1,10 +1,20 @@
 (module
-  (func $drawcube (import "" "drawcube") (param i32 i32) (result i32 i32))
+  (func $publish (import "orbital" "publish") (param i32 i32 i32 i32))
 
-  (func $run (export "run") (param i32 i32) (result i32 i32)
-    (local.set 0 (i32.const -1))
-    (local.set 1 (i32.const 14))
-    (call $drawcube (local.get 0) (local.get 1))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "/display")
+  (data (i32.const 16) "manycubes")
+
+  ;; where a string is and how long, as two results at once
+  (func $string (param i32 i32) (result i32 i32)
+    (local.get 0)
+    (local.get 1)
+  )
+
+  (func $run (export "run")
+    (call $string (i32.const 0) (i32.const 8))
+    (call $string (i32.const 16) (i32.const 9))
+    (call $publish)
   )
 
-)
\ No newline at end of file
+)